@@ -14,8 +14,41 @@ uniform mat4 projection;
 uniform vec3 camera_position;
 
 uniform sampler2D computeTexture;
+uniform vec2 chunk_window_ws_min;
+uniform vec2 chunk_window_ws_size;
 
 uniform bool is_arch;
+uniform bool is_selected;
+// see `WallBuilder::tint`, hedges and fences aren't grey
+uniform vec3 wall_tint;
+
+// the arches the compute passes found, see `arch_layout_bricks.comp`
+struct ArchSegmentData {
+    vec2 start;
+    vec2 end;
+    uint shape;
+    uint voussoirs;
+    float span;
+    float rise;
+};
+
+layout (std430, binding=4) buffer arches_buffer { 
+    ArchSegmentData arches[];
+};
+
+// the first field of the dispatch command of the arch pass
+layout (std430, binding=5) buffer arch_count_buffer { 
+    uint arch_count;
+};
+
+// same ids as `ArchShape`
+const uint ARCH_ROUND = 1;
+const uint ARCH_POINTED = 2;
+const uint ARCH_SEGMENTAL = 3;
+const uint ARCH_LINTEL = 4;
+
+// walls further than this from an arch are left whole
+const float ARCH_WALL_DISTANCE = 0.5;
 
 out vec4 FragColor;  
 
@@ -158,6 +191,45 @@ float arch_function(float h) {
     //return 1.0 - pow(1.0 - h, 8.0);
 }
 
+// same as in `arch_layout_bricks.comp`
+float arch_height(ArchSegmentData arch, float x, float mask_value) {
+    float half_span = arch.span / 2.0;
+    if (abs(x) > half_span) {
+        return 0.0;
+    }
+
+    if (arch.shape == ARCH_ROUND) {
+        return arch.rise * sqrt(max(1.0 - (x * x) / (half_span * half_span), 0.0));
+    }
+    if (arch.shape == ARCH_POINTED) {
+        float radius = (half_span * half_span + arch.rise * arch.rise) / arch.span;
+        float center = radius - half_span;
+        float d = abs(x) + center;
+        return sqrt(max(radius * radius - d * d, 0.0));
+    }
+    if (arch.shape == ARCH_SEGMENTAL) {
+        if (arch.rise < 0.0001) {
+            return 0.0;
+        }
+        float radius = (half_span * half_span + arch.rise * arch.rise) / (2.0 * arch.rise);
+        return arch.rise - radius + sqrt(max(radius * radius - x * x, 0.0));
+    }
+    if (arch.shape == ARCH_LINTEL) {
+        return arch.rise;
+    }
+    return arch.rise * arch_function(mask_value);
+}
+
+// How high the wall is cut out under an arch at this point of the curve, 0 if it isn't under one
+float arch_opening_height(ArchSegmentData arch, vec3 position, float mask_value) {
+    vec2 dir = normalize(arch.end - arch.start);
+    vec2 d = position.xz - (arch.start + arch.end) / 2.0;
+    if (abs(dot(d, vec2(-dir.y, dir.x))) > ARCH_WALL_DISTANCE) {
+        return 0.0;
+    }
+    return arch_height(arch, dot(d, dir), mask_value);
+}
+
 
 const float ALPHA = 0.14;
 const float INV_ALPHA = 1.0 / ALPHA;
@@ -199,7 +271,7 @@ void main()
     float r = gaussian_rand(vec2(instance_id+4), 0);
     r = clamp(r, 0.2, 1.0);
     r = fit01(r, 0.1, 0.35);
-    vec4 output_color = vec4(vec3(r), 1.0);
+    vec4 output_color = vec4(vec3(r) * wall_tint, 1.0);
     // Port from Bevy 0.5
     vec3 N = normalize(vertex_normal_ws);
     vec3 V = normalize(camera_position - vertex_position_ws);
@@ -251,19 +323,24 @@ void main()
     output_color.rgb += (diffuse_ambient + specular_ambient) * 0.075;// * AmbientColor.xyz * occlusion;
     //output_color.rgb += emissive.rgb * output_color.a;
 
+    // selected walls are tinted
+    if (is_selected) {
+        output_color.rgb = mix(output_color.rgb, vec3(1.0, 0.6, 0.15), 0.45);
+    }
+
     // tone_mapping
     //output_color.rgb = reinhard_luminance(output_color.rgb);
 
     if (!is_arch) {
         // sample compute texture
-        // convert pos_ws to texture_uv
-        // texture is from -10.0 to 10.0 in ws
-        vec2 texture_uv = (curve_position_ws / 20.0 + 0.5).xz;
+        // convert pos_ws to texture_uv, the texture covers the chunk window
+        vec2 texture_uv = (curve_position_ws.xz - chunk_window_ws_min) / chunk_window_ws_size;
         float texture_color = texture(computeTexture, texture_uv).x; 
 
-        float height_threshold = arch_function(texture_color);
-
-        if (texture_color > 0.01 && curve_position_ws.y < height_threshold) { discard; }
+        for (uint i=0; i<arch_count; i++) {
+            float height_threshold = arch_opening_height(arches[i], curve_position_ws, texture_color);
+            if (curve_position_ws.y < height_threshold) { discard; }
+        }
     }
 
     FragColor = output_color;
@@ -16,7 +16,7 @@ struct InstancedWallData {
     vec4 curve_uv_bbx_minmax;
 };
 
-uniform float wall_length;
+uniform float wall_height;
 
 // shader storage buffer
 layout (std430, binding=2) buffer instanced_wall_data
@@ -28,10 +28,13 @@ uniform mat4 model;
 uniform mat4 view;
 uniform mat4 projection;
 
+// terrain and path textures hold the tiles of the chunks in the chunk window
+uniform vec2 chunk_window_ws_min;
+uniform vec2 chunk_window_ws_size;
 uniform sampler2D terrain_texture;
 
 float sample_terrain_texture_ws(vec2 pos_ws) {
-    vec2 texture_uv = (pos_ws / 20.0 + 0.5);
+    vec2 texture_uv = (pos_ws - chunk_window_ws_min) / chunk_window_ws_size;
     return texture(terrain_texture, texture_uv).x;
 }
 
@@ -112,7 +115,8 @@ void main()
 
         float str = fit01(random_f(r*r+88.0), 0.015, 0.045);
 
-        vec2 vertex_cs = uv_cs * wall_length;
+        // curve space X is in meters already
+        vec2 vertex_cs = uv_cs;
         float sin_wave = sin(vertex_cs.x*freq + rand_offset) * str;
 
         final_p = vec3(p.x, p.y + sin_wave, p.z);
@@ -121,13 +125,9 @@ void main()
 
     // ---------------------- TERRAIN
 
-    const float WALL_HEIGHT = 1.4;
-    float height_u = final_p.y / WALL_HEIGHT * 0.7;
-
-    vec3 terrain_p = final_p;
-    terrain_p.y += sample_terrain_texture_ws(final_p.xz);
-
-    final_p = mix(terrain_p, final_p, height_u);
+    // Bricks are placed on the ground under their pivot, shear them so the wall follows the terrain under every vertex
+    vec2 pivot_ws = (model * instance_transform * vec4(0.0, 0.0, 0.0, 1.0)).xz;
+    final_p.y += sample_terrain_texture_ws(final_p.xz) - sample_terrain_texture_ws(pivot_ws);
 
 
     // ----------------------------------
@@ -144,6 +144,8 @@ void main()
     // Curve Position in WS is required to know whether to discard brick's fragment, because SDF road-texture is going through the curve
     // you can think of curve_position_ws, as a wall with no brick depth
     curve_position_ws = (instance_transform  * vec4(vec3(Vertex_Position.xy, 0.0), 1.0)).xyz;
+    // arches are compared against the height above the ground, not the terrain height
+    curve_position_ws.y = uv_cs.y * wall_height;
     
 } 
 
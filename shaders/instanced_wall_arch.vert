@@ -21,10 +21,13 @@ layout (std430, binding=2) buffer transforms_buffer {
 uniform mat4 view;
 uniform mat4 projection;
 
+// terrain and path textures hold the tiles of the chunks in the chunk window
+uniform vec2 chunk_window_ws_min;
+uniform vec2 chunk_window_ws_size;
 uniform sampler2D terrain_texture;
 
 float sample_terrain_texture_ws(vec2 pos_ws) {
-    vec2 texture_uv = (pos_ws / 20.0 + 0.5);
+    vec2 texture_uv = (pos_ws - chunk_window_ws_min) / chunk_window_ws_size;
     return texture(terrain_texture, texture_uv).x;
 }
 
@@ -35,15 +38,8 @@ void main()
 
     // ---------------------- TERRAIN
 
-    //vertex_ws.y += sample_terrain_texture_ws(vertex_ws.xz);
-
-    const float WALL_HEIGHT = 1.4;
-    float height_u = vertex_ws.y / WALL_HEIGHT * 0.7;
-
-    vec4 terrain_p = vertex_ws;
-    terrain_p.y += sample_terrain_texture_ws(vertex_ws.xz);
-
-    vertex_ws = mix(terrain_p, vertex_ws, height_u);
+    // arches are laid out from y = 0, shear them onto the terrain like the wall bricks
+    vertex_ws.y += sample_terrain_texture_ws(vertex_ws.xz);
 
 
     // ----------------------------------
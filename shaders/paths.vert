@@ -14,16 +14,19 @@ uniform mat4 model;
 uniform mat4 view;
 uniform mat4 projection;
 
+// terrain and path textures hold the tiles of the chunks in the chunk window
+uniform vec2 chunk_window_ws_min;
+uniform vec2 chunk_window_ws_size;
 uniform sampler2D path_texture;
 uniform sampler2D terrain_texture;
 
 float sample_terrain_texture_ws(vec2 pos_ws) {
-    vec2 texture_uv = (pos_ws / 20.0 + 0.5);
+    vec2 texture_uv = (pos_ws - chunk_window_ws_min) / chunk_window_ws_size;
     return texture(terrain_texture, texture_uv).x;
 }
 
 float sample_path_texture_ws(vec2 pos_ws) {
-    vec2 texture_uv = (pos_ws / 20.0 + 0.5);
+    vec2 texture_uv = (pos_ws - chunk_window_ws_min) / chunk_window_ws_size;
     return texture(path_texture, texture_uv).x;
 }
 
@@ -37,9 +40,11 @@ float fit01(float x, float min, float max) {
 
 void main()
 {   
-    vec3 pos_ws = Vertex_Position;
-    vec2 bbx_min = bbx_bounds.xy;
-    vec2 bbx_max = bbx_bounds.zw;
+    // the pebbles cover a single chunk, `model` moves them to theirs
+    vec3 chunk_pos_ws = (model * vec4(Vertex_Position, 1.0)).xyz;
+    vec3 pos_ws = chunk_pos_ws;
+    vec2 bbx_min = bbx_bounds.xy + model[3].xz;
+    vec2 bbx_max = bbx_bounds.zw + model[3].xz;
 
 
 
@@ -73,7 +78,7 @@ void main()
     float random_color = random_f(seed+50.0);
     random_color = fit01(random_color, 0.086, 0.14);
 
-    float h = sample_terrain_texture_ws(Vertex_Position.xz) + 0.4;
+    float h = sample_terrain_texture_ws(chunk_pos_ws.xz) + 0.4;
     h = fit01(h*h * sign(h), 0.1, 3.0);
     h = clamp(h, 0.1, 0.8);
 
@@ -9,9 +9,12 @@ uniform mat4 model;
 uniform mat4 view;
 uniform mat4 projection;
 
+// terrain and path textures hold the tiles of the chunks in the chunk window
+uniform vec2 chunk_window_ws_min;
+uniform vec2 chunk_window_ws_size;
 uniform sampler2D terrain_texture;
 float sample_terrain_texture_ws(vec2 pos_ws) {
-    vec2 texture_uv = (pos_ws / 20.0 + 0.5);
+    vec2 texture_uv = (pos_ws - chunk_window_ws_min) / chunk_window_ws_size;
     return texture(terrain_texture, texture_uv).x;
 }
 
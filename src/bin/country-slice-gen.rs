@@ -0,0 +1,280 @@
+// Headless wall generator: reads polylines from JSON and writes out brick transforms (JSON) or meshes (.glb)
+//
+// Input is either a scene file saved by the app (Ctrl+S) or anything of the form
+// { "walls": [ { "points": [[x, y, z], ...], "style": { <WallStyle fields> }, "seed": 123 }, ... ] }
+// where `style` and `seed` are optional. Point y is the ground height, bricks step up and down with it
+
+use std::collections::HashMap;
+
+use country_slice::{
+    geometry::{
+        curve::{Curve, WallCurve},
+        spline_curve::{SplineCurve, SPLINE_CONTROL_POINT_SPACING},
+        wall_constructor::BrickLayout,
+        wall_junction::JunctionGraph,
+        wall_style::WallStylePresets,
+    },
+    render::mesh::Mesh,
+    utils::{
+        export_gltf::{export_walls_as_glb, GltfExportMode},
+        load_gltf::load_gltf_as_mesh_buffer,
+        scene_file::SceneWall,
+    },
+};
+use serde_json::json;
+
+// Shorter segments would make resampling allocate and walk absurd numbers of points
+const MIN_RESAMPLING: f32 = 0.01;
+
+const USAGE: &str = "usage: country-slice-gen <input.json> <output.json|output.glb> [options]
+
+options:
+    --smooth <steps>       smooth the input polylines, same as the wall brush does (default: 0)
+    --resample <length>    resample the polylines to this segment length, at least 0.01 (default: 0.2)
+    --spline               fit a Catmull-Rom spline through the input polylines instead of smoothing them
+    --brick-mesh <path>    brick mesh to instance in .glb output (default: meshes/brick.glb)
+    --merged               bake all bricks into a single mesh in .glb output
+    --style <name>         use this style preset for all walls, instead of the styles stored in the input
+    --style-presets <path> where to look up --style (default: presets/wall_styles.json)
+    --no-junctions         don't join walls that end on another wall, let them overlap instead";
+
+struct Args {
+    input: String,
+    output: String,
+    smoothing_steps: usize,
+    resampling: f32,
+    spline: bool,
+    brick_mesh: String,
+    merged: bool,
+    style: Option<String>,
+    style_presets: String,
+    junctions: bool,
+}
+
+impl Args {
+    fn parse() -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut args = Args {
+            input: String::new(),
+            output: String::new(),
+            smoothing_steps: 0,
+            resampling: 0.2,
+            spline: false,
+            brick_mesh: String::from("meshes/brick.glb"),
+            merged: false,
+            style: None,
+            style_presets: String::from("presets/wall_styles.json"),
+            junctions: true,
+        };
+
+        let mut it = std::env::args().skip(1);
+        while let Some(arg) = it.next() {
+            match arg.as_str() {
+                "--smooth" => args.smoothing_steps = parse_value(&arg, it.next())?,
+                "--resample" => {
+                    args.resampling = parse_value(&arg, it.next())?;
+                    if !args.resampling.is_finite() || args.resampling < MIN_RESAMPLING {
+                        return Err(format!(
+                            "--resample expects a length of at least {}",
+                            MIN_RESAMPLING
+                        ));
+                    }
+                }
+                "--brick-mesh" => {
+                    args.brick_mesh = it.next().ok_or("--brick-mesh expects a path")?
+                }
+                "--spline" => args.spline = true,
+                "--merged" => args.merged = true,
+                "--style" => args.style = Some(it.next().ok_or("--style expects a name")?),
+                "--style-presets" => {
+                    args.style_presets = it.next().ok_or("--style-presets expects a path")?
+                }
+                "--no-junctions" => args.junctions = false,
+                "-h" | "--help" => return Err(String::new()),
+                _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+                _ => positional.push(arg),
+            }
+        }
+
+        match positional.as_slice() {
+            [input, output] => {
+                args.input = input.clone();
+                args.output = output.clone();
+                Ok(args)
+            }
+            _ => Err(String::from("expected an input and an output path")),
+        }
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(name: &str, value: Option<String>) -> Result<T, String> {
+    value
+        .and_then(|v| v.parse().ok())
+        .ok_or(format!("{} expects a number", name))
+}
+
+fn main() {
+    simple_logger::SimpleLogger::new().init().unwrap();
+
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(err) => {
+            if !err.is_empty() {
+                eprintln!("error: {}\n", err);
+            }
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(err) = run(&args) {
+        log::error!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let data = std::fs::read_to_string(&args.input)
+        .map_err(|err| format!("couldn't read {}: {}", args.input, err))?;
+    let v: serde_json::Value = serde_json::from_str(&data)
+        .map_err(|err| format!("couldn't parse {}: {}", args.input, err))?;
+    let input_walls = SceneWall::list_from_json(&v["walls"])?;
+
+    let style_override = match &args.style {
+        Some(name) => Some(
+            *WallStylePresets::load(&args.style_presets)?
+                .get(name)
+                .ok_or(format!("no style '{}' in {}", name, args.style_presets))?,
+        ),
+        None => None,
+    };
+
+    let mut curves = Vec::new();
+    for (i, wall) in input_walls.into_iter().enumerate() {
+        let curve = Curve::from(wall.points);
+        if curve.points.len() < 2 || curve.length <= 0.0 {
+            log::warn!("Skipping wall {}: it needs at least 2 distinct points", i);
+            continue;
+        }
+
+        let style = style_override.unwrap_or(wall.style);
+        let layout = BrickLayout {
+            start_offset: wall.start_offset,
+            ..BrickLayout::new(wall.seed)
+        };
+        // walls saved as splines keep their control points
+        if wall.spline || args.spline {
+            let spline = if wall.spline {
+                SplineCurve::from(curve.points)
+            } else {
+                SplineCurve::fit(&curve.points, SPLINE_CONTROL_POINT_SPACING)
+            };
+            curves.push((
+                i,
+                spline.polyline().into_owned(),
+                Some(spline),
+                style,
+                layout,
+                wall.openings,
+            ));
+        } else {
+            let curve = curve.smooth(args.smoothing_steps).resample(args.resampling);
+            curves.push((i, curve, None, style, layout, wall.openings));
+        }
+    }
+
+    // junctions are found on the polylines, splines are close enough to them
+    let mut junctions = JunctionGraph::new();
+    if args.junctions {
+        junctions.update(
+            &curves
+                .iter()
+                .map(|(i, curve, _, style, ..)| (*i, curve, style))
+                .collect::<Vec<_>>(),
+            &curves.iter().map(|(i, ..)| *i).collect(),
+        );
+    }
+
+    let seeds: HashMap<usize, u64> = curves
+        .iter()
+        .map(|(i, _, _, _, layout, _)| (*i, layout.seed))
+        .collect();
+    let mut walls = Vec::new();
+    for (i, curve, spline, style, layout, openings) in &curves {
+        let joints = junctions.joints(*i);
+        // rows line up with the walls it interlocks with, like `WallManager::layout`
+        let layout = &BrickLayout {
+            rows_seed: seeds[&junctions.interlocked_with(*i)[0]],
+            ..*layout
+        };
+        let builder = style.construction.builder();
+        let bricks = match spline {
+            Some(spline) => builder.build(spline, style, layout, &joints, openings),
+            None => builder.build(curve, style, layout, &joints, openings),
+        };
+        walls.push((*i, bricks));
+    }
+
+    if args.output.ends_with(".glb") {
+        let mode = if args.merged {
+            GltfExportMode::Merged
+        } else {
+            GltfExportMode::Instanced
+        };
+        export_walls_as_glb(
+            &args.output,
+            &load_brick_mesh(&args.brick_mesh)?,
+            &walls,
+            &[],
+            mode,
+        )?;
+    } else {
+        let out = json!({
+            "walls": walls.iter().map(|(i, bricks)| json!({
+                "index": i,
+                "bricks": bricks.iter().map(|b| {
+                    let rotation: [f32; 4] = b.transform.rotation.into();
+                    json!({
+                        "translation": b.transform.translation.to_array(),
+                        "rotation": rotation,
+                        "scale": b.transform.scale.to_array(),
+                        "pivot_uv": b.pivot_uv.to_array(),
+                        "bounds_uv": b.bounds_uv.to_array(),
+                    })
+                }).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+        });
+
+        let data = serde_json::to_string(&out)
+            .map_err(|err| format!("couldn't serialize {}: {}", args.output, err))?;
+        std::fs::write(&args.output, data)
+            .map_err(|err| format!("couldn't write {}: {}", args.output, err))?;
+    }
+
+    log::info!(
+        "Generated {} bricks for {} walls into {}",
+        walls.iter().map(|(_, b)| b.len()).sum::<usize>(),
+        walls.len(),
+        args.output
+    );
+
+    Ok(())
+}
+
+fn load_brick_mesh(path: &str) -> Result<Mesh, String> {
+    if !std::path::Path::new(path).exists() {
+        return Err(format!("brick mesh {} doesn't exist", path));
+    }
+
+    let mesh_buffer = load_gltf_as_mesh_buffer(path);
+
+    let mut mesh = Mesh::new();
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, mesh_buffer.positions);
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, mesh_buffer.normals);
+    if !mesh_buffer.colors.is_empty() {
+        mesh.set_attribute(Mesh::ATTRIBUTE_COLOR, mesh_buffer.colors);
+    }
+    mesh.set_indices(mesh_buffer.indices);
+
+    Ok(mesh)
+}
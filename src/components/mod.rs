@@ -1,7 +1,8 @@
 pub mod drawable;
-pub mod transform;
 pub mod transient_mesh;
 
+pub use country_slice::components::transform;
+
 pub use drawable::*;
 pub use transform::*;
 pub use transient_mesh::*;
@@ -13,6 +14,9 @@ pub struct CursorRaycast(pub glam::Vec3);
 
 pub struct DisplayTestMask;
 
+// Marks the wall entity of `WallManager::selected`, which is drawn highlighted
+pub struct SelectedWall;
+
 // component
 pub struct IndirectDraw;
 
@@ -1,6 +1,6 @@
 use glam::{Mat4, Quat, Vec3};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Transform {
     pub translation: Vec3,
     pub rotation: Quat,
@@ -0,0 +1,163 @@
+use std::convert::TryFrom;
+
+use serde_json::{json, Value};
+
+// every voussoir is laid out by the same GPU invocation, so there can't be too many
+const MAX_VOUSSOIRS: u32 = 1000;
+
+// Profile of the arches laid out where a wall crosses a path, see `geometry::arches`.
+// The values are the ids the arch shaders use
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchShape {
+    // rises with the blurred path mask, so it follows the edges of the path
+    Eased = 0,
+    // half a circle, or half an ellipse if the rise isn't half the span
+    Round = 1,
+    // two arcs meeting at the crown
+    Pointed = 2,
+    // an arc of a circle larger than the span
+    Segmental = 3,
+    // a flat lintel over a rectangular opening, with a keystone in the middle
+    Lintel = 4,
+}
+
+impl ArchShape {
+    pub const ALL: [ArchShape; 5] = [
+        ArchShape::Eased,
+        ArchShape::Round,
+        ArchShape::Pointed,
+        ArchShape::Segmental,
+        ArchShape::Lintel,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArchShape::Eased => "eased",
+            ArchShape::Round => "round",
+            ArchShape::Pointed => "pointed",
+            ArchShape::Segmental => "segmental",
+            ArchShape::Lintel => "lintel",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|shape| shape.name() == name)
+            .ok_or(format!(
+                "Arch style: unknown shape `{}`, expected eased, round, pointed, segmental or lintel",
+                name
+            ))
+    }
+
+    pub fn next(&self) -> Self {
+        let i = Self::ALL.iter().position(|shape| shape == self).unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+// How the arches of a wall are built, part of its `WallStyle`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ArchStyle {
+    pub shape: ArchShape,
+    // width of the opening in meters, centered on the path. 0 spans the path and its blurred edges
+    pub span: f32,
+    // height of the crown in meters. 0 gives the shape its own, see `rise_over`
+    pub rise: f32,
+    // bricks in the arch ring. 0 fits as many as the ring is long, 0.2 m each
+    pub voussoirs: u32,
+}
+
+impl Default for ArchStyle {
+    fn default() -> Self {
+        Self {
+            shape: ArchShape::Eased,
+            span: 0.0,
+            rise: 0.0,
+            voussoirs: 0,
+        }
+    }
+}
+
+impl ArchStyle {
+    // Height of the crown of an arch over `span` meters.
+    // Pointed arches are at least as high as round ones, and segmental arches at most
+    pub fn rise_over(&self, span: f32) -> f32 {
+        let rise = if self.rise > 0.0 {
+            self.rise
+        } else {
+            match self.shape {
+                ArchShape::Eased | ArchShape::Lintel => 1.0,
+                ArchShape::Round => span / 2.0,
+                // equilateral
+                ArchShape::Pointed => span * 3.0f32.sqrt() / 2.0,
+                ArchShape::Segmental => span / 4.0,
+            }
+        };
+
+        match self.shape {
+            ArchShape::Pointed => rise.max(span / 2.0),
+            ArchShape::Segmental => rise.min(span / 2.0),
+            _ => rise,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "shape": self.shape.name(),
+            "span": self.span,
+            "rise": self.rise,
+            "voussoirs": self.voussoirs,
+        })
+    }
+
+    // Missing fields fall back to the default style
+    pub fn from_json(v: &Value) -> Result<Self, String> {
+        if !v.is_object() {
+            return Err(format!("Arch style: expected an object, got {}", v));
+        }
+
+        let mut style = Self::default();
+        if let Some(shape) = v.get("shape") {
+            style.shape = ArchShape::from_name(
+                shape
+                    .as_str()
+                    .ok_or("Arch style: `shape` is not a string")?,
+            )?;
+        }
+
+        for (name, field) in [("span", &mut style.span), ("rise", &mut style.rise)] {
+            if let Some(value) = v.get(name) {
+                *field = value
+                    .as_f64()
+                    .ok_or(format!("Arch style: `{}` is not a number", name))?
+                    as f32;
+            }
+        }
+
+        if let Some(value) = v.get("voussoirs") {
+            let voussoirs = value
+                .as_u64()
+                .ok_or("Arch style: `voussoirs` is not a positive integer")?;
+            style.voussoirs = u32::try_from(voussoirs).map_err(|_| {
+                format!(
+                    "Arch style: {} voussoirs, an arch has at most {}",
+                    voussoirs, MAX_VOUSSOIRS
+                )
+            })?;
+        }
+
+        if style.span < 0.0 || style.rise < 0.0 {
+            return Err(String::from("Arch style: span and rise can't be negative"));
+        }
+        if style.voussoirs > MAX_VOUSSOIRS {
+            return Err(format!(
+                "Arch style: {} voussoirs, an arch has at most {}",
+                style.voussoirs, MAX_VOUSSOIRS
+            ));
+        }
+
+        Ok(style)
+    }
+}
@@ -0,0 +1,601 @@
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+use super::{
+    arch_style::{ArchShape, ArchStyle},
+    curve::WallCurve,
+    path_mask::PathMaskImage,
+    wall_constructor::Brick,
+};
+use crate::components::transform::Transform;
+
+// CPU version of the arch compute passes: `arch_curve_segments.comp` finds where the walls cross a path,
+// `arch_layout_bricks.comp` lays bricks along an arch over each crossing.
+// Both are ported as literally as possible, so that the results can be compared with the GPU ones
+
+const TARGET_BRICK_WIDTH: f32 = 0.2;
+const ARCH_BRICK_HEIGHT: f32 = 0.15;
+const ARCH_BRICK_DEPTH: f32 = 0.25;
+// the keystone of a lintel sticks out above the other bricks
+const KEYSTONE_HEIGHT: f32 = 0.25;
+
+// Subdivisions of a segment in `find_t_change`, and of the arch ring in `layout_arch_bricks`
+const CLIP_SUBDIV: usize = 20;
+const PROFILE_SUBDIV: usize = 128;
+
+// An arch over the stretch of a wall that crosses a path, from `start` to `end`.
+// The style is resolved for the crossing, so `span` and `rise` are in meters
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct ArchSegmentDataSSBO {
+    pub start: [f32; 2], // x and z coordinate
+    pub end: [f32; 2],
+    pub shape: u32, // `ArchShape` id
+    pub voussoirs: u32,
+    pub span: f32,
+    pub rise: f32,
+}
+
+impl Default for ArchSegmentDataSSBO {
+    fn default() -> Self {
+        ArchSegmentDataSSBO {
+            start: [0.0, 0.0],
+            end: [0.0, -1.0],
+            shape: ArchShape::Eased as u32,
+            voussoirs: 0,
+            span: 0.0,
+            rise: 0.0,
+        }
+    }
+}
+
+impl ArchSegmentDataSSBO {
+    // Same arch, with every position and length within `tolerance`
+    pub fn abs_diff_eq(&self, other: &Self, tolerance: f32) -> bool {
+        let close = |a: f32, b: f32| (a - b).abs() <= tolerance;
+        self.shape == other.shape
+            && self.voussoirs == other.voussoirs
+            && close(self.start[0], other.start[0])
+            && close(self.start[1], other.start[1])
+            && close(self.end[0], other.end[0])
+            && close(self.end[1], other.end[1])
+            && close(self.span, other.span)
+            && close(self.rise, other.rise)
+    }
+}
+
+// What the arch passes read the path mask through
+pub trait ArchMask {
+    fn value_at(&self, position: Vec3) -> f32;
+}
+
+// The pixels as they are, for masks that are blurred already
+impl ArchMask for PathMaskImage {
+    fn value_at(&self, position: Vec3) -> f32 {
+        let (x, y) = self.pixel_coord(position);
+        self.load(x, y)
+    }
+}
+
+// The mask as the GPU passes read it, after `blur.comp`. Only the pixels that are read get blurred
+pub struct BlurredPathMask<'a>(pub &'a PathMaskImage);
+
+impl ArchMask for BlurredPathMask<'_> {
+    fn value_at(&self, position: Vec3) -> f32 {
+        let (x, y) = self.0.pixel_coord(position);
+        self.0.load_blurred(x, y)
+    }
+}
+
+// Height of an eased arch over a mask value
+pub fn arch_function(h: f32) -> f32 {
+    1.0 - (-5.0 * h).exp()
+}
+
+// Height of the arch ring `x` meters from the middle of the span, 0 outside of it.
+// `mask_value` is the path mask under that point, which only eased arches follow
+pub fn arch_height(arch: &ArchSegmentDataSSBO, x: f32, mask_value: f32) -> f32 {
+    const ROUND: u32 = ArchShape::Round as u32;
+    const POINTED: u32 = ArchShape::Pointed as u32;
+    const SEGMENTAL: u32 = ArchShape::Segmental as u32;
+    const LINTEL: u32 = ArchShape::Lintel as u32;
+
+    let half_span = arch.span / 2.0;
+    if x.abs() > half_span {
+        return 0.0;
+    }
+
+    match arch.shape {
+        ROUND => arch.rise * (1.0 - (x * x) / (half_span * half_span)).max(0.0).sqrt(),
+        POINTED => {
+            // both arcs are centered on the springing line, on the other side of the middle
+            let radius = (half_span * half_span + arch.rise * arch.rise) / arch.span;
+            let center = radius - half_span;
+            let d = x.abs() + center;
+            (radius * radius - d * d).max(0.0).sqrt()
+        }
+        SEGMENTAL => {
+            if arch.rise < 0.0001 {
+                return 0.0;
+            }
+            // the circle is centered below the springing line
+            let radius = (half_span * half_span + arch.rise * arch.rise) / (2.0 * arch.rise);
+            arch.rise - radius + (radius * radius - x * x).max(0.0).sqrt()
+        }
+        LINTEL => arch.rise,
+        _ => arch.rise * arch_function(mask_value),
+    }
+}
+
+// Arches over the stretches of the curve that cross a path, in the curve's order.
+// A crossing starts where the mask rises above 0 and ends where it's back to 0.
+// The GPU pass adds the arches of all curves in parallel, so its order is arbitrary
+pub fn arch_segments<C: WallCurve + ?Sized>(
+    curve: &C,
+    style: &ArchStyle,
+    mask: &impl ArchMask,
+) -> Vec<ArchSegmentDataSSBO> {
+    let polyline = curve.polyline();
+    let mut arches = Vec::new();
+    let mut crossing_start = None;
+
+    for s in polyline.points.windows(2) {
+        let (p1, p2) = (s[0], s[1]);
+        let val_1 = mask.value_at(p1);
+        let val_2 = mask.value_at(p2);
+
+        if val_1 > 0.0 || val_2 > 0.0 {
+            let start = *crossing_start.get_or_insert_with(|| {
+                if val_1 > 0.0 {
+                    p1
+                } else {
+                    mix(p1, p2, find_t_change(p1, p2, mask))
+                }
+            });
+
+            if val_2 <= 0.0 {
+                crossing_start = None;
+                let end = mix(p1, p2, find_t_change(p1, p2, mask));
+                arches.extend(arch_over(start, end, style));
+            }
+        }
+    }
+
+    // the curve ends over the path
+    if let (Some(start), Some(end)) = (crossing_start, polyline.points.last()) {
+        arches.extend(arch_over(start, *end, style));
+    }
+
+    arches
+}
+
+// Transforms of the bricks of all the arches, as `arch_layout_bricks.comp` writes them into the transforms buffer
+pub fn arch_bricks(arches: &[ArchSegmentDataSSBO], mask: &impl ArchMask) -> Vec<Mat4> {
+    arches
+        .iter()
+        .flat_map(|arch| layout_arch_bricks(arch, mask))
+        .collect()
+}
+
+// Bricks of a single arch, one invocation of `arch_layout_bricks.comp`.
+// The voussoirs are spread evenly along the ring, which is sampled across the span
+pub fn layout_arch_bricks(arch: &ArchSegmentDataSSBO, mask: &impl ArchMask) -> Vec<Mat4> {
+    let profile: Vec<Vec3> = (0..=PROFILE_SUBDIV)
+        .map(|i| {
+            arch_point(
+                arch,
+                (i as f32 / PROFILE_SUBDIV as f32 - 0.5) * arch.span,
+                mask,
+            )
+        })
+        .collect();
+    let mut profile_length = vec![0.0];
+    for pair in profile.windows(2) {
+        profile_length.push(profile_length[profile_length.len() - 1] + pair[0].distance(pair[1]));
+    }
+    let ring_length = profile_length[PROFILE_SUBDIV];
+
+    let lintel = arch.shape == ArchShape::Lintel as u32;
+    let mut total_bricks = if arch.voussoirs > 0 {
+        arch.voussoirs as i32
+    } else {
+        ((ring_length / TARGET_BRICK_WIDTH).floor() as i32).max(1)
+    };
+    // so that the keystone is in the middle
+    if lintel && total_bricks % 2 == 0 {
+        total_bricks += 1;
+    }
+
+    let mut bricks = Vec::with_capacity(total_bricks as usize);
+    let mut sample = 0;
+    let mut subseg_p1 = profile[0];
+    for k in 0..total_bricks {
+        let target_length = ring_length * (k + 1) as f32 / total_bricks as f32;
+        while sample < PROFILE_SUBDIV - 1 && profile_length[sample + 1] < target_length {
+            sample += 1;
+        }
+        let t = (target_length - profile_length[sample])
+            / (profile_length[sample + 1] - profile_length[sample]).max(1e-6);
+        let subseg_p2 = mix(profile[sample], profile[sample + 1], t.clamp(0.0, 1.0));
+
+        let mut pivot = (subseg_p1 + subseg_p2) / 2.0;
+        let width = subseg_p1.distance(subseg_p2);
+        let mut height = ARCH_BRICK_HEIGHT;
+        if lintel && k == total_bricks / 2 {
+            height = KEYSTONE_HEIGHT;
+            pivot.y += (KEYSTONE_HEIGHT - ARCH_BRICK_HEIGHT) / 2.0;
+        }
+
+        let x = (subseg_p2 - subseg_p1).normalize();
+        let z = x.cross(Vec3::Y).normalize();
+        let y = x.cross(z).normalize();
+
+        let rotate = Mat4::from_cols(
+            x.extend(0.0),
+            y.extend(0.0),
+            z.extend(0.0),
+            Vec4::new(0.0, 0.0, 0.0, 1.0),
+        );
+
+        bricks.push(
+            Mat4::from_translation(pivot)
+                * rotate
+                * Mat4::from_scale(Vec3::new(width, height, ARCH_BRICK_DEPTH)),
+        );
+        subseg_p1 = subseg_p2;
+    }
+
+    bricks
+}
+
+// Arch brick as a `Brick`, e.g. for the glTF export.
+// The shader's basis has Y pointing down, which turns the brick inside out, so Y is flipped back up
+pub fn arch_brick(transform: Mat4) -> Brick {
+    let flipped = transform * Mat4::from_scale(Vec3::new(1.0, -1.0, 1.0));
+    let (scale, rotation, translation) = flipped.to_scale_rotation_translation();
+
+    Brick {
+        bounds_uv: Vec2::ZERO,
+        pivot_uv: Vec2::ZERO,
+        transform: Transform {
+            translation,
+            rotation,
+            scale,
+        },
+    }
+}
+
+// Checks that both lists hold the same bricks, `tolerance` is per matrix element.
+// The order doesn't matter, the GPU adds bricks in parallel
+pub fn compare_arch_bricks(
+    expected: &[Mat4],
+    actual: &[Mat4],
+    tolerance: f32,
+) -> Result<(), String> {
+    if expected.len() != actual.len() {
+        return Err(format!(
+            "{} arch bricks, expected {}",
+            actual.len(),
+            expected.len()
+        ));
+    }
+
+    let mut unmatched: Vec<&Mat4> = actual.iter().collect();
+    let mut mismatches = 0;
+    for brick in expected {
+        match unmatched
+            .iter()
+            .position(|other| other.abs_diff_eq(*brick, tolerance))
+        {
+            Some(i) => {
+                unmatched.swap_remove(i);
+            }
+            None => mismatches += 1,
+        }
+    }
+
+    if mismatches > 0 {
+        return Err(format!(
+            "{} of {} arch bricks don't match",
+            mismatches,
+            expected.len()
+        ));
+    }
+    Ok(())
+}
+
+// GLSL `mix`, which rounds differently from `Vec3::lerp`
+fn mix(a: Vec3, b: Vec3, t: f32) -> Vec3 {
+    a * (1.0 - t) + b * t
+}
+
+// The arch over a crossing from `start` to `end`, or none if the crossing is too short to span
+fn arch_over(start: Vec3, end: Vec3, style: &ArchStyle) -> Option<ArchSegmentDataSSBO> {
+    let length = Vec2::new(start.x, start.z).distance(Vec2::new(end.x, end.z));
+    if length < 0.0001 {
+        return None;
+    }
+
+    let span = if style.span > 0.0 { style.span } else { length };
+    Some(ArchSegmentDataSSBO {
+        start: [start.x, start.z],
+        end: [end.x, end.z],
+        shape: style.shape as u32,
+        voussoirs: style.voussoirs,
+        span,
+        rise: style.rise_over(span),
+    })
+}
+
+// Point of the arch ring `x` meters from the middle of the span, which is centered on the crossing
+fn arch_point(arch: &ArchSegmentDataSSBO, x: f32, mask: &impl ArchMask) -> Vec3 {
+    let start = Vec2::from(arch.start);
+    let end = Vec2::from(arch.end);
+    let p = (start + end) / 2.0 + (end - start).normalize() * x;
+
+    let mask_value = mask.value_at(Vec3::new(p.x, 0.0, p.y));
+    Vec3::new(p.x, arch_height(arch, x, mask_value), p.y)
+}
+
+// Where along the segment the mask starts to change from 0, or 0.0 if it doesn't
+fn find_t_change(p1: Vec3, p2: Vec3, mask: &impl ArchMask) -> f32 {
+    for i in 0..CLIP_SUBDIV {
+        let t1 = i as f32 / CLIP_SUBDIV as f32;
+        let t2 = (i + 1) as f32 / CLIP_SUBDIV as f32;
+
+        let h1 = mask.value_at(mix(p1, p2, t1));
+        let h2 = mask.value_at(mix(p1, p2, t2));
+
+        if (h1 - h2).abs() > 0.001 && (h1 < 0.0001 || h2 < 0.0001) {
+            return if h1 < h2 { t1 } else { t2 };
+        }
+    }
+    0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::curve::Curve;
+
+    // 16 m wide, 1/16 m pixels, so the blur reaches 0.625 m around paths
+    const DIMS: (i32, i32) = (256, 256);
+    const WS_MIN: [f32; 2] = [-8.0, -8.0];
+    const WS_SIZE: [f32; 2] = [16.0, 16.0];
+
+    // A 2 m wide path going along Z from z = -3 to z = 3
+    fn path_mask() -> PathMaskImage {
+        let path = Curve::from(vec![Vec3::new(0.0, 0.0, -3.0), Vec3::new(0.0, 0.0, 3.0)]);
+        PathMaskImage::from_paths(&[(&path, 2.0)], DIMS, WS_MIN.into(), WS_SIZE.into())
+    }
+
+    // A wall along X at `z`, with points every 0.25 m
+    fn wall(z: f32) -> Curve {
+        Curve::from(
+            (-24..=24)
+                .map(|i| Vec3::new(i as f32 * 0.25, 0.0, z))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn blur_spreads_the_mask() {
+        let mask = path_mask();
+        let (x, y) = mask.pixel_coord(Vec3::ZERO);
+
+        // inside the path the blur only reads ones, but it divides by a bit more than it sums
+        let center = mask.load_blurred(x, y);
+        assert!(center > 0.9 && center < 1.0, "{}", center);
+        assert_eq!(mask.load_blurred(x + 3, y), center);
+
+        // the path ends 16 pixels away, the blur reaches 10
+        assert!(mask.load_blurred(x + 20, y) > 0.0);
+        assert!(mask.load_blurred(x + 20, y) < center);
+        assert_eq!(mask.load_blurred(x + 27, y), 0.0);
+    }
+
+    #[test]
+    fn finds_the_crossing() {
+        let mask = path_mask();
+        let blurred = BlurredPathMask(&mask);
+
+        let arches = arch_segments(&wall(0.0), &ArchStyle::default(), &blurred);
+        assert_eq!(arches.len(), 1);
+        let arch = arches[0];
+
+        // the crossing is clipped to where the blurred path starts
+        for x in [arch.start[0], arch.end[0]] {
+            assert!((x.abs() - (1.0 + 0.625)).abs() < 0.1, "{:?}", arch);
+        }
+        assert_eq!((arch.start[1], arch.end[1]), (0.0, 0.0));
+        assert!((arch.span - (arch.end[0] - arch.start[0])).abs() < 1e-5);
+        assert_eq!(arch.rise, 1.0);
+
+        assert!(arch_segments(&wall(5.0), &ArchStyle::default(), &blurred).is_empty());
+    }
+
+    #[test]
+    fn bricks_follow_the_arch() {
+        let mask = path_mask();
+        let blurred = BlurredPathMask(&mask);
+        let arches = arch_segments(&wall(0.0), &ArchStyle::default(), &blurred);
+        let bricks = arch_bricks(&arches, &blurred);
+        assert!(!bricks.is_empty());
+
+        let top = arch_function(blurred.value_at(Vec3::ZERO));
+        for brick in &bricks {
+            let pivot = brick.w_axis.truncate();
+            assert!(pivot.y >= 0.0 && pivot.y <= top + 1e-4, "{}", pivot);
+            assert!(pivot.z.abs() < 1e-4, "{}", pivot);
+            assert!((brick.y_axis.length() - ARCH_BRICK_HEIGHT).abs() < 1e-4);
+            assert!((brick.z_axis.length() - ARCH_BRICK_DEPTH).abs() < 1e-4);
+        }
+
+        // bricks over the path are at the top of the arch, the ones past its sides come down
+        let height_near = |x: f32| {
+            bricks
+                .iter()
+                .map(|b| b.w_axis)
+                .min_by(|a, b| (a.x - x).abs().partial_cmp(&(b.x - x).abs()).unwrap())
+                .unwrap()
+                .y
+        };
+        assert!((height_near(0.0) - top).abs() < 1e-3);
+        assert!(height_near(1.5) < height_near(0.5));
+    }
+
+    #[test]
+    fn flat_mask_gives_level_bricks() {
+        let mut mask = PathMaskImage::new(DIMS, WS_MIN.into(), WS_SIZE.into());
+        mask.pixels.iter_mut().for_each(|v| *v = 0.5);
+
+        let arch = ArchSegmentDataSSBO {
+            start: [0.0, 1.0],
+            end: [1.1, 1.0],
+            span: 1.1,
+            rise: 1.0,
+            ..Default::default()
+        };
+        let bricks = layout_arch_bricks(&arch, &mask);
+
+        // 1.1 m long, 0.2 m bricks
+        assert_eq!(bricks.len(), 5);
+        for brick in &bricks {
+            let pivot = brick.w_axis.truncate();
+            assert!((pivot.y - arch_function(0.5)).abs() < 1e-5);
+            assert!((pivot.z - 1.0).abs() < 1e-5);
+            assert!(pivot.x > 0.0 && pivot.x < 1.1);
+        }
+    }
+
+    // An arch of `style` over a 2 m crossing along X, centered on the origin
+    fn shaped_arch(style: ArchStyle) -> ArchSegmentDataSSBO {
+        arch_over(Vec3::new(-1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), &style).unwrap()
+    }
+
+    #[test]
+    fn shapes_rise_to_the_crown() {
+        let mask = PathMaskImage::new(DIMS, WS_MIN.into(), WS_SIZE.into());
+        let style = |shape| ArchStyle {
+            shape,
+            ..Default::default()
+        };
+
+        // the ends are on the ground, and the crown in the middle is as high as the rise
+        for (shape, rise) in [
+            (ArchShape::Round, 1.0),
+            (ArchShape::Pointed, 3.0f32.sqrt()),
+            (ArchShape::Segmental, 0.5),
+        ] {
+            let arch = shaped_arch(style(shape));
+            assert!((arch.rise - rise).abs() < 1e-5, "{:?}", shape);
+            assert!(arch_height(&arch, -1.0, 0.0).abs() < 1e-3, "{:?}", shape);
+            assert!(arch_height(&arch, 1.0, 0.0).abs() < 1e-3, "{:?}", shape);
+            assert!(
+                (arch_height(&arch, 0.0, 0.0) - rise).abs() < 1e-5,
+                "{:?}",
+                shape
+            );
+        }
+
+        // round arches are circles, so their bricks are a radius away from the middle
+        let round = shaped_arch(style(ArchShape::Round));
+        for brick in layout_arch_bricks(&round, &mask) {
+            let distance = brick.w_axis.truncate().length();
+            assert!(distance < 1.0 && distance > 0.98, "{}", distance);
+        }
+
+        // a pointed arch can't be lower than a round one, nor a segmental one higher
+        let low = ArchStyle {
+            rise: 0.5,
+            ..style(ArchShape::Pointed)
+        };
+        assert_eq!(shaped_arch(low).rise, 1.0);
+        let high = ArchStyle {
+            rise: 2.0,
+            ..style(ArchShape::Segmental)
+        };
+        assert_eq!(shaped_arch(high).rise, 1.0);
+    }
+
+    #[test]
+    fn span_and_voussoirs_are_kept() {
+        let mask = PathMaskImage::new(DIMS, WS_MIN.into(), WS_SIZE.into());
+        let arch = shaped_arch(ArchStyle {
+            shape: ArchShape::Round,
+            span: 3.0,
+            rise: 0.8,
+            voussoirs: 9,
+        });
+        assert_eq!((arch.span, arch.rise), (3.0, 0.8));
+
+        let bricks = layout_arch_bricks(&arch, &mask);
+        assert_eq!(bricks.len(), 9);
+        let xs: Vec<f32> = bricks.iter().map(|b| b.w_axis.x).collect();
+        assert!(xs.iter().all(|x| x.abs() < 1.5));
+        assert!(xs[0] < -1.2 && xs[8] > 1.2);
+        // the middle brick is a chord under the crown
+        assert!(bricks[4].w_axis.y < 0.8 && bricks[4].w_axis.y > 0.78);
+    }
+
+    #[test]
+    fn lintel_has_a_keystone() {
+        let mask = PathMaskImage::new(DIMS, WS_MIN.into(), WS_SIZE.into());
+        let arch = shaped_arch(ArchStyle {
+            shape: ArchShape::Lintel,
+            voussoirs: 6,
+            ..Default::default()
+        });
+
+        // an odd number of bricks, so the keystone is in the middle
+        let bricks = layout_arch_bricks(&arch, &mask);
+        assert_eq!(bricks.len(), 7);
+        for (i, brick) in bricks.iter().enumerate() {
+            let height = brick.y_axis.length();
+            if i == 3 {
+                assert!((height - KEYSTONE_HEIGHT).abs() < 1e-4);
+                assert!(brick.w_axis.y > 1.0);
+                assert!(brick.w_axis.x.abs() < 1e-4);
+            } else {
+                assert!((height - ARCH_BRICK_HEIGHT).abs() < 1e-4);
+                assert!((brick.w_axis.y - 1.0).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn compares_bricks_in_any_order() {
+        let a = Mat4::from_translation(Vec3::X);
+        let b = Mat4::from_scale(Vec3::splat(2.0));
+
+        assert!(compare_arch_bricks(&[a, b], &[b, a], 1e-5).is_ok());
+        assert!(compare_arch_bricks(&[a, b], &[a], 1e-5).is_err());
+        assert!(compare_arch_bricks(&[a, b], &[a, a], 1e-5).is_err());
+        assert!(
+            compare_arch_bricks(&[a], &[a * Mat4::from_translation(Vec3::Y * 1e-3)], 1e-2).is_ok()
+        );
+    }
+
+    #[test]
+    fn arch_brick_keeps_the_placement() {
+        let mask = path_mask();
+        let blurred = BlurredPathMask(&mask);
+        let arches = arch_segments(&wall(0.0), &ArchStyle::default(), &blurred);
+        let transform = arch_bricks(&arches, &blurred)[0];
+
+        let brick = arch_brick(transform).transform.compute_matrix();
+        assert!(brick.w_axis.abs_diff_eq(transform.w_axis, 1e-5));
+        assert!(brick.x_axis.abs_diff_eq(transform.x_axis, 1e-5));
+        assert!(brick.y_axis.abs_diff_eq(-transform.y_axis, 1e-5));
+        assert!(brick.determinant() > 0.0);
+    }
+
+    #[test]
+    fn voussoirs_that_dont_fit_are_rejected() {
+        let style =
+            |voussoirs: u64| ArchStyle::from_json(&serde_json::json!({ "voussoirs": voussoirs }));
+        assert_eq!(style(11).unwrap().voussoirs, 11);
+        assert!(style(1001).is_err());
+        // would be read as 5 if it was truncated to 32 bits
+        assert!(style((1 << 32) + 5).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use glam::{Vec2, Vec3};
+
+// Side of a square chunk of the world, chunk (0, 0) is centered on the origin
+pub const CHUNK_SIZE: f32 = 20.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChunkCoord {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl ChunkCoord {
+    pub fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    // Chunk that `pos` is in, only XZ is taken into account
+    pub fn of(pos: Vec3) -> Self {
+        Self::of_xz(Vec2::new(pos.x, pos.z))
+    }
+
+    fn of_xz(pos: Vec2) -> Self {
+        Self {
+            x: (pos.x / CHUNK_SIZE + 0.5).floor() as i32,
+            z: (pos.y / CHUNK_SIZE + 0.5).floor() as i32,
+        }
+    }
+
+    // XZ of the corner with the lowest coordinates
+    pub fn min(&self) -> Vec2 {
+        (Vec2::new(self.x as f32, self.z as f32) - Vec2::splat(0.5)) * CHUNK_SIZE
+    }
+
+    pub fn center(&self) -> Vec2 {
+        Vec2::new(self.x as f32, self.z as f32) * CHUNK_SIZE
+    }
+
+    // Chunks overlapping the XZ rectangle from `min` to `max`
+    pub fn covering(min: Vec2, max: Vec2) -> Vec<Self> {
+        let (from, to) = (Self::of_xz(min), Self::of_xz(max));
+        (from.z..=to.z)
+            .flat_map(|z| (from.x..=to.x).map(move |x| Self::new(x, z)))
+            .collect()
+    }
+}
+
+// Square of chunks around `center`, that's what the GPU textures hold.
+// Textures are made of one tile per chunk, laid out like the chunks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkWindow {
+    pub center: ChunkCoord,
+    // in chunks, not counting the center one
+    pub radius: i32,
+}
+
+impl ChunkWindow {
+    pub fn new(center: ChunkCoord, radius: i32) -> Self {
+        Self { center, radius }
+    }
+
+    // Number of chunks along each side
+    pub fn side(&self) -> i32 {
+        self.radius * 2 + 1
+    }
+
+    pub fn chunks(&self) -> Vec<ChunkCoord> {
+        let r = self.radius;
+        (-r..=r)
+            .flat_map(|z| (-r..=r).map(move |x| (x, z)))
+            .map(|(x, z)| ChunkCoord::new(self.center.x + x, self.center.z + z))
+            .collect()
+    }
+
+    pub fn contains(&self, coord: ChunkCoord) -> bool {
+        (coord.x - self.center.x).abs() <= self.radius
+            && (coord.z - self.center.z).abs() <= self.radius
+    }
+
+    // XZ of the corner with the lowest coordinates
+    pub fn min(&self) -> Vec2 {
+        ChunkCoord::new(self.center.x - self.radius, self.center.z - self.radius).min()
+    }
+
+    // Size in world space
+    pub fn size(&self) -> Vec2 {
+        Vec2::splat(self.side() as f32 * CHUNK_SIZE)
+    }
+
+    // Dimensions of a texture made of `tile_dims` tiles
+    pub fn texture_dims(&self, tile_dims: (i32, i32)) -> (i32, i32) {
+        (tile_dims.0 * self.side(), tile_dims.1 * self.side())
+    }
+
+    // Pixel where the tile of `coord` starts, `None` if it's outside of the window
+    pub fn tile_offset(&self, coord: ChunkCoord, tile_dims: (i32, i32)) -> Option<(i32, i32)> {
+        if !self.contains(coord) {
+            return None;
+        }
+
+        Some((
+            (coord.x - self.center.x + self.radius) * tile_dims.0,
+            (coord.z - self.center.z + self.radius) * tile_dims.1,
+        ))
+    }
+}
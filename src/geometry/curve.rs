@@ -1,6 +1,25 @@
-use glam::Vec3;
+use std::borrow::Cow;
 
-#[derive(Clone)]
+use glam::{Vec2, Vec3};
+
+// What walls need from a curve, so they can be built from polylines (`Curve`) as well as splines (`SplineCurve`)
+pub trait WallCurve {
+    fn length(&self) -> f32;
+
+    fn get_pos_at_distance(&self, distance: f32) -> Vec3;
+
+    fn get_tangent_at_distance(&self, distance: f32) -> Vec3;
+
+    // Horizontal, perpendicular to the tangent
+    fn get_normal_at_distance(&self, distance: f32) -> Vec3 {
+        self.get_tangent_at_distance(distance).cross(Vec3::Y)
+    }
+
+    // For everything that works on points, e.g. shadow decals and the curve SSBO
+    fn polyline(&self) -> Cow<'_, Curve>;
+}
+
+#[derive(Clone, Default)]
 pub struct Curve {
     pub points: Vec<Vec3>,
     // cache u values upon creation
@@ -18,17 +37,54 @@ impl Curve {
     }
 
     pub fn add(&mut self, pt: Vec3) {
+        let added_length = self.points.last().map(|p| (pt - *p).length());
         self.points.push(pt);
 
-        // TODO: this is slow & lazy
-        *self = Self::from(self.points.clone());
+        match added_length {
+            Some(added_length) if self.length > 0.0 => {
+                let length = self.length + added_length;
+                for u in &mut self.points_u {
+                    *u *= self.length / length;
+                }
+                self.points_u.push(1.0);
+                self.length = length;
+            }
+            // u isn't defined yet for curves without length
+            _ => *self = Self::from(std::mem::take(&mut self.points)),
+        }
     }
 
     pub fn add_to_front(&mut self, pt: Vec3) {
+        let added_length = self.points.first().map(|p| (pt - *p).length());
         self.points.insert(0, pt);
 
-        // TODO: this is slow & lazy
-        *self = Self::from(self.points.clone());
+        match added_length {
+            Some(added_length) if self.length > 0.0 => {
+                let length = self.length + added_length;
+                for u in &mut self.points_u {
+                    *u = (*u * self.length + added_length) / length;
+                }
+                self.points_u.insert(0, 0.0);
+                self.length = length;
+            }
+            _ => *self = Self::from(std::mem::take(&mut self.points)),
+        }
+    }
+
+    // Length along the curve over which `other` has exactly the same points
+    pub fn common_length(&self, other: &Curve) -> f32 {
+        let common_points = self
+            .points
+            .iter()
+            .zip(&other.points)
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        if common_points < 2 {
+            0.0
+        } else {
+            self.points_u[common_points - 1] * self.length
+        }
     }
 
     pub fn from(points: Vec<Vec3>) -> Self {
@@ -86,21 +142,29 @@ impl Curve {
         self
     }
 
+    // Points are laid every `segment_length` from the start of the curve, so if only the end of a curve changes,
+    // the points before it stay exactly the same. The last segment is between 0.5 and 1.5 `segment_length` long
     pub fn resample(self, segment_length: f32) -> Self {
         if segment_length >= self.length {
             return Curve::from(vec![self.points[0], *self.points.last().unwrap()]);
         }
 
-        //  TODO: this is TEMPORARY & SLOW! this re-uses the `get_pos_at_u` which searches the curve from start every time
-        let u_spacing = segment_length / self.length;
-        let target_points = (1.0 / u_spacing).round() as usize;
-        let target_u_spacing = 1.0 / (target_points as f32);
+        let mut segments = (self.length / segment_length).floor() as usize;
+        if self.length - segments as f32 * segment_length < segment_length / 2.0 {
+            segments -= 1;
+        }
 
-        Curve::from(
-            (0..=target_points)
-                .map(|i| self.get_pos_at_u((i as f32) * target_u_spacing))
-                .collect(),
-        )
+        let mut points = Vec::with_capacity(segments + 2);
+        points.push(self.points[0]);
+
+        let mut walker = CurveWalker::new(&self);
+        for i in 1..=segments {
+            points.push(walker.pos_at(i as f32 * segment_length));
+        }
+
+        points.push(*self.points.last().unwrap());
+
+        Curve::from(points)
     }
 
     // Curve segment is defined by start_point_index and end_point_index
@@ -111,17 +175,16 @@ impl Curve {
             return (0, 1);
         }
 
-        for (i, pt_u) in self.points_u.iter().enumerate() {
-            if u <= *pt_u {
-                return (i - 1, i);
-            }
-        }
-
-        unreachable!()
+        // first point that is at or past `u`
+        let i = self
+            .points_u
+            .partition_point(|pt_u| *pt_u < u)
+            .clamp(1, self.points.len() - 1);
+        (i - 1, i)
     }
 
     pub fn get_pos_at_u(&self, u: f32) -> Vec3 {
-        assert!(u <= 1.0 && u >= 0.0, "u is in incorrect range");
+        assert!((0.0..=1.0).contains(&u), "u is in incorrect range");
 
         let (idx1, idx2) = self.get_curve_segment_from_u(u);
 
@@ -134,10 +197,257 @@ impl Curve {
     }
 
     pub fn get_tangent_at_u(&self, u: f32) -> Vec3 {
-        assert!(u <= 1.0 && u >= 0.0, "u is in incorrect range");
+        assert!((0.0..=1.0).contains(&u), "u is in incorrect range");
 
         let (idx1, idx2) = self.get_curve_segment_from_u(u);
 
         (self.points[idx2] - self.points[idx1]).normalize()
     }
+
+    fn u_at_distance(&self, distance: f32) -> f32 {
+        (distance / self.length).clamp(0.0, 1.0)
+    }
+
+    // Walls live on the ground plane, so only XZ is taken into account
+    pub fn closest_point(&self, pos: Vec3) -> Option<ClosestPoint> {
+        let pos = xz(pos);
+
+        let mut closest: Option<ClosestPoint> = None;
+        let mut length_traveled = 0.0;
+        for seg in self.points.windows(2) {
+            let (a, b) = (xz(seg[0]), xz(seg[1]));
+            let t =
+                ((pos - a).dot(b - a) / (b - a).length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+            let separation = pos.distance(a.lerp(b, t));
+
+            let segment = (seg[1] - seg[0]).length();
+            if closest.as_ref().is_none_or(|c| separation < c.separation) {
+                closest = Some(ClosestPoint {
+                    position: seg[0].lerp(seg[1], t),
+                    distance: length_traveled + segment * t,
+                    separation,
+                });
+            }
+            length_traveled += segment;
+        }
+
+        closest
+    }
+
+    // Distance ranges along the curve that are within `radius` from `center` in XZ, ordered and merged
+    pub fn ranges_within(&self, center: Vec3, radius: f32) -> Vec<(f32, f32)> {
+        self.ranges_within_capsule(center, center, radius)
+    }
+
+    // Distance ranges along the curve that are within `radius` from the segment `from`-`to` in XZ, ordered and merged
+    pub fn ranges_within_capsule(&self, from: Vec3, to: Vec3, radius: f32) -> Vec<(f32, f32)> {
+        let (from, to) = (xz(from), xz(to));
+
+        let mut ranges: Vec<(f32, f32)> = Vec::new();
+        let mut length_traveled = 0.0;
+        for seg in self.points.windows(2) {
+            let segment = (seg[1] - seg[0]).length();
+            let (a, b) = (xz(seg[0]), xz(seg[1]));
+
+            // a capsule is convex, so the segment is inside of it for a single range of t,
+            // spanning where it's inside the two end circles and the rectangle between them
+            let inside = [
+                circle_range(a, b, from, radius),
+                circle_range(a, b, to, radius),
+                rectangle_range(a, b, from, to, radius),
+            ]
+            .iter()
+            .flatten()
+            .fold(None, |hull: Option<(f32, f32)>, (t0, t1)| match hull {
+                Some((h0, h1)) => Some((h0.min(*t0), h1.max(*t1))),
+                None => Some((*t0, *t1)),
+            });
+
+            if let Some((t0, t1)) = inside {
+                let (t0, t1) = (t0.max(0.0), t1.min(1.0));
+                if t0 < t1 {
+                    let range = (
+                        length_traveled + segment * t0,
+                        length_traveled + segment * t1,
+                    );
+                    match ranges.last_mut() {
+                        Some(last) if range.0 <= last.1 => last.1 = last.1.max(range.1),
+                        _ => ranges.push(range),
+                    }
+                }
+            }
+
+            length_traveled += segment;
+        }
+
+        ranges
+    }
+
+    // Points where the two curves cross in XZ, as (distance along this curve, distance along `other`), ordered along this curve
+    pub fn crossings(&self, other: &Curve) -> Vec<(f32, f32)> {
+        let mut crossings = Vec::new();
+        let mut length_traveled = 0.0;
+        for seg in self.points.windows(2) {
+            let (a, b) = (xz(seg[0]), xz(seg[1]));
+            let segment = (seg[1] - seg[0]).length();
+
+            let mut other_length_traveled = 0.0;
+            for other_seg in other.points.windows(2) {
+                let (c, d) = (xz(other_seg[0]), xz(other_seg[1]));
+                let other_segment = (other_seg[1] - other_seg[0]).length();
+
+                // a + (b - a) * t = c + (d - c) * s
+                let denominator = (b - a).perp_dot(d - c);
+                if denominator.abs() > f32::EPSILON {
+                    let t = (c - a).perp_dot(d - c) / denominator;
+                    let s = (c - a).perp_dot(b - a) / denominator;
+                    if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&s) {
+                        crossings.push((
+                            length_traveled + segment * t,
+                            other_length_traveled + other_segment * s,
+                        ));
+                    }
+                }
+
+                other_length_traveled += other_segment;
+            }
+
+            length_traveled += segment;
+        }
+
+        crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        crossings
+    }
+
+    // Part of the curve between two distances along it
+    pub fn slice(&self, from: f32, to: f32) -> Curve {
+        let mut points = vec![self.get_pos_at_distance(from)];
+        points.extend(
+            self.points
+                .iter()
+                .zip(&self.points_u)
+                .filter(|(_, u)| {
+                    let distance = *u * self.length;
+                    distance > from && distance < to
+                })
+                .map(|(p, _)| *p),
+        );
+        points.push(self.get_pos_at_distance(to));
+
+        Curve::from(points)
+    }
+}
+
+impl WallCurve for Curve {
+    fn length(&self) -> f32 {
+        self.length
+    }
+
+    fn get_pos_at_distance(&self, distance: f32) -> Vec3 {
+        self.get_pos_at_u(self.u_at_distance(distance))
+    }
+
+    fn get_tangent_at_distance(&self, distance: f32) -> Vec3 {
+        self.get_tangent_at_u(self.u_at_distance(distance))
+    }
+
+    fn polyline(&self) -> Cow<'_, Curve> {
+        Cow::Borrowed(self)
+    }
+}
+
+pub struct ClosestPoint {
+    pub position: Vec3,
+    // along the curve
+    pub distance: f32,
+    // from the query point, in XZ
+    pub separation: f32,
+}
+
+// Samples positions at increasing distances along a curve, walking it only once
+pub struct CurveWalker<'a> {
+    curve: &'a Curve,
+    idx: usize,
+    length_traveled: f32,
+}
+
+impl<'a> CurveWalker<'a> {
+    pub fn new(curve: &'a Curve) -> Self {
+        Self {
+            curve,
+            idx: 0,
+            length_traveled: 0.0,
+        }
+    }
+
+    // `distance` can't be smaller than in the previous call
+    pub fn pos_at(&mut self, distance: f32) -> Vec3 {
+        let points = &self.curve.points;
+
+        let mut segment = (points[self.idx + 1] - points[self.idx]).length();
+        while self.length_traveled + segment < distance && self.idx + 2 < points.len() {
+            self.length_traveled += segment;
+            self.idx += 1;
+            segment = (points[self.idx + 1] - points[self.idx]).length();
+        }
+
+        let t = ((distance - self.length_traveled) / segment).min(1.0);
+        points[self.idx].lerp(points[self.idx + 1], t)
+    }
+}
+
+fn xz(v: Vec3) -> Vec2 {
+    Vec2::new(v.x, v.z)
+}
+
+// Range of t for which a + (b - a) * t is within `radius` from `center`, not clamped to the segment
+fn circle_range(a: Vec2, b: Vec2, center: Vec2, radius: f32) -> Option<(f32, f32)> {
+    // |a + (b - a) * t - center| = radius
+    let dir = b - a;
+    let f = a - center;
+    let qa = dir.dot(dir);
+    let qb = 2.0 * f.dot(dir);
+    let qc = f.dot(f) - radius * radius;
+    let discriminant = qb * qb - 4.0 * qa * qc;
+
+    if qa > 0.0 && discriminant > 0.0 {
+        Some((
+            (-qb - discriminant.sqrt()) / (2.0 * qa),
+            (-qb + discriminant.sqrt()) / (2.0 * qa),
+        ))
+    } else {
+        None
+    }
+}
+
+// Range of t for which a + (b - a) * t is within `radius` from the segment `from`-`to`, measured perpendicular to it
+fn rectangle_range(a: Vec2, b: Vec2, from: Vec2, to: Vec2, radius: f32) -> Option<(f32, f32)> {
+    let length = from.distance(to);
+    if length <= f32::EPSILON {
+        return None;
+    }
+
+    let along = (to - from) / length;
+    let across = along.perp();
+    let (a, dir) = (a - from, b - a);
+
+    let mut range = (f32::NEG_INFINITY, f32::INFINITY);
+    for (axis, min, max) in [(along, 0.0, length), (across, -radius, radius)] {
+        // min <= x + dx * t <= max
+        let (x, dx) = (a.dot(axis), dir.dot(axis));
+        if dx.abs() <= f32::EPSILON {
+            if x < min || x > max {
+                return None;
+            }
+        } else {
+            let (t0, t1) = ((min - x) / dx, (max - x) / dx);
+            range = (range.0.max(t0.min(t1)), range.1.min(t0.max(t1)));
+        }
+    }
+
+    if range.0 < range.1 {
+        Some(range)
+    } else {
+        None
+    }
 }
@@ -0,0 +1,139 @@
+use glam::{Quat, Vec2, Vec3};
+
+use crate::components::transform::Transform;
+
+use super::{
+    curve::WallCurve,
+    wall_builder::{cut_openings, WallBuilder},
+    wall_constructor::{brick_rng, to_world_space, Brick, BrickLayout},
+    wall_junction::WallJoints,
+    wall_opening::WallOpening,
+    wall_style::WallStyle,
+};
+
+// Stones are this much larger than a brick of the style in the bottom course, and this much smaller in the top one
+const BOTTOM_STONE_SCALE: f32 = 1.6;
+const TOP_STONE_SCALE: f32 = 0.7;
+// Stones are tilted in the plane of the wall by at most this, in radians
+const MAX_TILT: f32 = 0.12;
+// Stones are shrunk by up to this much, so they don't all touch
+const MAX_SHRINK: f32 = 0.12;
+// Chance for a stone to go through the wall, sticking out on both sides
+const THROUGH_STONE_CHANCE: f32 = 0.08;
+// Coping stones are set on their edge along the top, this much narrower than they're high
+const COPING_WIDTH: f32 = 0.5;
+
+// Irregular stones packed in courses, the largest at the bottom, under a row of coping stones set on edge.
+// Joints only matter to brick walls, dry-stone walls just overlap where they meet
+pub struct DryStoneWall;
+
+impl WallBuilder for DryStoneWall {
+    fn build_after(
+        &self,
+        curve: &dyn WallCurve,
+        style: &WallStyle,
+        layout: &BrickLayout,
+        _joints: &WallJoints,
+        openings: &[WallOpening],
+        _reusable_length: f32,
+    ) -> (usize, Vec<Brick>) {
+        let wall_length = curve.length();
+        let mut stones = Vec::new();
+
+        let mut bottom = 0.0;
+        let mut course = 0;
+        while bottom < style.wall_height {
+            let rng = brick_rng(layout.seed, course, 0);
+            let scale = BOTTOM_STONE_SCALE
+                + (TOP_STONE_SCALE - BOTTOM_STONE_SCALE) * bottom / style.wall_height;
+
+            // the last course doesn't go above the wall height, the coping stones do
+            let height =
+                (style.brick_height + (rng.f32() - 0.5) * style.brick_height_variance) * scale;
+            let height = height
+                .min(style.wall_height - bottom)
+                .max(style.brick_height / 4.0);
+
+            // stones are packed from a random offset, so the joints of two courses don't line up
+            let mut from = -rng.f32() * style.brick_width * scale / 2.0;
+            while from < wall_length {
+                let width = ((style.brick_width
+                    + (rng.f32() - 0.5) * 2.0 * style.brick_width_variance)
+                    * scale)
+                    .max(style.brick_width / 2.0);
+                let to = (from + width).min(wall_length);
+                let from_clamped = from.max(0.0);
+
+                let depth = if rng.f32() < THROUGH_STONE_CHANCE {
+                    style.brick_depth * BOTTOM_STONE_SCALE * 1.5
+                } else {
+                    (style.brick_depth + (rng.f32() - 0.5) * style.brick_depth_variance) * scale
+                };
+                let shrink = 1.0 - rng.f32() * MAX_SHRINK;
+                let tilt = (rng.f32() - 0.5) * 2.0 * MAX_TILT;
+
+                stones.push(stone(
+                    (from_clamped, to),
+                    (bottom, height),
+                    Vec3::new((to - from_clamped) * shrink, height * shrink, depth),
+                    tilt,
+                    wall_length,
+                    style.wall_height,
+                ));
+                from = to;
+            }
+
+            bottom += height;
+            course += 1;
+        }
+
+        // coping stones on edge, as high as the largest bricks of the style
+        let rng = brick_rng(layout.seed, course, 0);
+        let coping_height = style.brick_height + style.brick_height_variance;
+        let mut from = 0.0;
+        while from < wall_length {
+            let width = coping_height * COPING_WIDTH * (0.8 + rng.f32() * 0.4);
+            let to = (from + width).min(wall_length);
+            let height = coping_height * (0.85 + rng.f32() * 0.3);
+            let tilt = (rng.f32() - 0.5) * MAX_TILT;
+            stones.push(stone(
+                (from, to),
+                (bottom, height),
+                Vec3::new(
+                    (to - from) * 0.95,
+                    height,
+                    style.brick_depth * TOP_STONE_SCALE,
+                ),
+                tilt,
+                wall_length,
+                style.wall_height,
+            ));
+            from = to;
+        }
+
+        cut_openings(&mut stones, openings, style, wall_length);
+        to_world_space(curve, &mut stones, style.wall_height);
+        (0, stones)
+    }
+}
+
+// Stone from `from` to `to` along the curve, and from `bottom` up `height` (in meters), tilted in the plane of the wall
+fn stone(
+    (from, to): (f32, f32),
+    (bottom, height): (f32, f32),
+    scale: Vec3,
+    tilt: f32,
+    wall_length: f32,
+    wall_height: f32,
+) -> Brick {
+    let pivot_u = (from + to) / 2.0 / wall_length;
+    Brick {
+        pivot_uv: Vec2::new(pivot_u, (bottom + height / 2.0) / wall_height),
+        bounds_uv: Vec2::new((to - from) / wall_length, height / wall_height),
+        transform: Transform {
+            translation: Vec3::new(pivot_u * wall_length, 0.0, 0.0),
+            rotation: Quat::from_rotation_z(tilt),
+            scale,
+        },
+    }
+}
@@ -0,0 +1,163 @@
+use glam::{Quat, Vec2, Vec3};
+
+use crate::components::transform::Transform;
+
+use super::{
+    curve::WallCurve,
+    wall_builder::{cut_openings, WallBuilder},
+    wall_constructor::{brick_rng, to_world_space, Brick, BrickLayout},
+    wall_junction::WallJoints,
+    wall_opening::WallOpening,
+    wall_style::WallStyle,
+};
+
+// Posts are evenly spaced, at most this far apart
+const POST_SPACING: f32 = 2.0;
+const POST_SIZE: f32 = 0.12;
+// Posts lean by at most this, in radians
+const MAX_POST_LEAN: f32 = 0.04;
+const RAIL_HEIGHT: f32 = 0.08;
+const RAIL_DEPTH: f32 = 0.05;
+// A post-and-rail fence gets a rail for every this much of its height
+const RAIL_SPACING: f32 = 0.4;
+const PICKET_WIDTH: f32 = 0.08;
+const PICKET_SPACING: f32 = 0.15;
+const PICKET_DEPTH: f32 = 0.025;
+// Pickets are this much of the fence height, and stand this far above the ground
+const PICKET_HEIGHT: f32 = 0.85;
+const PICKET_CLEARANCE: f32 = 0.05;
+
+// Rows of the randomness of the pieces, see `brick_rng`
+const POST_ROW: usize = 0;
+const RAIL_ROW: usize = 1;
+const PICKET_ROW: usize = 2;
+
+// Posts as high as the wall, with rails between them, and pickets on the rails if `pickets`.
+// Joints only matter to brick walls, fences just overlap where they meet
+pub struct Fence {
+    pub pickets: bool,
+}
+
+impl WallBuilder for Fence {
+    fn build_after(
+        &self,
+        curve: &dyn WallCurve,
+        style: &WallStyle,
+        layout: &BrickLayout,
+        _joints: &WallJoints,
+        openings: &[WallOpening],
+        _reusable_length: f32,
+    ) -> (usize, Vec<Brick>) {
+        let wall_length = curve.length();
+        let height = style.wall_height;
+        let mut pieces = Vec::new();
+
+        let spans = (wall_length / POST_SPACING).ceil().max(1.0) as usize;
+        let span = wall_length / spans as f32;
+
+        for i in 0..=spans {
+            let rng = brick_rng(layout.seed, POST_ROW, i);
+            let x = i as f32 * span;
+            let post_height = height * (1.0 + (rng.f32() - 0.5) * 0.1);
+            pieces.push(piece(
+                (x - POST_SIZE / 2.0, x + POST_SIZE / 2.0),
+                (0.0, post_height),
+                Vec3::new(POST_SIZE, post_height, POST_SIZE),
+                Quat::from_rotation_z((rng.f32() - 0.5) * 2.0 * MAX_POST_LEAN),
+                0.0,
+                wall_length,
+                height,
+            ));
+        }
+
+        // pickets hang on a low and a high rail, a post-and-rail fence is all rails up to just under the top of the posts
+        let rails: Vec<f32> = if self.pickets {
+            vec![0.2, 0.7]
+        } else {
+            let count = (height / RAIL_SPACING).round().max(2.0) as usize;
+            (0..count)
+                .map(|i| 0.9 * (i + 1) as f32 / count as f32)
+                .collect()
+        };
+        for i in 0..spans {
+            let (from, to) = (
+                i as f32 * span + POST_SIZE / 2.0,
+                (i + 1) as f32 * span - POST_SIZE / 2.0,
+            );
+            for (j, v) in rails.iter().enumerate() {
+                let rng = brick_rng(layout.seed, RAIL_ROW, i * rails.len() + j);
+                // rails aren't quite level
+                let bottom = v * height - RAIL_HEIGHT / 2.0 + (rng.f32() - 0.5) * 0.04;
+                pieces.push(piece(
+                    (from, to),
+                    (bottom, RAIL_HEIGHT),
+                    Vec3::new(to - from, RAIL_HEIGHT, RAIL_DEPTH),
+                    Quat::IDENTITY,
+                    0.0,
+                    wall_length,
+                    height,
+                ));
+            }
+        }
+
+        if self.pickets {
+            let count = (wall_length / PICKET_SPACING).floor() as usize;
+            for i in 0..count {
+                let x = (i as f32 + 0.5) * PICKET_SPACING;
+                // no pickets in front of the posts
+                let to_post = (x / span).round() * span - x;
+                if to_post.abs() < (POST_SIZE + PICKET_WIDTH) / 2.0 {
+                    continue;
+                }
+
+                let rng = brick_rng(layout.seed, PICKET_ROW, i);
+                let picket_height = height * PICKET_HEIGHT * (1.0 + (rng.f32() - 0.5) * 0.06);
+                pieces.push(piece(
+                    (x - PICKET_WIDTH / 2.0, x + PICKET_WIDTH / 2.0),
+                    (PICKET_CLEARANCE, picket_height),
+                    Vec3::new(PICKET_WIDTH, picket_height, PICKET_DEPTH),
+                    Quat::IDENTITY,
+                    // nailed on the side of the rails
+                    (RAIL_DEPTH + PICKET_DEPTH) / 2.0,
+                    wall_length,
+                    height,
+                ));
+            }
+        }
+
+        cut_openings(&mut pieces, openings, style, wall_length);
+        to_world_space(curve, &mut pieces, height);
+        (0, pieces)
+    }
+
+    // weathered wood
+    fn tint(&self) -> [f32; 3] {
+        [1.0, 0.75, 0.5]
+    }
+
+    fn has_arches(&self) -> bool {
+        false
+    }
+}
+
+// Piece from `from` to `to` along the curve, and from `bottom` up `height` (in meters), `offset` in front of the curve
+fn piece(
+    (from, to): (f32, f32),
+    (bottom, height): (f32, f32),
+    scale: Vec3,
+    rotation: Quat,
+    offset: f32,
+    wall_length: f32,
+    wall_height: f32,
+) -> Brick {
+    let pivot_u = (from + to) / 2.0 / wall_length;
+    Brick {
+        pivot_uv: Vec2::new(pivot_u, (bottom + height / 2.0) / wall_height),
+        bounds_uv: Vec2::new((to - from) / wall_length, height / wall_height),
+        transform: Transform {
+            translation: Vec3::new(pivot_u * wall_length, 0.0, offset),
+            rotation,
+            scale,
+        },
+    }
+}
@@ -0,0 +1,85 @@
+use glam::{Quat, Vec2, Vec3};
+
+use crate::components::transform::Transform;
+
+use super::{
+    curve::WallCurve,
+    wall_builder::{cut_openings, WallBuilder},
+    wall_constructor::{brick_rng, to_world_space, Brick, BrickLayout},
+    wall_junction::WallJoints,
+    wall_opening::WallOpening,
+    wall_style::WallStyle,
+};
+
+// Blobs are about this large, and overlap so the hedge looks filled in
+const BLOB_SIZE: f32 = 0.45;
+const BLOB_SPACING: f32 = 0.3;
+// Blobs are scattered this far in front and behind the curve
+const BLOB_SCATTER: f32 = 0.15;
+// Blobs are tilted by at most this, in radians
+const MAX_BLOB_TILT: f32 = 0.5;
+
+// Foliage blobs scattered over the height of the wall, the top ones are left out like bricks of the top row.
+// Joints only matter to brick walls, hedges just overlap where they meet
+pub struct Hedge;
+
+impl WallBuilder for Hedge {
+    fn build_after(
+        &self,
+        curve: &dyn WallCurve,
+        style: &WallStyle,
+        layout: &BrickLayout,
+        _joints: &WallJoints,
+        openings: &[WallOpening],
+        _reusable_length: f32,
+    ) -> (usize, Vec<Brick>) {
+        let wall_length = curve.length();
+        let height = style.wall_height;
+        let rows = (height / BLOB_SPACING).ceil().max(1.0) as usize;
+        let columns = (wall_length / BLOB_SPACING).ceil().max(1.0) as usize + 1;
+
+        let mut blobs = Vec::new();
+        for row in 0..rows {
+            for column in 0..columns {
+                let rng = brick_rng(layout.seed, row, column);
+                if row == rows - 1 && rng.f32() < style.top_row_skip_chance {
+                    continue;
+                }
+
+                let size = BLOB_SIZE * (0.7 + rng.f32() * 0.6);
+                let x = (column as f32 + (rng.f32() - 0.5) * 0.6) * BLOB_SPACING;
+                let x = x.clamp(0.0, wall_length);
+                // the bottom blobs sit on the ground, the top ones don't go much past the wall height
+                let y = ((row as f32 + 0.5) * BLOB_SPACING + (rng.f32() - 0.5) * 0.1)
+                    .min(height - size / 4.0)
+                    .max(size / 2.0);
+                let offset = (rng.f32() - 0.5) * 2.0 * BLOB_SCATTER;
+                let rotation = Quat::from_rotation_y((rng.f32() - 0.5) * 2.0 * MAX_BLOB_TILT)
+                    * Quat::from_rotation_z((rng.f32() - 0.5) * 2.0 * MAX_BLOB_TILT);
+
+                let pivot_u = x / wall_length;
+                blobs.push(Brick {
+                    pivot_uv: Vec2::new(pivot_u, y / height),
+                    bounds_uv: Vec2::new(size / wall_length, size / height),
+                    transform: Transform {
+                        translation: Vec3::new(x, 0.0, offset),
+                        rotation,
+                        scale: Vec3::new(size, size * 0.8, size * 1.2),
+                    },
+                });
+            }
+        }
+
+        cut_openings(&mut blobs, openings, style, wall_length);
+        to_world_space(curve, &mut blobs, height);
+        (0, blobs)
+    }
+
+    fn tint(&self) -> [f32; 3] {
+        [0.45, 1.2, 0.35]
+    }
+
+    fn has_arches(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,295 @@
+use glam::Vec3;
+
+// Offset of the central differences the hit normal is computed from, in meters
+const NORMAL_EPSILON: f32 = 0.01;
+
+// How a ray is marched through a heightfield, distances are in meters
+#[derive(Clone, Copy, Debug)]
+pub struct RaycastSettings {
+    // steps never get shorter than this, features thinner than it can be missed
+    pub min_step: f32,
+    pub max_step: f32,
+    // steepest slope (rise over run) the heightfield is expected to have, the steps are sized so that
+    // they can't go through slopes up to this one
+    pub max_slope: f32,
+    // the ray gives up after this distance from its origin, which matters at grazing angles
+    pub max_distance: f32,
+    // binary search stops once the hit is known to within this distance along the ray
+    pub tolerance: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    pub position: Vec3,
+    pub normal: Vec3,
+    // along the ray, from its origin
+    pub distance: f32,
+}
+
+// First point where the ray goes below `height_at(x, z)`, or `None` if it doesn't within `settings.max_distance`,
+// e.g. when it points at the sky. `height_range` bounds the heightfield, the ray is only marched inside of it.
+// Steps are long where the ray is high above the heightfield and get shorter as it gets closer, then the crossing
+// between the last step above and the first step below is found by binary search
+pub fn raycast_heightfield(
+    origin: Vec3,
+    direction: Vec3,
+    height_range: (f32, f32),
+    settings: &RaycastSettings,
+    height_at: impl Fn(f32, f32) -> f32,
+) -> Option<RayHit> {
+    let direction = direction.normalize();
+    let (t_enter, t_exit) = slab_interval(origin, direction, height_range)?;
+
+    let t_exit = t_exit.min(settings.max_distance);
+    if t_enter > t_exit {
+        return None;
+    }
+
+    let gap_at = |t: f32| {
+        let p = origin + direction * t;
+        p.y - height_at(p.x, p.z)
+    };
+
+    // how fast the gap between the ray and the heightfield can shrink, per meter along the ray
+    let closing_speed =
+        (-direction.y).max(0.0) + settings.max_slope * direction.x.hypot(direction.z);
+
+    let mut t = t_enter;
+    let mut gap = gap_at(t);
+    if gap <= 0.0 {
+        // starts under the heightfield
+        return Some(hit_at(origin, direction, t, &height_at));
+    }
+
+    loop {
+        let step = if closing_speed > 0.0 {
+            (gap / closing_speed).clamp(settings.min_step, settings.max_step)
+        } else {
+            settings.max_step
+        };
+
+        let next_t = t + step;
+        if next_t > t_exit {
+            // the heightfield can still be crossed before the ray leaves the bounds
+            if t < t_exit && gap_at(t_exit) <= 0.0 {
+                return Some(refine(origin, direction, t, t_exit, settings, &height_at));
+            }
+            return None;
+        }
+
+        let next_gap = gap_at(next_t);
+        if next_gap <= 0.0 {
+            return Some(refine(origin, direction, t, next_t, settings, &height_at));
+        }
+
+        t = next_t;
+        gap = next_gap;
+    }
+}
+
+// Distances along the ray between which it's within `height_range`, `None` if it never is ahead of the origin
+fn slab_interval(origin: Vec3, direction: Vec3, height_range: (f32, f32)) -> Option<(f32, f32)> {
+    let (min_y, max_y) = height_range;
+
+    if direction.y.abs() < f32::EPSILON {
+        return if (min_y..=max_y).contains(&origin.y) {
+            Some((0.0, f32::INFINITY))
+        } else {
+            None
+        };
+    }
+
+    let (t_min, t_max) = (
+        (min_y - origin.y) / direction.y,
+        (max_y - origin.y) / direction.y,
+    );
+    let (t_enter, t_exit) = (t_min.min(t_max).max(0.0), t_min.max(t_max));
+
+    if t_exit < 0.0 {
+        None
+    } else {
+        Some((t_enter, t_exit))
+    }
+}
+
+// `above` is a distance where the ray is above the heightfield, `below` one where it's under it
+fn refine(
+    origin: Vec3,
+    direction: Vec3,
+    mut above: f32,
+    mut below: f32,
+    settings: &RaycastSettings,
+    height_at: &impl Fn(f32, f32) -> f32,
+) -> RayHit {
+    while below - above > settings.tolerance {
+        let t = (above + below) * 0.5;
+        let p = origin + direction * t;
+        if p.y > height_at(p.x, p.z) {
+            above = t;
+        } else {
+            below = t;
+        }
+    }
+
+    hit_at(origin, direction, (above + below) * 0.5, height_at)
+}
+
+fn hit_at(origin: Vec3, direction: Vec3, t: f32, height_at: &impl Fn(f32, f32) -> f32) -> RayHit {
+    let p = origin + direction * t;
+    let position = Vec3::new(p.x, height_at(p.x, p.z), p.z);
+
+    let dx = height_at(p.x + NORMAL_EPSILON, p.z) - height_at(p.x - NORMAL_EPSILON, p.z);
+    let dz = height_at(p.x, p.z + NORMAL_EPSILON) - height_at(p.x, p.z - NORMAL_EPSILON);
+    let normal = Vec3::new(-dx, 2.0 * NORMAL_EPSILON, -dz).normalize();
+
+    RayHit {
+        position,
+        normal,
+        distance: t,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: RaycastSettings = RaycastSettings {
+        min_step: 0.05,
+        max_step: 5.0,
+        max_slope: 2.0,
+        max_distance: 500.0,
+        tolerance: 1e-4,
+    };
+
+    fn assert_close(a: Vec3, b: Vec3, epsilon: f32) {
+        assert!(a.distance(b) < epsilon, "{} is not close to {}", a, b);
+    }
+
+    #[test]
+    fn hits_plane() {
+        let hit = raycast_heightfield(
+            Vec3::new(1.0, 10.0, 2.0),
+            Vec3::new(1.0, -1.0, 0.0),
+            (0.0, 0.0),
+            &SETTINGS,
+            |_, _| 0.0,
+        )
+        .unwrap();
+
+        assert_close(hit.position, Vec3::new(11.0, 0.0, 2.0), 1e-3);
+        assert_close(hit.normal, Vec3::Y, 1e-5);
+        assert!((hit.distance - 200f32.sqrt()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn hits_slope() {
+        // y = 0.5 * x, the ray comes straight down
+        let hit = raycast_heightfield(
+            Vec3::new(4.0, 20.0, -3.0),
+            -Vec3::Y,
+            (-10.0, 10.0),
+            &SETTINGS,
+            |x, _| 0.5 * x,
+        )
+        .unwrap();
+
+        assert_close(hit.position, Vec3::new(4.0, 2.0, -3.0), 1e-3);
+        assert_close(hit.normal, Vec3::new(-0.5, 1.0, 0.0).normalize(), 1e-4);
+        assert!((hit.distance - 18.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn hits_sphere_cap() {
+        // upper half of a sphere of radius 5 around the origin, on a flat ground
+        let radius: f32 = 5.0;
+        let dome = |x: f32, z: f32| (radius * radius - x * x - z * z).max(0.0).sqrt();
+
+        let origin = Vec3::new(-20.0, 3.0, 0.0);
+        let hit = raycast_heightfield(origin, Vec3::X, (0.0, radius), &SETTINGS, dome).unwrap();
+
+        // the ray is horizontal, so it hits where the sphere is 3 m high
+        let x = -(radius * radius - 9.0).sqrt();
+        assert_close(hit.position, Vec3::new(x, 3.0, 0.0), 1e-2);
+        assert_close(hit.normal, Vec3::new(x, 3.0, 0.0) / radius, 1e-2);
+        assert!((hit.distance - (x - origin.x)).abs() < 1e-2);
+    }
+
+    #[test]
+    fn finds_first_crossing_at_grazing_angle() {
+        // waves that a fixed step of the ray's length through the bounds would jump over
+        let waves = |x: f32, _: f32| (x * 0.5).sin();
+
+        let origin = Vec3::new(0.0, 1.0, 0.0);
+        let direction = Vec3::new(1.0, -0.002, 0.0);
+        let hit = raycast_heightfield(origin, direction, (-1.0, 1.0), &SETTINGS, waves).unwrap();
+
+        // marching finely enough to not miss anything
+        let direction = direction.normalize();
+        let mut t = 0.0;
+        while (origin + direction * t).y > waves((origin + direction * t).x, 0.0) {
+            t += 1e-4;
+        }
+
+        assert!((hit.distance - t).abs() < 1e-2, "{} != {}", hit.distance, t);
+        assert!((hit.position.y - waves(hit.position.x, 0.0)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn misses_when_looking_at_the_sky() {
+        let hit = raycast_heightfield(
+            Vec3::new(0.0, 5.0, 0.0),
+            Vec3::new(0.3, 1.0, 0.2),
+            (-1.0, 1.0),
+            &SETTINGS,
+            |x, z| (x + z).sin(),
+        );
+        assert_eq!(hit, None);
+    }
+
+    #[test]
+    fn misses_when_parallel_above_the_heightfield() {
+        let hit = raycast_heightfield(
+            Vec3::new(0.0, 2.0, 0.0),
+            Vec3::Z,
+            (-1.0, 1.0),
+            &SETTINGS,
+            |x, z| (x * z).cos(),
+        );
+        assert_eq!(hit, None);
+    }
+
+    #[test]
+    fn misses_beyond_max_distance() {
+        let hit = raycast_heightfield(
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, -0.001, 0.0),
+            (0.0, 0.0),
+            &SETTINGS,
+            |_, _| 0.0,
+        );
+        assert_eq!(hit, None);
+    }
+
+    #[test]
+    fn starts_under_the_heightfield() {
+        let hit = raycast_heightfield(
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, -1.0, 1.0),
+            (0.0, 0.0),
+            &SETTINGS,
+            |_, _| 0.0,
+        );
+        assert_eq!(hit, None);
+
+        let hit = raycast_heightfield(
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::X,
+            (-2.0, 2.0),
+            &SETTINGS,
+            |_, _| 0.0,
+        )
+        .unwrap();
+        assert_close(hit.position, Vec3::new(0.0, 0.0, 0.0), 1e-5);
+        assert_eq!(hit.distance, 0.0);
+    }
+}
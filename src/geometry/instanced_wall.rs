@@ -2,19 +2,36 @@ use glam::Mat4;
 
 use crate::render::ssbo::GLShaderStorageBuffer;
 
-use super::wall_constructor::Brick;
+use super::{
+    curve::{Curve, WallCurve},
+    wall_constructor::{Brick, BrickLayout},
+    wall_junction::WallJoints,
+    wall_opening::WallOpening,
+    wall_style::WallStyle,
+};
 
-const SSBO_BUFFER_SIZE: usize = 10000;
+// The instance buffer starts this large, and grows for longer walls
+const SSBO_BUFFER_SIZE: usize = 1000;
 const SSBO_BINDING_POINT: u32 = 2;
 
 #[repr(C)]
 pub struct InstancedWall {
-    pub wall_length: f32,
+    pub wall_height: f32,
+    // see `WallBuilder::tint`
+    pub tint: [f32; 3],
     pub instance_buffer: GLShaderStorageBuffer<BrickTransformSSBO>,
+
+    // what the bricks in `instance_buffer` were constructed from, `curve` is the polyline of the wall's shape
+    curve: Curve,
+    style: WallStyle,
+    layout: BrickLayout,
+    joints: WallJoints,
+    openings: Vec<WallOpening>,
 }
 
 impl InstancedWall {
-    fn instanced_wall_data(bricks: Vec<Brick>) -> Vec<BrickTransformSSBO> {
+    // Curve space X is stored in meters instead of u, so bricks that were kept when the wall got longer stay valid
+    fn instanced_wall_data(wall_length: f32, bricks: Vec<Brick>) -> Vec<BrickTransformSSBO> {
         bricks
             .iter()
             .map(|b| {
@@ -23,27 +40,99 @@ impl InstancedWall {
 
                 BrickTransformSSBO {
                     transform: b.transform.compute_matrix(),
-                    curve_uv_bbx_minmax: [min.x, min.y, max.x, max.y],
+                    curve_uv_bbx_minmax: [min.x * wall_length, min.y, max.x * wall_length, max.y],
                 }
             })
             .collect()
     }
 
-    pub fn from(curve_length: f32, bricks: Vec<Brick>) -> Self {
+    // All the bricks of a wall, the way they're first constructed
+    pub fn bricks(
+        curve: &dyn WallCurve,
+        style: &WallStyle,
+        layout: &BrickLayout,
+        joints: &WallJoints,
+        openings: &[WallOpening],
+    ) -> Vec<Brick> {
+        let bricks = style
+            .construction
+            .builder()
+            .build(curve, style, layout, joints, openings);
+        if bricks.is_empty() {
+            log::warn!("WallConstructor returned empty wall");
+        }
+        bricks
+    }
+
+    pub fn from(
+        curve: &dyn WallCurve,
+        style: &WallStyle,
+        layout: &BrickLayout,
+        joints: &WallJoints,
+        openings: &[WallOpening],
+    ) -> Self {
+        let builder = style.construction.builder();
+        let bricks = Self::bricks(curve, style, layout, joints, openings);
+
         Self {
-            wall_length: curve_length,
+            wall_height: style.wall_height,
+            tint: builder.tint(),
             instance_buffer: GLShaderStorageBuffer::<BrickTransformSSBO>::new(
-                &Self::instanced_wall_data(bricks),
+                &Self::instanced_wall_data(curve.length(), bricks),
                 SSBO_BUFFER_SIZE,
                 SSBO_BINDING_POINT,
             ),
+            curve: curve.polyline().into_owned(),
+            style: *style,
+            layout: *layout,
+            joints: joints.clone(),
+            openings: openings.to_vec(),
         }
     }
 
-    pub fn update(&mut self, curve_length: f32, bricks: Vec<Brick>) {
-        self.wall_length = curve_length;
+    // Only re-constructs and uploads the bricks past the part of the curve that didn't change
+    pub fn update(
+        &mut self,
+        curve: &dyn WallCurve,
+        style: &WallStyle,
+        layout: &BrickLayout,
+        joints: &WallJoints,
+        openings: &[WallOpening],
+    ) {
+        let builder = style.construction.builder();
+        let polyline = curve.polyline();
+        let reusable_length =
+            if *style == self.style && *layout == self.layout && openings == self.openings {
+                builder.reusable_length(
+                    style,
+                    layout,
+                    self.curve.length,
+                    &self.joints,
+                    curve.length(),
+                    joints,
+                    openings,
+                    self.curve.common_length(&polyline),
+                )
+            } else {
+                0.0
+            };
+
+        let (kept, bricks) =
+            builder.build_after(curve, style, layout, joints, openings, reusable_length);
+        if kept + bricks.len() == 0 {
+            log::warn!("WallConstructor returned empty wall");
+        }
+
+        self.wall_height = style.wall_height;
+        self.tint = builder.tint();
         self.instance_buffer
-            .update(&Self::instanced_wall_data(bricks));
+            .update_after(kept, &Self::instanced_wall_data(curve.length(), bricks));
+
+        self.curve = polyline.into_owned();
+        self.style = *style;
+        self.layout = *layout;
+        self.joints = joints.clone();
+        self.openings = openings.to_vec();
     }
 }
 
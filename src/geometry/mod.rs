@@ -1,6 +1,10 @@
 pub mod cube;
-pub mod curve;
 pub mod instanced_wall;
 pub mod plane;
 pub mod shadow_decal;
-pub mod wall_constructor;
+
+// GPU-free geometry lives in the library
+pub use country_slice::geometry::{
+    arch_style, arches, chunks, curve, heightfield_raycast, path_mask, shadow_decal_mesh, spline_curve,
+    terrain_settings, wall_constructor, wall_junction, wall_opening, wall_style,
+};
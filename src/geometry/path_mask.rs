@@ -0,0 +1,137 @@
+use std::f32::consts::TAU;
+
+use glam::{Vec2, Vec3};
+
+use super::curve::Curve;
+
+// Greyscale path mask, one value per pixel, 1.0 wherever a path covers the pixel.
+// `paths` are (centerline, width) pairs, the mask covers the XZ rectangle from `ws_min`, `ws_size` large.
+// Pixels map to world space the same way the mask is read in the arches and curve segments passes
+pub fn rasterize_paths(
+    paths: &[(&Curve, f32)],
+    dims: (i32, i32),
+    ws_min: Vec2,
+    ws_size: Vec2,
+) -> Vec<f32> {
+    let mut pixels = vec![0.0; (dims.0 * dims.1) as usize];
+
+    let pixel_size = ws_size / Vec2::new(dims.0 as f32, dims.1 as f32);
+    let to_ws = |x: i32, y: i32| ws_min + Vec2::new(x as f32, y as f32) * pixel_size;
+    let to_pixel = |p: Vec2| (p - ws_min) / pixel_size;
+
+    for (curve, width) in paths {
+        let radius = width / 2.0;
+        let points: Vec<Vec2> = curve.points.iter().map(|p| Vec2::new(p.x, p.z)).collect();
+
+        // a path that was just started has a single point, it's drawn as a dot
+        let segments: Vec<(Vec2, Vec2)> = match points.as_slice() {
+            [] => Vec::new(),
+            [p] => vec![(*p, *p)],
+            _ => points.windows(2).map(|s| (s[0], s[1])).collect(),
+        };
+
+        for (a, b) in segments {
+            let min = to_pixel(a.min(b) - Vec2::splat(radius)).floor();
+            let max = to_pixel(a.max(b) + Vec2::splat(radius)).ceil();
+            let (x0, y0) = ((min.x as i32).max(0), (min.y as i32).max(0));
+            let (x1, y1) = (
+                (max.x as i32).min(dims.0 - 1),
+                (max.y as i32).min(dims.1 - 1),
+            );
+
+            let dir = b - a;
+            for y in y0..=y1 {
+                for x in x0..=x1 {
+                    let p = to_ws(x, y);
+                    let t =
+                        ((p - a).dot(dir) / dir.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+                    if p.distance(a + dir * t) <= radius {
+                        pixels[(y * dims.0 + x) as usize] = 1.0;
+                    }
+                }
+            }
+        }
+    }
+
+    pixels
+}
+
+// Settings of `shaders/blur.comp`
+const BLUR_DIRECTIONS: f32 = 30.0;
+const BLUR_QUALITY: f32 = 10.0;
+// in pixels
+pub const BLUR_SIZE: f32 = 10.0;
+
+// Path mask covering the XZ rectangle from `ws_min`, `ws_size` large, e.g. the tiles of the chunk window.
+// Reads the same way as the mask texture does in the compute shaders
+#[derive(Clone)]
+pub struct PathMaskImage {
+    pub dims: (i32, i32),
+    // greyscale, row by row
+    pub pixels: Vec<f32>,
+    pub ws_min: Vec2,
+    pub ws_size: Vec2,
+}
+
+impl PathMaskImage {
+    pub fn new(dims: (i32, i32), ws_min: Vec2, ws_size: Vec2) -> Self {
+        Self {
+            dims,
+            pixels: vec![0.0; (dims.0 * dims.1) as usize],
+            ws_min,
+            ws_size,
+        }
+    }
+
+    pub fn from_paths(
+        paths: &[(&Curve, f32)],
+        dims: (i32, i32),
+        ws_min: Vec2,
+        ws_size: Vec2,
+    ) -> Self {
+        Self {
+            dims,
+            pixels: rasterize_paths(paths, dims, ws_min, ws_size),
+            ws_min,
+            ws_size,
+        }
+    }
+
+    // Like `imageLoad`, pixels outside of the image are 0
+    pub fn load(&self, x: i32, y: i32) -> f32 {
+        if x < 0 || y < 0 || x >= self.dims.0 || y >= self.dims.1 {
+            return 0.0;
+        }
+        self.pixels[(y * self.dims.0 + x) as usize]
+    }
+
+    // Pixel under a world space position, `ws_pos_to_pixel_coord` in the shaders
+    pub fn pixel_coord(&self, position: Vec3) -> (i32, i32) {
+        let uv = (Vec2::new(position.x, position.z) - self.ws_min) / self.ws_size;
+        (
+            (uv.x * self.dims.0 as f32) as i32,
+            (uv.y * self.dims.1 as f32) as i32,
+        )
+    }
+
+    // Pixel of the blurred mask, computed the same way as `shaders/blur.comp` does, loop counters included
+    pub fn load_blurred(&self, x: i32, y: i32) -> f32 {
+        let dims = Vec2::new(self.dims.0 as f32, self.dims.1 as f32);
+        let radius = Vec2::splat(BLUR_SIZE) / dims;
+        let uv = Vec2::new(x as f32 / dims.x, y as f32 / dims.y);
+
+        let mut color = self.load(x, y);
+        let mut d = 0.0_f32;
+        while d < TAU {
+            let mut i = 1.0 / BLUR_QUALITY;
+            while i <= 1.0 {
+                let sample = uv + Vec2::new(d.cos(), d.sin()) * radius * i;
+                color += self.load((sample.x * dims.x) as i32, (sample.y * dims.y) as i32);
+                i += 1.0 / BLUR_QUALITY;
+            }
+            d += TAU / BLUR_DIRECTIONS;
+        }
+
+        color / (BLUR_QUALITY * BLUR_DIRECTIONS - 15.0)
+    }
+}
@@ -0,0 +1,207 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+
+use super::curve::{Curve, WallCurve};
+
+// Control points are picked from the raw stroke at least this far apart
+pub const SPLINE_CONTROL_POINT_SPACING: f32 = 0.4;
+// Spacing of the polyline that approximates the spline
+pub const SPLINE_POLYLINE_SPACING: f32 = 0.2;
+
+// Arc length is measured on this many steps per span
+const ARC_LENGTH_STEPS: usize = 16;
+
+// Uniform Catmull-Rom spline, it passes through all of its control points, so it doesn't shrink the stroke like `Curve::smooth`
+#[derive(Clone)]
+pub struct SplineCurve {
+    pub control_points: Vec<Vec3>,
+    // arc length at every step of every span, starts with 0.0
+    arc_lengths: Vec<f32>,
+}
+
+impl SplineCurve {
+    pub fn from(control_points: Vec<Vec3>) -> Self {
+        let mut spline = Self {
+            control_points,
+            arc_lengths: vec![0.0],
+        };
+
+        let mut length_traveled = 0.0;
+        for span in 0..spline.spans() {
+            let mut prev = spline.pos(span, 0.0);
+            for step in 1..=ARC_LENGTH_STEPS {
+                let p = spline.pos(span, step as f32 / ARC_LENGTH_STEPS as f32);
+                length_traveled += (p - prev).length();
+                spline.arc_lengths.push(length_traveled);
+                prev = p;
+            }
+        }
+
+        spline
+    }
+
+    // Fits a spline to a raw stroke. The first points are picked the same way as long as the stroke only grows,
+    // so the beginning of the spline doesn't change while the user keeps drawing
+    pub fn fit(points: &[Vec3], spacing: f32) -> Self {
+        let mut control_points: Vec<Vec3> = Vec::new();
+        for p in points {
+            match control_points.last() {
+                Some(last) if last.distance(*p) < spacing => {}
+                _ => control_points.push(*p),
+            }
+        }
+
+        // always end at the last point of the stroke
+        if let (Some(last), Some(last_control)) = (points.last(), control_points.last()) {
+            if last != last_control {
+                if control_points.len() > 1 && last.distance(*last_control) < spacing / 2.0 {
+                    control_points.pop();
+                }
+                control_points.push(*last);
+            }
+        }
+
+        Self::from(control_points)
+    }
+
+    // Spline through the part of this one from `from` to `to` along it. It keeps the control points in between,
+    // except the ones too close to the new ends
+    pub fn slice(&self, from: f32, to: f32) -> Self {
+        let (start, end) = (self.get_pos_at_distance(from), self.get_pos_at_distance(to));
+        let min_distance = SPLINE_CONTROL_POINT_SPACING / 2.0;
+
+        let mut control_points = vec![start];
+        for (i, p) in self.control_points.iter().enumerate() {
+            let distance = self.arc_lengths[(i * ARC_LENGTH_STEPS).min(self.arc_lengths.len() - 1)];
+            if distance > from + min_distance && distance < to - min_distance {
+                control_points.push(*p);
+            }
+        }
+        control_points.push(end);
+
+        Self::from(control_points)
+    }
+
+    fn spans(&self) -> usize {
+        self.control_points.len().saturating_sub(1)
+    }
+
+    // Control points of a span, the ends are extended by reflecting their neighbour
+    fn span_points(&self, span: usize) -> [Vec3; 4] {
+        let p = &self.control_points;
+        let p1 = p[span];
+        let p2 = p[span + 1];
+        let p0 = if span > 0 { p[span - 1] } else { 2.0 * p1 - p2 };
+        let p3 = p.get(span + 2).copied().unwrap_or(2.0 * p2 - p1);
+        [p0, p1, p2, p3]
+    }
+
+    fn pos(&self, span: usize, t: f32) -> Vec3 {
+        let [p0, p1, p2, p3] = self.span_points(span);
+        let (t2, t3) = (t * t, t * t * t);
+
+        0.5 * (2.0 * p1
+            + (p2 - p0) * t
+            + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+            + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+    }
+
+    fn derivative(&self, span: usize, t: f32) -> Vec3 {
+        let [p0, p1, p2, p3] = self.span_points(span);
+
+        0.5 * ((p2 - p0)
+            + 2.0 * (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t
+            + 3.0 * (3.0 * p1 - p0 - 3.0 * p2 + p3) * t * t)
+    }
+
+    // Span and its parameter at a distance along the spline
+    fn span_at_distance(&self, distance: f32) -> (usize, f32) {
+        let distance = distance.clamp(0.0, self.length());
+
+        // first step that ends at or past `distance`
+        let step = self
+            .arc_lengths
+            .partition_point(|l| *l < distance)
+            .clamp(1, self.arc_lengths.len() - 1);
+        let (start, end) = (self.arc_lengths[step - 1], self.arc_lengths[step]);
+        let step_t = if end > start {
+            (distance - start) / (end - start)
+        } else {
+            0.0
+        };
+
+        let global_step = (step - 1) as f32 + step_t;
+        let span = ((step - 1) / ARC_LENGTH_STEPS).min(self.spans() - 1);
+        let t = global_step / ARC_LENGTH_STEPS as f32 - span as f32;
+
+        (span, t.clamp(0.0, 1.0))
+    }
+}
+
+impl WallCurve for SplineCurve {
+    fn length(&self) -> f32 {
+        *self.arc_lengths.last().unwrap()
+    }
+
+    fn get_pos_at_distance(&self, distance: f32) -> Vec3 {
+        if self.spans() == 0 {
+            return self.control_points.first().copied().unwrap_or(Vec3::ZERO);
+        }
+
+        let (span, t) = self.span_at_distance(distance);
+        self.pos(span, t)
+    }
+
+    fn get_tangent_at_distance(&self, distance: f32) -> Vec3 {
+        if self.spans() == 0 {
+            return Vec3::X;
+        }
+
+        let (span, t) = self.span_at_distance(distance);
+        self.derivative(span, t).normalize()
+    }
+
+    fn polyline(&self) -> Cow<'_, Curve> {
+        if self.spans() == 0 {
+            return Cow::Owned(Curve::from(self.control_points.clone()));
+        }
+
+        let dense: Vec<Vec3> = (0..self.spans())
+            .flat_map(|span| {
+                (0..ARC_LENGTH_STEPS).map(move |step| (span, step as f32 / ARC_LENGTH_STEPS as f32))
+            })
+            .map(|(span, t)| self.pos(span, t))
+            .chain(self.control_points.last().copied())
+            .collect();
+
+        Cow::Owned(Curve::from(dense).resample(SPLINE_POLYLINE_SPACING))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slices_follow_the_spline() {
+        let spline = SplineCurve::from(
+            (0..=10)
+                .map(|i| Vec3::new(i as f32, 0.0, (i as f32 * 0.7).sin()))
+                .collect(),
+        );
+        let (from, to) = (2.5, spline.length() - 3.0);
+        let slice = spline.slice(from, to);
+
+        assert!((slice.length() - (to - from)).abs() < 0.05);
+        for i in 0..=20 {
+            let distance = slice.length() * i as f32 / 20.0;
+            let original = spline.get_pos_at_distance(from + distance);
+            assert!(
+                slice.get_pos_at_distance(distance).distance(original) < 0.05,
+                "{}",
+                distance
+            );
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use serde_json::{json, Value};
+
+// Noise the terrain heights are generated from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoiseKind {
+    Perlin,
+    Simplex,
+    // a random height per cell, good for plateaus. Octaves, gain and lacunarity don't apply to it
+    Cellular,
+    Value,
+}
+
+impl NoiseKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NoiseKind::Perlin => "perlin",
+            NoiseKind::Simplex => "simplex",
+            NoiseKind::Cellular => "cellular",
+            NoiseKind::Value => "value",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        [
+            NoiseKind::Perlin,
+            NoiseKind::Simplex,
+            NoiseKind::Cellular,
+            NoiseKind::Value,
+        ]
+        .iter()
+        .copied()
+        .find(|kind| kind.name() == name)
+        .ok_or(format!(
+            "Terrain settings: unknown noise `{}`, expected perlin, simplex, cellular or value",
+            name
+        ))
+    }
+}
+
+// Everything the procedural terrain is generated from, see `TerrainData`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TerrainSettings {
+    pub noise: NoiseKind,
+    pub seed: u64,
+
+    // layers of noise added together, 1 is a single layer
+    pub octaves: u32,
+    // amplitude of every octave relative to the previous one
+    pub gain: f32,
+    // frequency of every octave relative to the previous one
+    pub lacunarity: f32,
+    // of the first octave, per meter
+    pub frequency: f32,
+    // heights go roughly from -amplitude to amplitude, in meters
+    pub amplitude: f32,
+
+    // how far another noise pushes the sampled positions around, in meters. 0 turns warping off
+    pub warp_amplitude: f32,
+    pub warp_frequency: f32,
+
+    // height of a terrace step, in meters. 0 turns terracing off
+    pub terrace_height: f32,
+    // 0 keeps the slopes as they are, towards 1 terraces get flatter tops and steeper edges
+    pub terrace_sharpness: f32,
+}
+
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        Self {
+            noise: NoiseKind::Perlin,
+            seed: 0,
+            octaves: 3,
+            gain: 1.0,
+            lacunarity: 3.0,
+            frequency: 0.05,
+            amplitude: 1.3,
+            warp_amplitude: 0.0,
+            warp_frequency: 0.05,
+            terrace_height: 0.0,
+            terrace_sharpness: 0.5,
+        }
+    }
+}
+
+impl TerrainSettings {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "noise": self.noise.name(),
+            "seed": self.seed,
+            "octaves": self.octaves,
+            "gain": self.gain,
+            "lacunarity": self.lacunarity,
+            "frequency": self.frequency,
+            "amplitude": self.amplitude,
+            "warp_amplitude": self.warp_amplitude,
+            "warp_frequency": self.warp_frequency,
+            "terrace_height": self.terrace_height,
+            "terrace_sharpness": self.terrace_sharpness,
+        })
+    }
+
+    // Missing fields fall back to the default settings, so presets only need to list what they change
+    pub fn from_json(v: &Value) -> Result<Self, String> {
+        if !v.is_object() {
+            return Err(format!("Terrain settings: expected an object, got {}", v));
+        }
+
+        let mut settings = Self::default();
+        if let Some(noise) = v.get("noise") {
+            settings.noise = NoiseKind::from_name(
+                noise
+                    .as_str()
+                    .ok_or("Terrain settings: `noise` is not a string")?,
+            )?;
+        }
+
+        if let Some(value) = v.get("seed") {
+            settings.seed = value
+                .as_u64()
+                .ok_or("Terrain settings: `seed` is not a positive integer")?;
+        }
+
+        if let Some(value) = v.get("octaves") {
+            settings.octaves = value
+                .as_u64()
+                .ok_or("Terrain settings: `octaves` is not a positive integer")?
+                as u32;
+        }
+
+        for (name, field) in [
+            ("gain", &mut settings.gain),
+            ("lacunarity", &mut settings.lacunarity),
+            ("frequency", &mut settings.frequency),
+            ("amplitude", &mut settings.amplitude),
+            ("warp_amplitude", &mut settings.warp_amplitude),
+            ("warp_frequency", &mut settings.warp_frequency),
+            ("terrace_height", &mut settings.terrace_height),
+            ("terrace_sharpness", &mut settings.terrace_sharpness),
+        ] {
+            if let Some(value) = v.get(name) {
+                *field = value
+                    .as_f64()
+                    .ok_or(format!("Terrain settings: `{}` is not a number", name))?
+                    as f32;
+            }
+        }
+
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.octaves == 0 {
+            return Err(String::from(
+                "Terrain settings: there has to be at least one octave",
+            ));
+        }
+
+        if self.frequency <= 0.0 || self.warp_frequency <= 0.0 {
+            return Err(String::from(
+                "Terrain settings: frequencies must be positive",
+            ));
+        }
+
+        if self.warp_amplitude < 0.0 || self.terrace_height < 0.0 {
+            return Err(String::from(
+                "Terrain settings: warp amplitude and terrace height can't be negative",
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&self.terrace_sharpness) {
+            return Err(format!(
+                "Terrain settings: terrace sharpness {} is outside of 0..1",
+                self.terrace_sharpness
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Snaps `height` towards the terrace steps
+    pub fn terrace(&self, height: f32) -> f32 {
+        if self.terrace_height <= 0.0 {
+            return height;
+        }
+
+        let steps = height / self.terrace_height;
+        let step = steps.floor();
+        // the higher the exponent, the longer the heights stay at the bottom of the step before climbing to the next one
+        let t = (steps - step).powf(1.0 + self.terrace_sharpness * 7.0);
+        (step + t) * self.terrace_height
+    }
+}
+
+// Named settings, loaded from and saved to a JSON file of the form { "presets": [ { "name": "...", <TerrainSettings fields> }, ... ] }
+pub struct TerrainSettingsPresets {
+    pub presets: Vec<(String, TerrainSettings)>,
+}
+
+impl TerrainSettingsPresets {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|err| format!("Terrain presets: couldn't read {}: {}", path, err))?;
+        let v: Value = serde_json::from_str(&data)
+            .map_err(|err| format!("Terrain presets: couldn't parse {}: {}", path, err))?;
+
+        let mut presets = Vec::new();
+        for preset in v["presets"]
+            .as_array()
+            .ok_or("Terrain presets: missing `presets`")?
+        {
+            let name = preset["name"]
+                .as_str()
+                .ok_or("Terrain presets: preset has no `name`")?;
+            let settings = TerrainSettings::from_json(preset)
+                .map_err(|err| format!("{} (preset '{}')", err, name))?;
+            presets.push((String::from(name), settings));
+        }
+
+        if presets.is_empty() {
+            return Err(format!("Terrain presets: {} has no presets", path));
+        }
+
+        Ok(Self { presets })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let presets: Vec<Value> = self
+            .presets
+            .iter()
+            .map(|(name, settings)| {
+                let mut preset = settings.to_json();
+                preset["name"] = json!(name);
+                preset
+            })
+            .collect();
+
+        let data = serde_json::to_string_pretty(&json!({ "presets": presets }))
+            .map_err(|err| format!("Terrain presets: couldn't serialize {}: {}", path, err))?;
+        std::fs::write(path, data)
+            .map_err(|err| format!("Terrain presets: couldn't write {}: {}", path, err))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TerrainSettings> {
+        self.presets
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, settings)| settings)
+    }
+}
@@ -0,0 +1,403 @@
+use fastrand::Rng;
+
+use super::{
+    curve::WallCurve,
+    dry_stone_wall::DryStoneWall,
+    fence::Fence,
+    hedge::Hedge,
+    wall_constructor::{curve_space_brick, Brick, BrickLayout, WallConstructor},
+    wall_junction::WallJoints,
+    wall_opening::*,
+    wall_style::WallStyle,
+};
+
+// At the far end of a ruin, this much of the wall height has crumbled away
+const RUIN_DROP: f32 = 0.6;
+// Meters between the bumps of the crumbled top of a ruin
+const RUIN_BUMP_LENGTH: f32 = 1.5;
+
+// How a wall is put together, part of its `WallStyle`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Construction {
+    // courses of bricks, see `WallConstructor`
+    Bricks,
+    // irregular stones, larger at the bottom, see `DryStoneWall`
+    DryStone,
+    // pickets on two rails between posts, see `Fence`
+    PicketFence,
+    // rails between posts
+    PostAndRail,
+    // overlapping foliage blobs, see `Hedge`
+    Hedge,
+    // courses of bricks that crumble further along the wall, see `RuinedWall`
+    Ruin,
+}
+
+impl Construction {
+    pub const ALL: [Construction; 6] = [
+        Construction::Bricks,
+        Construction::DryStone,
+        Construction::PicketFence,
+        Construction::PostAndRail,
+        Construction::Hedge,
+        Construction::Ruin,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Construction::Bricks => "bricks",
+            Construction::DryStone => "dry_stone",
+            Construction::PicketFence => "picket_fence",
+            Construction::PostAndRail => "post_and_rail",
+            Construction::Hedge => "hedge",
+            Construction::Ruin => "ruin",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|construction| construction.name() == name)
+            .ok_or(format!(
+                "Wall style: unknown construction `{}`, expected bricks, dry_stone, picket_fence, post_and_rail, hedge or ruin",
+                name
+            ))
+    }
+
+    pub fn next(&self) -> Self {
+        let i = Self::ALL
+            .iter()
+            .position(|construction| construction == self)
+            .unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn builder(&self) -> &'static dyn WallBuilder {
+        match self {
+            Construction::Bricks => &WallConstructor,
+            Construction::DryStone => &DryStoneWall,
+            Construction::PicketFence => &Fence { pickets: true },
+            Construction::PostAndRail => &Fence { pickets: false },
+            Construction::Hedge => &Hedge,
+            Construction::Ruin => &RuinedWall,
+        }
+    }
+}
+
+// Lays out the pieces of a wall along its curve. Every builder gives `Brick`s, so all walls are drawn (and exported)
+// the same way, with the brick mesh scaled and rotated per instance
+pub trait WallBuilder {
+    // Skips the bricks that start before `reusable_length` along the curve, see `WallConstructor::from_curve_after`
+    fn build_after(
+        &self,
+        curve: &dyn WallCurve,
+        style: &WallStyle,
+        layout: &BrickLayout,
+        joints: &WallJoints,
+        openings: &[WallOpening],
+        reusable_length: f32,
+    ) -> (usize, Vec<Brick>);
+
+    fn build(
+        &self,
+        curve: &dyn WallCurve,
+        style: &WallStyle,
+        layout: &BrickLayout,
+        joints: &WallJoints,
+        openings: &[WallOpening],
+    ) -> Vec<Brick> {
+        self.build_after(curve, style, layout, joints, openings, 0.0)
+            .1
+    }
+
+    // How far along the curve a previous build is still valid, see `WallConstructor::reusable_length`.
+    // By default walls are built again from the start
+    #[allow(clippy::too_many_arguments)]
+    fn reusable_length(
+        &self,
+        _style: &WallStyle,
+        _layout: &BrickLayout,
+        _previous_length: f32,
+        _previous_joints: &WallJoints,
+        _curve_length: f32,
+        _joints: &WallJoints,
+        _openings: &[WallOpening],
+        _unchanged_length: f32,
+    ) -> f32 {
+        0.0
+    }
+
+    // Multiplies the color of the bricks in the wall shader
+    fn tint(&self) -> [f32; 3] {
+        [1.0; 3]
+    }
+
+    // Whether arches are laid where the wall crosses a path
+    fn has_arches(&self) -> bool {
+        true
+    }
+}
+
+impl WallBuilder for WallConstructor {
+    fn build_after(
+        &self,
+        curve: &dyn WallCurve,
+        style: &WallStyle,
+        layout: &BrickLayout,
+        joints: &WallJoints,
+        openings: &[WallOpening],
+        reusable_length: f32,
+    ) -> (usize, Vec<Brick>) {
+        Self::from_curve_after(curve, style, layout, joints, openings, reusable_length)
+    }
+
+    fn reusable_length(
+        &self,
+        style: &WallStyle,
+        layout: &BrickLayout,
+        previous_length: f32,
+        previous_joints: &WallJoints,
+        curve_length: f32,
+        joints: &WallJoints,
+        openings: &[WallOpening],
+        unchanged_length: f32,
+    ) -> f32 {
+        Self::reusable_length(
+            style,
+            layout,
+            previous_length,
+            previous_joints,
+            curve_length,
+            joints,
+            openings,
+            unchanged_length,
+        )
+    }
+}
+
+// Brick wall whose top crumbles, more and more further along the curve
+pub struct RuinedWall;
+
+impl WallBuilder for RuinedWall {
+    fn build_after(
+        &self,
+        curve: &dyn WallCurve,
+        style: &WallStyle,
+        layout: &BrickLayout,
+        joints: &WallJoints,
+        openings: &[WallOpening],
+        _reusable_length: f32,
+    ) -> (usize, Vec<Brick>) {
+        let (_, mut bricks) =
+            WallConstructor::from_curve_after(curve, style, layout, joints, openings, 0.0);
+
+        // the top of the highest brick the style can have, so the start of the wall is whole
+        let wall_top = 1.0 + (style.brick_height + style.brick_height_variance) / style.wall_height;
+        let wall_length = curve.length();
+        bricks.retain(|b| {
+            let distance = b.pivot_uv.x * wall_length;
+            let crumbled = (b.pivot_uv.x
+                * (0.5 + value_noise(layout.seed, distance / RUIN_BUMP_LENGTH)))
+            .clamp(0.0, 1.0);
+            b.pivot_uv.y + b.bounds_uv.y / 2.0 <= wall_top - crumbled * RUIN_DROP
+        });
+
+        (0, bricks)
+    }
+}
+
+// Smooth noise in [0, 1], with a random value at every whole `x`
+fn value_noise(seed: u64, x: f32) -> f32 {
+    let x = x.max(0.0);
+    let (i, t) = (x.floor() as u64, x.fract());
+    let at = |i: u64| Rng::with_seed(seed ^ i.wrapping_mul(0x9E37_79B9_7F4A_7C15)).f32();
+    let t = t * t * (3.0 - 2.0 * t);
+    at(i) * (1.0 - t) + at(i + 1) * t
+}
+
+// For the builders that don't lay courses: leaves out the pieces that are in the openings and their posts, and adds
+// the posts and lintels. Lintels sit on top of the open part, there's no row for them to replace
+pub fn cut_openings(
+    bricks: &mut Vec<Brick>,
+    openings: &[WallOpening],
+    style: &WallStyle,
+    wall_length: f32,
+) {
+    let lintel_height = style.brick_height / style.wall_height;
+    bricks.retain(|b| {
+        let (u, v) = (b.pivot_uv.x * wall_length, b.pivot_uv.y);
+        !openings.iter().any(|o| {
+            let (from, to) = (o.from * wall_length, o.to * wall_length);
+            let (bottom, top) = o.kind.height_range();
+            let top = if o.lintel { top + lintel_height } else { top };
+            let in_opening = u > from - o.reach() && u < to + o.reach() && v > bottom && v < top;
+            let in_posts =
+                o.posts && ((u > from - POST_WIDTH && u < from) || (u > to && u < to + POST_WIDTH));
+            in_opening || in_posts
+        })
+    });
+
+    let post_height = 1.0 + POST_CAP / style.wall_height;
+    for opening in openings {
+        let (from, to) = (opening.from * wall_length, opening.to * wall_length);
+        let (_, top) = opening.kind.height_range();
+        if opening.lintel && top.is_finite() {
+            let overhang = if opening.posts { 0.0 } else { LINTEL_OVERHANG };
+            bricks.push(curve_space_brick(
+                (from - overhang, to + overhang),
+                (top, lintel_height),
+                style.brick_depth,
+                wall_length,
+                style.wall_height,
+            ));
+        }
+        if opening.posts {
+            for post in [(from - POST_WIDTH, from), (to, to + POST_WIDTH)] {
+                bricks.push(curve_space_brick(
+                    post,
+                    (0.0, post_height),
+                    style.brick_depth.max(POST_WIDTH),
+                    wall_length,
+                    style.wall_height,
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::curve::Curve;
+    use glam::Vec3;
+
+    // An 8 m long wall along X
+    fn wall() -> Curve {
+        Curve::from(
+            (0..=32)
+                .map(|i| Vec3::new(i as f32 * 0.25, 0.0, 0.0))
+                .collect(),
+        )
+    }
+
+    fn build(construction: Construction, openings: &[WallOpening]) -> Vec<Brick> {
+        let style = WallStyle {
+            construction,
+            ..WallStyle::default()
+        };
+        construction.builder().build(
+            &wall(),
+            &style,
+            &BrickLayout::new(7),
+            &WallJoints::default(),
+            openings,
+        )
+    }
+
+    #[test]
+    fn construction_names_round_trip() {
+        for construction in Construction::ALL {
+            assert_eq!(
+                Construction::from_name(construction.name()),
+                Ok(construction)
+            );
+        }
+        assert!(Construction::from_name("wattle").is_err());
+
+        let style = WallStyle {
+            construction: Construction::Hedge,
+            ..WallStyle::default()
+        };
+        assert_eq!(WallStyle::from_json(&style.to_json()).unwrap(), style);
+    }
+
+    #[test]
+    fn every_construction_builds_along_the_curve() {
+        for construction in Construction::ALL {
+            let bricks = build(construction, &[]);
+            assert!(!bricks.is_empty(), "{}", construction.name());
+            for b in &bricks {
+                assert!(
+                    b.transform.translation.is_finite() && b.transform.scale.min_element() > 0.0
+                );
+                // near the curve, which goes from x = 0 to 8 at z = 0
+                let p = b.transform.translation;
+                assert!(
+                    p.x > -0.5 && p.x < 8.5 && p.z.abs() < 0.5,
+                    "{} {:?}",
+                    construction.name(),
+                    p
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn gates_go_through_every_construction() {
+        let gate = WallOpening::new(OpeningKind::Gate, 0.5, 0.625);
+        for construction in Construction::ALL {
+            let bricks = build(construction, &[gate]);
+            let in_gate = bricks
+                .iter()
+                .filter(|b| b.pivot_uv.x > 0.5 + 0.01 && b.pivot_uv.x < 0.625 - 0.01)
+                .count();
+            assert_eq!(in_gate, 0, "{}", construction.name());
+        }
+    }
+
+    #[test]
+    fn ruins_crumble_along_the_wall() {
+        let whole = build(Construction::Bricks, &[]);
+        let ruin = build(Construction::Ruin, &[]);
+        assert!(ruin.len() < whole.len());
+
+        let top = |bricks: &[Brick], from: f32, to: f32| {
+            bricks
+                .iter()
+                .filter(|b| b.pivot_uv.x >= from && b.pivot_uv.x < to)
+                .map(|b| b.pivot_uv.y + b.bounds_uv.y / 2.0)
+                .fold(0.0, f32::max)
+        };
+        assert!(top(&ruin, 0.0, 0.1) > 0.95);
+        assert!(top(&ruin, 0.9, 1.0) < top(&ruin, 0.0, 0.1));
+    }
+
+    #[test]
+    fn drawing_in_front_keeps_the_bricks() {
+        let along_x = |from: f32| {
+            Curve::from(
+                (0..=32)
+                    .map(|i| Vec3::new(from + i as f32 * (8.0 - from) / 32.0, 0.0, 0.0))
+                    .collect(),
+            )
+        };
+        // where each brick starts and ends along X, and its size
+        let bricks = |from: f32, layout: &BrickLayout| -> Vec<[i32; 5]> {
+            let length = 8.0 - from;
+            WallConstructor::from_curve(&along_x(from), &WallStyle::default(), layout)
+                .iter()
+                .map(|b| {
+                    let min = from + (b.pivot_uv.x - b.bounds_uv.x / 2.0) * length;
+                    let max = from + (b.pivot_uv.x + b.bounds_uv.x / 2.0) * length;
+                    let size = b.transform.scale;
+                    [min, max, size.x, size.y, size.z].map(|v| (v * 1000.0).round() as i32)
+                })
+                .filter(|b| b[0] > 3000)
+                .collect()
+        };
+
+        // the same wall, drawn 2 m further from its first point
+        let wall = bricks(2.0, &BrickLayout::new(7));
+        let extended = bricks(
+            0.0,
+            &BrickLayout {
+                start_offset: 2.0,
+                ..BrickLayout::new(7)
+            },
+        );
+        assert!(!wall.is_empty());
+        assert_eq!(wall, extended);
+        assert_ne!(wall, bricks(0.0, &BrickLayout::new(7)));
+    }
+}
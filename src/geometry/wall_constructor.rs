@@ -2,119 +2,344 @@ use crate::{components::transform::Transform};
 use fastrand::Rng;
 use glam::{Mat3, Quat, Vec2, Vec3};
 
-use super::curve::Curve;
+use super::{curve::WallCurve, wall_junction::WallJoints, wall_opening::*, wall_style::WallStyle};
 
 // could be interesting for the bricks offset https://www.iquilezles.org/www/articles/voronoise/voronoise.htm
 
-const BRICK_WIDTH: f32 = 0.2;
-const BRICK_WIDTH_VARIANCE: f32 = 0.14;
-
-const BRICK_HEIGHT: f32 = 0.2;
-const BRICK_HEIGHT_VARIANCE: f32 = 0.09;
+// Where the randomness of a wall's bricks comes from, and where it's measured from
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BrickLayout {
+    // brick layout seed of the wall, see `brick_rng`
+    pub seed: u64,
+    // rows of bricks only depend on this and the style. Walls that interlock share it, so their rows line up where
+    // they are joined
+    pub rows_seed: u64,
+    // brick columns are counted from this far along the curve, so drawing more in front of a wall (or erasing its start)
+    // keeps the bricks it already had
+    pub start_offset: f32,
+}
 
-const BRICK_DEPTH: f32 = 0.2;
-const BRICK_DEPTH_VARIANCE: f32 = 0.05;
+impl BrickLayout {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, rows_seed: seed, start_offset: 0.0 }
+    }
 
-const WALL_HEIGHT: f32 = 1.4;
+    // First column of the row at the start of the curve, and how far its left edge is before the start of the curve
+    fn first_column(&self, brick_width: f32) -> (i64, f32) {
+        let column = (-self.start_offset / brick_width).floor() as i64;
+        (column, -(self.start_offset + column as f32 * brick_width))
+    }
+}
 
 pub struct WallConstructor;
 
 impl WallConstructor {
-    pub fn from_curve(curve: &Curve) -> Vec<Brick> {
-        let rng = fastrand::Rng::with_seed(0);
+    pub fn from_curve<C: WallCurve + ?Sized>(curve: &C, style: &WallStyle, layout: &BrickLayout) -> Vec<Brick> {
+        Self::from_curve_with_joints(curve, style, layout, &WallJoints::default(), &[])
+    }
 
-        let wall_length: f32 = curve.length;
-        
-        let row_count = (WALL_HEIGHT / BRICK_HEIGHT).floor() as usize;
-        let rows  = random_splits(row_count, BRICK_HEIGHT_VARIANCE / WALL_HEIGHT, &rng);
-        let bricks_per_row = (wall_length / BRICK_WIDTH).ceil() as usize; // this needs ceil, so that we always draw a brickwall if a curve is given, even if the bricks are too short
+    // Wall that is joined with other walls, see `JunctionGraph::joints`, and has gates, doors or windows
+    pub fn from_curve_with_joints<C: WallCurve + ?Sized>(curve: &C, style: &WallStyle, layout: &BrickLayout, joints: &WallJoints, openings: &[WallOpening]) -> Vec<Brick> {
+        Self::from_curve_after(curve, style, layout, joints, openings, 0.0).1
+    }
 
-        let mut bricks = Vec::new();
+    // Same as `from_curve`, but skips the bricks that start before `reusable_length` along the curve (see `reusable_length`).
+    // Bricks are ordered by where they start along the curve, so the skipped ones are always the first bricks of the previous construction.
+    // Returns how many bricks were skipped, and the rest of the bricks
+    pub fn from_curve_after<C: WallCurve + ?Sized>(curve: &C, style: &WallStyle, layout: &BrickLayout, joints: &WallJoints, openings: &[WallOpening], reusable_length: f32) -> (usize, Vec<Brick>) {
+        let rng = fastrand::Rng::with_seed(layout.rows_seed);
+
+        let wall_length: f32 = curve.length();
+        
+        let row_count = (style.wall_height / style.brick_height).floor() as usize;
+        let rows  = random_splits(row_count, style.brick_height_variance / style.wall_height, &rng);
+        let (first_column, shift) = layout.first_column(style.brick_width);
+        let bricks_per_row = bricks_per_row(wall_length + shift, style.brick_width);
+
+        // (where the brick starts along the curve, row, bricks), a brick might have been split into two
+        let mut slots: Vec<(f32, usize, Vec<Brick>)> = Vec::new();
+        let mut previous_row_mid = f32::NEG_INFINITY;
         for (i, row_u) in rows.iter().enumerate() {
 
             let brick_height = if let Some(next_row_u) = rows.get(i+1) {
-                (next_row_u - row_u) * WALL_HEIGHT
+                (next_row_u - row_u) * style.wall_height
             } else {
-                BRICK_HEIGHT + (rng.f32()-0.5) * BRICK_HEIGHT_VARIANCE
+                style.brick_height + (rng.f32()-0.5) * style.brick_height_variance
             };
 
-            let brick_widths = random_splits(bricks_per_row, BRICK_WIDTH_VARIANCE / wall_length, &rng);
+            // openings leave out parts of the rows, and put a lintel in place of the row above them
+            let row_mid = row_u + brick_height / style.wall_height / 2.0;
+            let opening_row = opening_row(openings, wall_length, row_mid, previous_row_mid);
+            previous_row_mid = row_mid;
+            for &(from, to) in &opening_row.lintels {
+                let brick = curve_space_brick((from, to), (*row_u, brick_height / style.wall_height), style.brick_depth, wall_length, style.wall_height);
+                slots.push((from, i, vec![brick]));
+            }
+
+            // one generator per brick, the first number it gives is the jitter of the brick's left edge
+            let brick_rngs: Vec<Rng> = (0..bricks_per_row).map(|j| brick_rng(layout.seed, i, column_key(first_column + j as i64))).collect();
+            let row_bricks = row_bricks(wall_length, shift, style, joints, &opening_row.gaps, i, &brick_rngs);
 
              // Bricks in curve space
-            let mut brick_row: Vec<Brick> = Vec::new();
-            for (j, this_u) in brick_widths.iter().enumerate() {
-                if let Some(next_u) = brick_widths.get(j+1) {
-                    // if its the last row, randomly skip some bricks!
-                    if i == rows.len()-1 {
-                        if rng.f32() < 0.35 {
-                            continue;
-                        }
-                    }
+            for (this_edge, next_edge, j) in row_bricks {
+                let rng = &brick_rngs[j];
+                let (this_u, next_u) = (this_edge / wall_length, next_edge / wall_length);
+                let mut brick_row: Vec<Brick> = Vec::new();
+
+                // if its the last row, randomly skip some bricks!
+                if i == rows.len()-1 && rng.f32() < style.top_row_skip_chance {
+                    continue;
+                }
 
-                    let brick_depth = BRICK_DEPTH + (rng.f32()-0.5) * BRICK_DEPTH_VARIANCE;
-                    //random chance to split horizontally into two bricks (except top row)
-                    if rng.f32() < 0.4 && i != rows.len()-1  {
-                        let range = (0.3, 0.7);
-                        let random_split = rng.f32() * (range.1 - range.0) + range.0;
-                        let pivot_u = (next_u + this_u) / 2.0;
-                        let height_u_1 = brick_height / WALL_HEIGHT * random_split;
-                        let height_u_2 = brick_height / WALL_HEIGHT * (1.0-random_split);
-                        let pivot_v_1 = row_u + height_u_1 / 2.0;
-                        let pivot_v_2 = (row_u + brick_height / WALL_HEIGHT) - height_u_2 / 2.0;
-                        let width_u = next_u - this_u;
-                        let width_ws = width_u * wall_length;
-                        for (height, pivot_v, _idx) in vec![(height_u_1, pivot_v_1, i*2), (height_u_2, pivot_v_2, i*2+1)] {
-                            brick_row.push(Brick {
-                                //row_count: row_count * 2, // HACK: multiple it by two, to account for random splits that create in-between rows
-                                //row_id_bottom: idx,
-                                //row_id_top: idx+1,
-                                pivot_uv: Vec2::new(pivot_u, pivot_v),
-                                bounds_uv: Vec2::new(width_u, height),
-                                transform: Transform {
-                                    translation:  Vec3::new(pivot_u*wall_length, 0.0, 0.0),
-                                    rotation: Quat::IDENTITY,
-                                    scale: Vec3::new(width_ws, height * WALL_HEIGHT, brick_depth)
-                                }
-                            });
-                        }
-                    } else {
-                        let pivot_u = (next_u + this_u) / 2.0;
-                        let width_u = next_u - this_u;
-                        let width_ws = width_u * wall_length;
+                let brick_depth = style.brick_depth + (rng.f32()-0.5) * style.brick_depth_variance;
+                //random chance to split horizontally into two bricks (except top row)
+                if rng.f32() < style.split_chance && i != rows.len()-1  {
+                    let range = (0.3, 0.7);
+                    let random_split = rng.f32() * (range.1 - range.0) + range.0;
+                    let pivot_u = (next_u + this_u) / 2.0;
+                    let height_u_1 = brick_height / style.wall_height * random_split;
+                    let height_u_2 = brick_height / style.wall_height * (1.0-random_split);
+                    let pivot_v_1 = row_u + height_u_1 / 2.0;
+                    let pivot_v_2 = (row_u + brick_height / style.wall_height) - height_u_2 / 2.0;
+                    let width_u = next_u - this_u;
+                    let width_ws = width_u * wall_length;
+                    for (height, pivot_v, _idx) in [(height_u_1, pivot_v_1, i*2), (height_u_2, pivot_v_2, i*2+1)] {
                         brick_row.push(Brick {
-                            pivot_uv: Vec2::new(pivot_u, row_u + brick_height / WALL_HEIGHT / 2.0),
-                            bounds_uv: Vec2::new(width_u, brick_height / WALL_HEIGHT), 
-                            transform: Transform { scale: Vec3::new(width_ws, brick_height, brick_depth),
-                            translation: Vec3::new(pivot_u*wall_length, 0.0, 0.0),
-                            rotation: Quat::IDENTITY}
+                            //row_count: row_count * 2, // HACK: multiple it by two, to account for random splits that create in-between rows
+                            //row_id_bottom: idx,
+                            //row_id_top: idx+1,
+                            pivot_uv: Vec2::new(pivot_u, pivot_v),
+                            bounds_uv: Vec2::new(width_u, height),
+                            transform: Transform {
+                                translation:  Vec3::new(pivot_u*wall_length, 0.0, 0.0),
+                                rotation: Quat::IDENTITY,
+                                scale: Vec3::new(width_ws, height * style.wall_height, brick_depth)
+                            }
                         });
                     }
+                } else {
+                    let pivot_u = (next_u + this_u) / 2.0;
+                    let width_u = next_u - this_u;
+                    let width_ws = width_u * wall_length;
+                    brick_row.push(Brick {
+                        pivot_uv: Vec2::new(pivot_u, row_u + brick_height / style.wall_height / 2.0),
+                        bounds_uv: Vec2::new(width_u, brick_height / style.wall_height), 
+                        transform: Transform { scale: Vec3::new(width_ws, brick_height, brick_depth),
+                        translation: Vec3::new(pivot_u*wall_length, 0.0, 0.0),
+                        rotation: Quat::IDENTITY}
+                    });
                 }
-            }
 
-            // Transform bricks into world space
-            for brick in &mut brick_row {
-                brick.transform.translation = curve.get_pos_at_u(brick.pivot_uv.x);
-                brick.transform.translation.y = brick.pivot_uv.y * WALL_HEIGHT; //row_u * WALL_HEIGHT + brick_height / 2.0;
+                slots.push((this_edge, i, brick_row));
+            }
+        }
 
-                let curve_tangent = curve.get_tangent_at_u(brick.pivot_uv.x);
-                let normal = curve_tangent.cross(Vec3::Y);
-                brick.transform.rotation = Quat::from_mat3(&Mat3::from_cols(curve_tangent, Vec3::Y, normal));
+        // gate posts go from the ground to above the top row
+        let post_height = 1.0 + (style.brick_height + POST_CAP) / style.wall_height;
+        for opening in openings.iter().filter(|o| o.posts) {
+            let (from, to) = (opening.from * wall_length, opening.to * wall_length);
+            for post in [(from - POST_WIDTH, from), (to, to + POST_WIDTH)] {
+                let brick = curve_space_brick(post, (0.0, post_height), style.brick_depth.max(POST_WIDTH), wall_length, style.wall_height);
+                slots.push((post.0, 0, vec![brick]));
             }
+        }
+
+        slots.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap().then(a.1.cmp(&b.1)));
+
+        let skipped = slots.iter().take_while(|(edge, _, _)| *edge < reusable_length).map(|(_, _, b)| b.len()).sum();
+
+        let mut bricks: Vec<Brick> = slots.into_iter().filter(|(edge, _, _)| *edge >= reusable_length).flat_map(|(_, _, b)| b).collect();
+        to_world_space(curve, &mut bricks, style.wall_height);
+
+        (skipped, bricks)
+    }
 
-            bricks.extend(brick_row);
+    // How far along the curve bricks of a previous construction are still valid, if the curve used to be `previous_length` long
+    // with `previous_joints`, and is exactly the same as before for the first `unchanged_length`. Style, layout and openings have to be the same as well
+    #[allow(clippy::too_many_arguments)]
+    pub fn reusable_length(style: &WallStyle, layout: &BrickLayout, previous_length: f32, previous_joints: &WallJoints, curve_length: f32, joints: &WallJoints, openings: &[WallOpening], unchanged_length: f32) -> f32 {
+        // the first brick of each row depends on the start cap
+        if previous_joints.start != joints.start {
+            return 0.0;
         }
 
-        bricks
+        // the last brick of each row depends on the wall length
+        let (_, shift) = layout.first_column(style.brick_width);
+        let columns = bricks_per_row(previous_length + shift, style.brick_width).min(bricks_per_row(curve_length + shift, style.brick_width));
+        let last_edge = (columns as f32 - 1.0) * style.brick_width - shift - style.brick_width_variance / 2.0;
+
+        // bricks get stretched over the edges that are too close to the end or to a gap
+        let joint_edge = [previous_length + previous_joints.end.min(), curve_length + joints.end.min()]
+            .iter()
+            .copied()
+            .chain(previous_joints.gaps.iter().chain(&joints.gaps).map(|g| g.from))
+            // openings are placed in u, so they move when the length changes
+            .chain(openings.iter().map(|o| o.from * previous_length.min(curve_length) - o.reach()))
+            .fold(f32::INFINITY, f32::min) - min_brick_width(style);
+
+        // bricks that start before the returned length also have to end before `last_edge`, widest brick (except the last) is brick_width + variance
+        (unchanged_length.min(last_edge).min(joint_edge) - (style.brick_width + style.brick_width_variance)).max(0.0)
     }
 }
 
+#[derive(Debug, PartialEq)]
 pub struct Brick {
     pub bounds_uv: Vec2,
     pub pivot_uv: Vec2,
     pub transform: Transform, 
 }
 
+// Moves bricks from curve space onto the curve. The Z of their translation is kept as an offset along the normal of the curve,
+// and their rotation is applied in the frame of the curve
+pub fn to_world_space<C: WallCurve + ?Sized>(curve: &C, bricks: &mut [Brick], wall_height: f32) {
+    let wall_length = curve.length();
+    for brick in bricks {
+        let distance = brick.pivot_uv.x * wall_length;
+        // curves follow the terrain, but bricks stay upright, so only the horizontal part of the tangent is used
+        let curve_tangent = curve.get_tangent_at_distance(distance);
+        let curve_tangent = Vec3::new(curve_tangent.x, 0.0, curve_tangent.z).normalize_or_zero();
+        // bricks that go past the ends of the curve to reach a joined wall continue in a straight line
+        let distance_past_ends = distance - distance.clamp(0.0, wall_length);
+        let ground = curve.get_pos_at_distance(distance) + curve_tangent * distance_past_ends;
+        let normal = curve_tangent.cross(Vec3::Y);
+        // every brick sits on the ground under its pivot, so courses step along the slope (the wall shader shears them to the terrain)
+        brick.transform.translation = ground + Vec3::Y * brick.pivot_uv.y * wall_height + normal * brick.transform.translation.z;
+
+        brick.transform.rotation = Quat::from_mat3(&Mat3::from_cols(curve_tangent, Vec3::Y, normal)) * brick.transform.rotation;
+    }
+}
+
+
+// Bricks are laid from the start of the curve, so a wall that gets longer keeps the bricks it already had.
+// The last brick takes the remaining length, unless it would be shorter than half a brick, then the previous brick is stretched instead
+fn bricks_per_row(wall_length: f32, brick_width: f32) -> usize {
+    let full_bricks = (wall_length / brick_width).floor() as usize;
+    let remainder = wall_length - full_bricks as f32 * brick_width;
+
+    if full_bricks == 0 || remainder >= brick_width / 2.0 {
+        full_bricks + 1 // we always draw a brickwall if a curve is given, even if the bricks are too short
+    } else {
+        full_bricks
+    }
+}
+
+// brick edges along the curve, in meters so they don't depend on the wall length. The first column starts `shift` before the curve
+fn brick_edges(wall_length: f32, shift: f32, style: &WallStyle, brick_rngs: &[Rng]) -> Vec<f32> {
+    let mut edges: Vec<f32> = brick_rngs
+        .iter()
+        .enumerate()
+        .map(|(j, rng)| {
+            let jitter = (rng.f32() - 0.5) * style.brick_width_variance;
+            // first edge is at the start of the curve
+            if j == 0 {
+                0.0
+            } else {
+                j as f32 * style.brick_width - shift + jitter
+            }
+        })
+        .collect();
+    edges.push(wall_length);
+    edges
+}
+
+// Narrowest brick a row can have without joints, edges closer than that to a joint are left out
+fn min_brick_width(style: &WallStyle) -> f32 {
+    (style.brick_width - style.brick_width_variance) / 2.0
+}
+
+// Parts of a row that openings leave out, and the lintels laid in it, as (from, to) along the curve
+#[derive(Default)]
+struct OpeningRow {
+    gaps: Vec<(f32, f32)>,
+    lintels: Vec<(f32, f32)>,
+}
+
+// A lintel replaces the first row that isn't cut above an opening
+fn opening_row(openings: &[WallOpening], wall_length: f32, row_mid: f32, previous_row_mid: f32) -> OpeningRow {
+    let mut row = OpeningRow::default();
+    for opening in openings {
+        let (from, to) = (opening.from * wall_length, opening.to * wall_length);
+        let (bottom, top) = opening.kind.height_range();
+        let is_cut = |mid: f32| mid > bottom && mid < top;
+
+        if is_cut(row_mid) {
+            row.gaps.push((from, to));
+        } else if opening.lintel && row_mid >= top && is_cut(previous_row_mid) {
+            // between posts the lintel doesn't overhang
+            let overhang = if opening.posts { 0.0 } else { LINTEL_OVERHANG };
+            row.gaps.push((from - overhang, to + overhang));
+            row.lintels.push((from - overhang, to + overhang));
+        }
+
+        if opening.posts {
+            row.gaps.push((from - POST_WIDTH, from));
+            row.gaps.push((to, to + POST_WIDTH));
+        }
+    }
+    row
+}
+
+// Brick from `from` to `to` along the curve, and from `bottom` up `height` (in u of the wall height)
+pub fn curve_space_brick((from, to): (f32, f32), (bottom, height): (f32, f32), depth: f32, wall_length: f32, wall_height: f32) -> Brick {
+    let pivot_u = (from + to) / 2.0 / wall_length;
+    Brick {
+        pivot_uv: Vec2::new(pivot_u, bottom + height / 2.0),
+        bounds_uv: Vec2::new((to - from) / wall_length, height),
+        transform: Transform {
+            translation: Vec3::new(pivot_u * wall_length, 0.0, 0.0),
+            rotation: Quat::IDENTITY,
+            scale: Vec3::new(to - from, height * wall_height, depth),
+        },
+    }
+}
+
+// Bricks of a row as (left edge, right edge, index of the brick's generator), with the ends capped and the gaps of the
+// joints and of `opening_gaps` cut out
+fn row_bricks(wall_length: f32, shift: f32, style: &WallStyle, joints: &WallJoints, opening_gaps: &[(f32, f32)], row: usize, brick_rngs: &[Rng]) -> Vec<(f32, f32, usize)> {
+    let parity = row % 2;
+    let first = -joints.start.rows[parity];
+    let last = wall_length + joints.end.rows[parity];
+    let mut gaps: Vec<(f32, f32)> = joints.gaps.iter().filter(|g| g.row_parity == parity).map(|g| (g.from, g.to)).collect();
+    gaps.extend_from_slice(opening_gaps);
+
+    let min_width = min_brick_width(style);
+    let mut hard_edges = vec![first, last];
+    hard_edges.extend(gaps.iter().flat_map(|(from, to)| [*from, *to]).filter(|e| *e > first && *e < last));
+
+    // bricks starting at a joint use the generator of the column they are in
+    let column = |e: f32| (((e + shift) / style.brick_width).floor().max(0.0) as usize).min(brick_rngs.len() - 1);
+    let mut edges: Vec<(f32, usize)> = hard_edges.iter().map(|e| (*e, column(*e))).collect();
+
+    let soft_edges = brick_edges(wall_length, shift, style, brick_rngs);
+    for (j, e) in soft_edges.iter().enumerate().take(soft_edges.len() - 1).skip(1) {
+        let near_joint = hard_edges.iter().any(|h| (e - h).abs() < min_width);
+        let in_gap = gaps.iter().any(|(from, to)| e > from && e < to);
+        if *e > first && *e < last && !near_joint && !in_gap {
+            edges.push((*e, j));
+        }
+    }
+    edges.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+    edges
+        .windows(2)
+        .filter(|pair| {
+            let middle = (pair[0].0 + pair[1].0) / 2.0;
+            pair[1].0 > pair[0].0 && !gaps.iter().any(|(from, to)| middle > *from && middle < *to)
+        })
+        .map(|pair| (pair[0].0, pair[1].0, pair[0].1))
+        .collect()
+}
+
+// Columns before the start of the layout are negative, they keep to the 32 bits `brick_rng` has for the column
+fn column_key(column: i64) -> usize {
+    column as u32 as usize
+}
+
+// Randomness of a brick only depends on the wall seed and where the brick is in the wall
+pub fn brick_rng(seed: u64, row: usize, column: usize) -> Rng {
+    let position = ((row as u64) << 32) | column as u64;
+    Rng::with_seed(seed ^ position.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
 
 // random splits in [0;1] range
 fn random_splits(splits: usize, variance_u: f32, rng: &Rng) -> Vec<f32> {
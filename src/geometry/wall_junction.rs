@@ -0,0 +1,357 @@
+use std::collections::{HashMap, HashSet};
+
+use glam::Vec3;
+
+use super::{
+    curve::{Curve, WallCurve},
+    wall_style::WallStyle,
+};
+
+// A wall end joins another wall if it ends at most this far from it
+pub const JUNCTION_DISTANCE: f32 = 0.3;
+// or if it crossed the other wall and goes on for at most this long, the overshoot is left out
+pub const JUNCTION_OVERSHOOT: f32 = 0.6;
+
+// Walls that meet at a flatter angle than this (sine of it) aren't joined, closing the corner would need very long bricks
+const MIN_JUNCTION_SIN: f32 = 0.3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WallEnd {
+    Start,
+    End,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JunctionKind {
+    // ends of two walls meet
+    Corner,
+    // a wall ends against the middle of another one
+    Tee,
+}
+
+// How one end of a wall meets another wall
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Junction {
+    pub other: usize,
+    pub kind: JunctionKind,
+    // how far the other wall's curve is past this end, negative if this wall crossed it
+    pub reach: f32,
+    // where along the other wall's curve they meet
+    pub other_distance: f32,
+    // sine of the angle between the walls
+    pub sin_angle: f32,
+    pub depth: f32,
+    pub other_depth: f32,
+    // walls of the same style share their rows of bricks, so they can interlock
+    pub interlocked: bool,
+}
+
+impl Junction {
+    // Length along this wall that covers half of the other wall's depth
+    fn other_half_depth(&self) -> f32 {
+        self.other_depth / 2.0 / self.sin_angle
+    }
+
+    // The end of a brick is square, at an angle one of its corners gets to the other wall first
+    fn corner_overhang(&self) -> f32 {
+        self.depth / 2.0 * (1.0 - self.sin_angle * self.sin_angle).sqrt() / self.sin_angle
+    }
+
+    // Length along the other wall that's covered by this wall where they cross
+    fn half_width_on_other(&self) -> f32 {
+        let cos_angle = (1.0 - self.sin_angle * self.sin_angle).sqrt();
+        (self.depth + self.other_depth * cos_angle) / 2.0 / self.sin_angle
+    }
+}
+
+// How far the bricks of a wall end go past the end of its curve (negative if they stop before it), for even and odd rows
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EndCap {
+    pub rows: [f32; 2],
+}
+
+impl EndCap {
+    fn all_rows(offset: f32) -> Self {
+        Self {
+            rows: [offset, offset],
+        }
+    }
+
+    pub fn min(&self) -> f32 {
+        self.rows[0].min(self.rows[1])
+    }
+}
+
+// Part of a wall that's left empty in every other row, for the bricks of a wall that ends against it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gap {
+    pub from: f32,
+    pub to: f32,
+    // rows with `row % 2 == row_parity`
+    pub row_parity: usize,
+}
+
+// Everything `WallConstructor` needs to know about the walls a wall is joined with
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WallJoints {
+    pub start: EndCap,
+    pub end: EndCap,
+    pub gaps: Vec<Gap>,
+}
+
+// Which wall ends meet which walls. Junctions are detected from the curves again whenever walls change,
+// so they follow walls that are erased, extended or undone
+#[derive(Default)]
+pub struct JunctionGraph {
+    junctions: HashMap<(usize, WallEnd), Junction>,
+}
+
+impl JunctionGraph {
+    pub fn new() -> Self {
+        Self {
+            junctions: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, index: usize, end: WallEnd) -> Option<&Junction> {
+        self.junctions.get(&(index, end))
+    }
+
+    // Walls that have a junction with `index`
+    pub fn joined_to(&self, index: usize) -> Vec<usize> {
+        let mut joined: Vec<usize> = self
+            .junctions
+            .iter()
+            .filter_map(|((wall, _), j)| {
+                if *wall == index {
+                    Some(j.other)
+                } else if j.other == index {
+                    Some(*wall)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        joined.sort_unstable();
+        joined.dedup();
+        joined
+    }
+
+    // Walls that share their rows of bricks with `index`, through interlocked junctions. Sorted, `index` included
+    pub fn interlocked_with(&self, index: usize) -> Vec<usize> {
+        let mut group = vec![index];
+        let mut next = 0;
+        while next < group.len() {
+            let wall = group[next];
+            for ((other_wall, _), j) in self.junctions.iter().filter(|(_, j)| j.interlocked) {
+                let other = if *other_wall == wall {
+                    j.other
+                } else if j.other == wall {
+                    *other_wall
+                } else {
+                    continue;
+                };
+                if !group.contains(&other) {
+                    group.push(other);
+                }
+            }
+            next += 1;
+        }
+        group.sort_unstable();
+        group
+    }
+
+    // `walls` are all the walls there are, `changed` the ones that were added or changed since the last update.
+    // Returns the walls that have to be constructed again, because the way they are joined changed
+    pub fn update(
+        &mut self,
+        walls: &[(usize, &Curve, &WallStyle)],
+        changed: &HashSet<usize>,
+    ) -> HashSet<usize> {
+        let exists: HashSet<usize> = walls.iter().map(|(i, _, _)| *i).collect();
+        let mut affected = HashSet::new();
+
+        // removed walls
+        self.junctions.retain(|(index, _), j| {
+            let keep = exists.contains(index);
+            if !keep {
+                affected.insert(j.other);
+            }
+            keep
+        });
+
+        for (index, curve, style) in walls {
+            for end in [WallEnd::Start, WallEnd::End] {
+                let old = self.junctions.get(&(*index, end)).copied();
+
+                let new = match old {
+                    // wall changed, or the wall it was joined with did
+                    _ if changed.contains(index) => detect(*index, end, curve, style, walls),
+                    Some(j) if changed.contains(&j.other) || !exists.contains(&j.other) => {
+                        detect(*index, end, curve, style, walls)
+                    }
+                    Some(j) => Some(j),
+                    // a free end can only be reached by a wall that changed
+                    None => {
+                        let candidates: Vec<_> = walls
+                            .iter()
+                            .filter(|(i, _, _)| changed.contains(i))
+                            .copied()
+                            .collect();
+                        detect(*index, end, curve, style, &candidates)
+                    }
+                };
+
+                if new != old {
+                    affected.insert(*index);
+                    affected.extend(old.map(|j| j.other));
+                    affected.extend(new.map(|j| j.other));
+
+                    match new {
+                        Some(j) => self.junctions.insert((*index, end), j),
+                        None => self.junctions.remove(&(*index, end)),
+                    };
+                }
+            }
+        }
+
+        affected.retain(|i| exists.contains(i));
+        affected
+    }
+
+    // End caps of a wall, and the gaps left in it for the walls that end against it.
+    // At corners the wall with the lower index goes through on even rows, the other one on odd rows.
+    // At T-junctions the ending wall goes through the other one on even rows
+    pub fn joints(&self, index: usize) -> WallJoints {
+        let cap = |end: WallEnd| {
+            let j = match self.get(index, end) {
+                Some(j) => j,
+                None => return EndCap::default(),
+            };
+
+            let through = j.reach + j.other_half_depth() - j.corner_overhang();
+            let stop = j.reach - j.other_half_depth() - j.corner_overhang();
+            match (j.kind, j.interlocked) {
+                (JunctionKind::Tee, true) => EndCap {
+                    rows: [through, stop],
+                },
+                (JunctionKind::Tee, false) => EndCap::all_rows(stop),
+                (JunctionKind::Corner, true) if index < j.other => EndCap {
+                    rows: [through, stop],
+                },
+                (JunctionKind::Corner, true) => EndCap {
+                    rows: [stop, through],
+                },
+                (JunctionKind::Corner, false) if index < j.other => EndCap::all_rows(through),
+                (JunctionKind::Corner, false) => EndCap::all_rows(stop),
+            }
+        };
+
+        let mut gaps: Vec<Gap> = self
+            .junctions
+            .values()
+            .filter(|j| j.other == index && j.kind == JunctionKind::Tee && j.interlocked)
+            .map(|j| Gap {
+                from: j.other_distance - j.half_width_on_other(),
+                to: j.other_distance + j.half_width_on_other(),
+                row_parity: 0,
+            })
+            .collect();
+        gaps.sort_by(|a, b| a.from.partial_cmp(&b.from).unwrap());
+
+        WallJoints {
+            start: cap(WallEnd::Start),
+            end: cap(WallEnd::End),
+            gaps,
+        }
+    }
+}
+
+// Position of a wall end, and the direction the wall would go on in
+fn end_point(curve: &Curve, end: WallEnd) -> (Vec3, Vec3) {
+    match end {
+        WallEnd::Start => (
+            curve.get_pos_at_distance(0.0),
+            -curve.get_tangent_at_distance(0.0),
+        ),
+        WallEnd::End => (
+            curve.get_pos_at_distance(curve.length),
+            curve.get_tangent_at_distance(curve.length),
+        ),
+    }
+}
+
+// Closest wall the end of `index` crosses or lands on
+fn detect(
+    index: usize,
+    end: WallEnd,
+    curve: &Curve,
+    style: &WallStyle,
+    walls: &[(usize, &Curve, &WallStyle)],
+) -> Option<Junction> {
+    if curve.points.len() < 2 || curve.length <= 0.0 {
+        return None;
+    }
+
+    let (end_pos, end_dir) = end_point(curve, end);
+
+    // only the last bit of the wall can cross another wall, measured from the end
+    let overshoot = curve.length.min(JUNCTION_OVERSHOOT);
+    let end_part = match end {
+        WallEnd::Start => curve.slice(0.0, overshoot),
+        WallEnd::End => curve.slice(curve.length - overshoot, curve.length),
+    };
+    let from_end = |distance: f32| match end {
+        WallEnd::Start => distance,
+        WallEnd::End => overshoot - distance,
+    };
+
+    walls
+        .iter()
+        .filter(|(i, other, _)| *i != index && other.points.len() > 1 && other.length > 0.0)
+        .filter_map(|(i, other, other_style)| {
+            let crossing = end_part
+                .crossings(other)
+                .into_iter()
+                .map(|(distance, other_distance)| (-from_end(distance), other_distance))
+                .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+            let (reach, other_distance) = match crossing {
+                Some(crossing) => crossing,
+                None => {
+                    let closest = other.closest_point(end_pos)?;
+                    if closest.separation > JUNCTION_DISTANCE {
+                        return None;
+                    }
+                    (closest.separation, closest.distance)
+                }
+            };
+
+            let kind = if other_distance <= JUNCTION_DISTANCE
+                || other_distance >= other.length - JUNCTION_DISTANCE
+            {
+                JunctionKind::Corner
+            } else {
+                JunctionKind::Tee
+            };
+
+            let other_tangent = other.get_tangent_at_distance(other_distance);
+            let sin_angle = (end_dir.x * other_tangent.z - end_dir.z * other_tangent.x).abs();
+            if sin_angle < MIN_JUNCTION_SIN {
+                return None;
+            }
+
+            Some(Junction {
+                other: *i,
+                kind,
+                reach,
+                other_distance,
+                sin_angle,
+                depth: style.brick_depth,
+                other_depth: other_style.brick_depth,
+                interlocked: style == *other_style,
+            })
+        })
+        // the wall closest to the end
+        .min_by(|a, b| a.reach.abs().partial_cmp(&b.reach.abs()).unwrap())
+}
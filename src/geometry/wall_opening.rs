@@ -0,0 +1,253 @@
+use serde_json::{json, Value};
+
+// Narrowest opening, the opening tool takes shorter drags for clicks
+pub const MIN_OPENING_WIDTH: f32 = 0.3;
+// Lintels go this far past both sides of the opening, in meters
+pub const LINTEL_OVERHANG: f32 = 0.15;
+// Gate posts are this wide, and stick out this much above the wall
+pub const POST_WIDTH: f32 = 0.3;
+pub const POST_CAP: f32 = 0.1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpeningKind {
+    // open over the whole height of the wall
+    Gate,
+    // open from the ground
+    Door,
+    // open between a sill and a head
+    Window,
+}
+
+impl OpeningKind {
+    pub const ALL: [OpeningKind; 3] = [OpeningKind::Gate, OpeningKind::Door, OpeningKind::Window];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OpeningKind::Gate => "gate",
+            OpeningKind::Door => "door",
+            OpeningKind::Window => "window",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name() == name)
+            .ok_or(format!(
+                "Wall opening: unknown kind `{}`, expected gate, door or window",
+                name
+            ))
+    }
+
+    pub fn next(&self) -> Self {
+        let i = Self::ALL.iter().position(|kind| kind == self).unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    // Part of the wall height that's open, rows of bricks with their middle in it are left out.
+    // In u of the wall height, the top row sits above 1
+    pub fn height_range(&self) -> (f32, f32) {
+        match self {
+            OpeningKind::Gate => (0.0, f32::INFINITY),
+            OpeningKind::Door => (0.0, 0.75),
+            OpeningKind::Window => (0.35, 0.75),
+        }
+    }
+}
+
+// A gap in a wall, from `from` to `to` along its curve (in u, so it follows the curve when it's reshaped)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WallOpening {
+    pub kind: OpeningKind,
+    pub from: f32,
+    pub to: f32,
+    // a single brick over the opening, in place of the row above it
+    pub lintel: bool,
+    // a tall brick on both sides of the opening, over the whole height of the wall
+    pub posts: bool,
+}
+
+impl WallOpening {
+    // Gates get posts, doors and windows a lintel
+    pub fn new(kind: OpeningKind, from: f32, to: f32) -> Self {
+        Self {
+            kind,
+            from: from.min(to).clamp(0.0, 1.0),
+            to: from.max(to).clamp(0.0, 1.0),
+            lintel: kind != OpeningKind::Gate,
+            posts: kind == OpeningKind::Gate,
+        }
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.from < other.to && other.from < self.to
+    }
+
+    // How far before `from` and after `to` the bricks of the wall are changed, in meters
+    pub fn reach(&self) -> f32 {
+        let lintel = if self.lintel && !self.posts {
+            LINTEL_OVERHANG
+        } else {
+            0.0
+        };
+        let posts = if self.posts { POST_WIDTH } else { 0.0 };
+        lintel.max(posts)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "kind": self.kind.name(),
+            "from": self.from,
+            "to": self.to,
+            "lintel": self.lintel,
+            "posts": self.posts,
+        })
+    }
+
+    // `lintel` and `posts` fall back to what the kind gets by default
+    pub fn from_json(v: &Value) -> Result<Self, String> {
+        let kind =
+            OpeningKind::from_name(v["kind"].as_str().ok_or("Wall opening: missing `kind`")?)?;
+
+        let mut range = [0.0; 2];
+        for (name, field) in ["from", "to"].iter().zip(range.iter_mut()) {
+            *field = v[*name]
+                .as_f64()
+                .ok_or(format!("Wall opening: `{}` is not a number", name))?
+                as f32;
+        }
+
+        let mut opening = Self::new(kind, range[0], range[1]);
+        for (name, field) in [
+            ("lintel", &mut opening.lintel),
+            ("posts", &mut opening.posts),
+        ] {
+            if let Some(value) = v.get(name) {
+                *field = value
+                    .as_bool()
+                    .ok_or(format!("Wall opening: `{}` is not a boolean", name))?;
+            }
+        }
+
+        Ok(opening)
+    }
+}
+
+// Openings that are entirely within the part of a wall from `from` to `to` (in u), in the u of that part
+pub fn openings_within(openings: &[WallOpening], from: f32, to: f32) -> Vec<WallOpening> {
+    if to <= from {
+        return Vec::new();
+    }
+
+    openings
+        .iter()
+        .filter(|o| o.from >= from && o.to <= to)
+        .map(|o| WallOpening {
+            from: (o.from - from) / (to - from),
+            to: (o.to - from) / (to - from),
+            ..*o
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{
+        curve::Curve,
+        wall_constructor::{BrickLayout, WallConstructor},
+        wall_junction::WallJoints,
+        wall_style::WallStyle,
+    };
+    use glam::Vec3;
+
+    // An 8 m long wall along X
+    fn wall() -> Curve {
+        Curve::from(
+            (0..=32)
+                .map(|i| Vec3::new(i as f32 * 0.25, 0.0, 0.0))
+                .collect(),
+        )
+    }
+
+    fn bricks(openings: &[WallOpening]) -> Vec<(f32, f32, f32, f32)> {
+        WallConstructor::from_curve_with_joints(
+            &wall(),
+            &WallStyle::default(),
+            &BrickLayout::new(1),
+            &WallJoints::default(),
+            openings,
+        )
+        .iter()
+        .map(|b| {
+            let min = b.pivot_uv - b.bounds_uv / 2.0;
+            let max = b.pivot_uv + b.bounds_uv / 2.0;
+            (min.x * 8.0, max.x * 8.0, min.y, max.y)
+        })
+        .collect()
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let v = serde_json::json!({ "kind": "door", "from": 0.6, "to": 0.4 });
+        let opening = WallOpening::from_json(&v).unwrap();
+        assert_eq!(opening.kind, OpeningKind::Door);
+        assert!((opening.from - 0.4).abs() < 1e-6 && (opening.to - 0.6).abs() < 1e-6);
+        assert!(opening.lintel && !opening.posts);
+
+        assert_eq!(WallOpening::from_json(&opening.to_json()).unwrap(), opening);
+        assert!(
+            WallOpening::from_json(&serde_json::json!({ "kind": "arch", "from": 0, "to": 1 }))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn openings_within_pieces() {
+        let openings = [
+            WallOpening::new(OpeningKind::Gate, 0.1, 0.3),
+            WallOpening::new(OpeningKind::Window, 0.6, 0.8),
+        ];
+        // the gate is cut by the end of the piece
+        let kept = openings_within(&openings, 0.2, 1.0);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].kind, OpeningKind::Window);
+        assert!((kept[0].from - 0.5).abs() < 1e-6 && (kept[0].to - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn door_is_cut_out_under_a_lintel() {
+        let door = WallOpening::new(OpeningKind::Door, 0.5, 0.625);
+        let bricks = bricks(&[door]);
+
+        let (_, top) = OpeningKind::Door.height_range();
+        let in_door = |b: &&(f32, f32, f32, f32)| b.0 < 5.0 - 1e-3 && b.1 > 4.0 + 1e-3;
+        assert!(!bricks
+            .iter()
+            .filter(in_door)
+            .any(|b| (b.2 + b.3) / 2.0 < top));
+
+        // the lintel is the only brick over the whole door
+        let lintels: Vec<_> = bricks.iter().filter(|b| b.0 <= 4.0 && b.1 >= 5.0).collect();
+        assert_eq!(lintels.len(), 1);
+        assert!((lintels[0].1 - lintels[0].0 - (1.0 + 2.0 * LINTEL_OVERHANG)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn gate_has_posts() {
+        let gate = WallOpening::new(OpeningKind::Gate, 0.5, 0.625);
+        let bricks = bricks(&[gate]);
+
+        let in_gate = |b: &&(f32, f32, f32, f32)| b.0 < 5.0 - 1e-3 && b.1 > 4.0 + 1e-3;
+        assert_eq!(bricks.iter().filter(in_gate).count(), 0);
+
+        // posts stand above the top row
+        let posts: Vec<_> = bricks
+            .iter()
+            .filter(|b| b.3 > 1.0 + 0.01 && b.2 <= 0.0)
+            .collect();
+        assert_eq!(posts.len(), 2);
+        assert!((posts[0].1 - 4.0).abs() < 1e-4 && (posts[1].0 - 5.0).abs() < 1e-4);
+    }
+}
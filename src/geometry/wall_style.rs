@@ -0,0 +1,264 @@
+use serde_json::{json, Value};
+
+use super::{arch_style::ArchStyle, wall_builder::Construction};
+
+// Smaller bricks would make walls of absurd numbers of instances
+const MIN_BRICK_SIZE: f32 = 0.01;
+
+// Everything that defines the look of a brick wall, see `WallConstructor::from_curve`.
+// The other constructions read the brick sizes as the sizes of their stones, see `WallBuilder`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WallStyle {
+    pub construction: Construction,
+
+    pub brick_width: f32,
+    pub brick_width_variance: f32,
+
+    pub brick_height: f32,
+    pub brick_height_variance: f32,
+
+    pub brick_depth: f32,
+    pub brick_depth_variance: f32,
+
+    pub wall_height: f32,
+
+    // chance for a brick of the top row to be left out
+    pub top_row_skip_chance: f32,
+    // chance for a brick to be split horizontally into two (except top row)
+    pub split_chance: f32,
+
+    // arches over the paths the wall crosses
+    pub arch: ArchStyle,
+}
+
+impl Default for WallStyle {
+    fn default() -> Self {
+        Self {
+            construction: Construction::Bricks,
+            brick_width: 0.2,
+            brick_width_variance: 0.14,
+            brick_height: 0.2,
+            brick_height_variance: 0.09,
+            brick_depth: 0.2,
+            brick_depth_variance: 0.05,
+            wall_height: 1.4,
+            top_row_skip_chance: 0.35,
+            split_chance: 0.4,
+            arch: ArchStyle::default(),
+        }
+    }
+}
+
+impl WallStyle {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "construction": self.construction.name(),
+            "brick_width": self.brick_width,
+            "brick_width_variance": self.brick_width_variance,
+            "brick_height": self.brick_height,
+            "brick_height_variance": self.brick_height_variance,
+            "brick_depth": self.brick_depth,
+            "brick_depth_variance": self.brick_depth_variance,
+            "wall_height": self.wall_height,
+            "top_row_skip_chance": self.top_row_skip_chance,
+            "split_chance": self.split_chance,
+            "arch": self.arch.to_json(),
+        })
+    }
+
+    // Missing fields fall back to the default style, so presets only need to list what they change
+    pub fn from_json(v: &Value) -> Result<Self, String> {
+        if !v.is_object() {
+            return Err(format!("Wall style: expected an object, got {}", v));
+        }
+
+        let mut style = Self::default();
+        if let Some(construction) = v.get("construction") {
+            style.construction = Construction::from_name(
+                construction
+                    .as_str()
+                    .ok_or("Wall style: `construction` is not a string")?,
+            )?;
+        }
+
+        for (name, field) in [
+            ("brick_width", &mut style.brick_width),
+            ("brick_width_variance", &mut style.brick_width_variance),
+            ("brick_height", &mut style.brick_height),
+            ("brick_height_variance", &mut style.brick_height_variance),
+            ("brick_depth", &mut style.brick_depth),
+            ("brick_depth_variance", &mut style.brick_depth_variance),
+            ("wall_height", &mut style.wall_height),
+            ("top_row_skip_chance", &mut style.top_row_skip_chance),
+            ("split_chance", &mut style.split_chance),
+        ] {
+            if let Some(value) = v.get(name) {
+                *field = value
+                    .as_f64()
+                    .ok_or(format!("Wall style: `{}` is not a number", name))?
+                    as f32;
+            }
+        }
+
+        if let Some(arch) = v.get("arch") {
+            style.arch = ArchStyle::from_json(arch)?;
+        }
+
+        style.validate()?;
+        Ok(style)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let values = [
+            ("brick_width", self.brick_width),
+            ("brick_width_variance", self.brick_width_variance),
+            ("brick_height", self.brick_height),
+            ("brick_height_variance", self.brick_height_variance),
+            ("brick_depth", self.brick_depth),
+            ("brick_depth_variance", self.brick_depth_variance),
+            ("wall_height", self.wall_height),
+            ("top_row_skip_chance", self.top_row_skip_chance),
+            ("split_chance", self.split_chance),
+        ];
+        if let Some((name, value)) = values.iter().find(|(_, value)| !value.is_finite()) {
+            return Err(format!("Wall style: `{}` is {}", name, value));
+        }
+
+        for (name, size) in [
+            ("brick_width", self.brick_width),
+            ("brick_height", self.brick_height),
+            ("brick_depth", self.brick_depth),
+        ] {
+            if size < MIN_BRICK_SIZE {
+                return Err(format!(
+                    "Wall style: `{}` is {}, it must be at least {}",
+                    name, size, MIN_BRICK_SIZE
+                ));
+            }
+        }
+
+        // bricks could come out with no size at all otherwise
+        for (name, variance, size) in [
+            ("brick_width", self.brick_width_variance, self.brick_width),
+            (
+                "brick_height",
+                self.brick_height_variance,
+                self.brick_height,
+            ),
+            ("brick_depth", self.brick_depth_variance, self.brick_depth),
+        ] {
+            if variance < 0.0 || variance >= size {
+                return Err(format!(
+                    "Wall style: `{}_variance` is {}, it must be at least 0 and less than `{}` {}",
+                    name, variance, name, size
+                ));
+            }
+        }
+
+        for (name, chance) in [
+            ("top_row_skip_chance", self.top_row_skip_chance),
+            ("split_chance", self.split_chance),
+        ] {
+            if !(0.0..=1.0).contains(&chance) {
+                return Err(format!(
+                    "Wall style: `{}` is {}, it must be between 0 and 1",
+                    name, chance
+                ));
+            }
+        }
+
+        // we need at least one row of bricks
+        if self.wall_height < self.brick_height {
+            return Err(format!(
+                "Wall style: wall height {} is lower than the brick height {}",
+                self.wall_height, self.brick_height
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+// Named styles, loaded from a JSON file of the form { "presets": [ { "name": "...", <WallStyle fields> }, ... ] }
+pub struct WallStylePresets {
+    pub presets: Vec<(String, WallStyle)>,
+}
+
+impl WallStylePresets {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|err| format!("Wall style presets: couldn't read {}: {}", path, err))?;
+        let v: Value = serde_json::from_str(&data)
+            .map_err(|err| format!("Wall style presets: couldn't parse {}: {}", path, err))?;
+
+        let mut presets = Vec::new();
+        for preset in v["presets"]
+            .as_array()
+            .ok_or("Wall style presets: missing `presets`")?
+        {
+            let name = preset["name"]
+                .as_str()
+                .ok_or("Wall style presets: preset has no `name`")?;
+            let style = WallStyle::from_json(preset)
+                .map_err(|err| format!("{} (preset '{}')", err, name))?;
+            presets.push((String::from(name), style));
+        }
+
+        if presets.is_empty() {
+            return Err(format!("Wall style presets: {} has no presets", path));
+        }
+
+        Ok(Self { presets })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&WallStyle> {
+        self.presets
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, style)| style)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broken_styles_are_rejected() {
+        assert!(WallStyle::default().validate().is_ok());
+
+        let broken = [
+            WallStyle {
+                brick_width: f32::NAN,
+                ..WallStyle::default()
+            },
+            WallStyle {
+                wall_height: f32::INFINITY,
+                ..WallStyle::default()
+            },
+            WallStyle {
+                brick_depth: 0.001,
+                ..WallStyle::default()
+            },
+            WallStyle {
+                brick_width_variance: 0.4,
+                ..WallStyle::default()
+            },
+            WallStyle {
+                brick_height_variance: -0.1,
+                ..WallStyle::default()
+            },
+            WallStyle {
+                split_chance: 1.5,
+                ..WallStyle::default()
+            },
+            WallStyle {
+                top_row_skip_chance: -0.2,
+                ..WallStyle::default()
+            },
+        ];
+        for style in broken {
+            assert!(style.validate().is_err(), "{:?}", style);
+        }
+    }
+}
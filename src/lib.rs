@@ -0,0 +1,37 @@
+// GPU-free core of country-slice: curves, brick walls and shadow decals can be generated without an OpenGL context.
+// The app (`main.rs`) re-exports these modules under the same paths, so they are used the same way from both sides.
+
+pub mod components {
+    pub mod transform;
+}
+
+pub mod geometry {
+    pub mod arch_style;
+    pub mod arches;
+    pub mod chunks;
+    pub mod curve;
+    pub mod dry_stone_wall;
+    pub mod fence;
+    pub mod hedge;
+    pub mod heightfield_raycast;
+    pub mod path_mask;
+    pub mod shadow_decal_mesh;
+    pub mod spline_curve;
+    pub mod terrain_settings;
+    pub mod wall_builder;
+    pub mod wall_constructor;
+    pub mod wall_junction;
+    pub mod wall_opening;
+    pub mod wall_style;
+}
+
+pub mod render {
+    pub mod mesh;
+}
+
+pub mod utils {
+    pub mod export_gltf;
+    pub mod heightmap;
+    pub mod load_gltf;
+    pub mod scene_file;
+}
@@ -6,6 +6,7 @@ use bevy_app::App;
 use bevy_ecs::prelude::*;
 
 use components::CursorRaycast;
+use geometry::chunks::{ChunkCoord, ChunkWindow};
 use glam::Vec3;
 use glutin::event_loop::ControlFlow;
 
@@ -40,8 +41,6 @@ const SCR_HEIGHT: u32 = 1200;
 
 const VALIDATE_SHADERS: bool = false;
 
-// TODO: make the walls realistic size.. atm wall height is 1.4m that's very low & arches look out of proportion
-
 // UX TEST ---- TODO:
 // 1. camera controls (rotation + translation)
 // 2. middle mouse wheel for brush size
@@ -66,14 +65,17 @@ fn main() {
     let mut temp_shaderwatch = ShaderWatch::new();
     let mut temp_assets_shader = AssetShaderLibrary::new();
 
+    // CHUNKS ----------------------------------------------------
+    let chunk_window = ChunkWindow::new(ChunkCoord::new(0, 0), CHUNK_WINDOW_RADIUS);
+    let terrain_presets = TerrainPresets::new();
+    let mut terrain = TerrainData::new(&chunk_window);
+    terrain.set_settings(terrain_presets.active_settings());
+
     // COMPUTE SHADERS -------------------------------------------
-    let compute_paths_mask = ComputePathMask(ComputeTexture::init(
-        "shaders/compute_path_mask.comp",
-        &mut temp_shaderwatch,
-        &mut temp_assets_shader,
-    ));
+    let path_mask = PathMask::new(&chunk_window);
     let compute_paths_blur = ComputePathBlur(ComputeTexture::init(
         "shaders/blur.comp",
+        chunk_window.texture_dims(PATH_TILE_DIMS),
         &mut temp_shaderwatch,
         &mut temp_assets_shader,
     ));
@@ -90,22 +92,35 @@ fn main() {
         .add_event::<CursorMoved>() // add these events, to avoid loading the whole bevy_window plugin
         .add_event::<CurveChangedEvent>()
         .add_event::<CurveDeletedEvent>()
+        .add_event::<PathsChangedEvent>()
+        .add_event::<TerrainChangedEvent>()
         .add_event::<BrushModeJustChanged>()
         .insert_resource(CursorPosition(glam::Vec2::ZERO))
         .insert_resource(WindowSize::new(SCR_WIDTH, SCR_HEIGHT))
         .insert_resource(MainCamera::new(SCR_WIDTH as f32 / SCR_HEIGHT as f32))
         .insert_resource(temp_shaderwatch)
         .insert_resource(BrushMode::default())
+        .insert_resource(StrokeFitting::default())
         .insert_resource(WallManager::new())
+        .insert_resource(PathManager::new())
+        .insert_resource(PathBrush::new())
+        .insert_resource(EraserBrush::new())
+        .insert_resource(TerrainBrush::new())
+        .insert_resource(WallStyles::new())
+        .insert_resource(WallCurveEdit::new())
+        .insert_resource(OpeningDrag::default())
+        .insert_resource(CommandHistory::new())
         .insert_resource(CursorRaycast(Vec3::ZERO))
         .insert_resource(AssetMeshLibrary::new())
         .insert_resource(AssetVAOLibrary::new())
         .insert_resource(temp_assets_shader)
-        .insert_resource(compute_paths_mask)
+        .insert_resource(path_mask)
         .insert_resource(compute_paths_blur)
         .insert_resource(compute_arches_indirect)
         .insert_resource(compute_curve_segments)
-        .insert_resource(TerrainData::new())
+        .insert_resource(terrain)
+        .insert_resource(terrain_presets)
+        .insert_resource(chunk_window)
         .add_stage_after(
             bevy_app::CoreStage::PreUpdate,
             "opengl",
@@ -119,16 +134,31 @@ fn main() {
         .add_system_to_stage("opengl", shaderwatch.system().label("reload_shaders"))
         .add_system_to_stage("opengl", build_missing_vaos.system().label("build_vaos"))
         .add_system_to_stage("opengl", rebuild_vaos.system().after("build_vaos"))
+        .add_system_to_stage("opengl", history_begin_stroke.system())
+        .add_system_to_stage("opengl", check_arches.system())
         //.add_system(draw_curve.system().label("usercurve"))
         .add_system(main_camera_update.system())
         .add_system(mouse_raycast.system())
         .add_system(mode_manager.system())
         .add_system(brush_preview.system())
         .add_system(draw_wall.system().label("usercurve"))
-        .add_system(eraser.system().label("usercurve"))
+        .add_system(draw_path.system())
+        .add_system(delete_path.system())
+        .add_system(eraser_brush.system().label("eraser_brush"))
+        .add_system(eraser.system().label("usercurve").after("eraser_brush"))
         .add_system(curve_preview.system().after("usercurve"))
         .add_system(delete_wall.system().label("usercurve"))
         .add_system(signifier_continue_wall.system())
+        .add_system(wall_style_manager.system())
+        .add_system(arch_shape_manager.system())
+        .add_system(construction_manager.system())
+        .add_system(reroll_wall.system())
+        .add_system(select_wall.system())
+        .add_system(place_opening.system().label("usercurve"))
+        .add_system(edit_selected_wall.system())
+        .add_system(drag_wall_handle.system())
+        .add_system(wall_handles_preview.system())
+        .add_system(export_walls.system())
         .add_system_to_stage(
             "main_singlethread",
             update_curve_ssbo.system().after("usercurve"),
@@ -137,8 +167,20 @@ fn main() {
             "main_singlethread",
             walls_update.system().after("usercurve"),
         )
+        .add_system_to_stage("main_singlethread", highlight_selected_wall.system())
+        .add_system_to_stage("main_singlethread", stream_chunks.system())
         .add_system_to_stage("main_singlethread", update_terrain.system())
+        .add_system_to_stage("main_singlethread", terrain_preset_manager.system())
+        .add_system_to_stage("main_singlethread", sculpt_terrain.system())
+        .add_system_to_stage("main_singlethread", conform_to_terrain.system())
         .add_system_to_stage("main_singlethread", clear_canvas.system())
+        .add_system_to_stage("main_singlethread", save_scene.system())
+        .add_system_to_stage("main_singlethread", load_scene.system())
+        .add_system_to_stage("main_singlethread", export_heightmap.system())
+        .add_system_to_stage("main_singlethread", import_heightmap.system())
+        .add_system_to_stage("main_singlethread", history_end_stroke.system())
+        .add_system_to_stage("main_singlethread", undo_redo.system())
+        .add_system_to_stage("main_singlethread", update_path_mask.system())
         .add_system_to_stage("main_singlethread", delete_dropped_ssbos.system())
         .add_system_to_stage(
             "main_singlethread",
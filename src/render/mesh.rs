@@ -1,8 +1,8 @@
-use gl::types::*;
 use std::collections::HashMap;
 use std::mem;
 use std::os::raw::c_void;
 
+#[derive(Default)]
 pub struct Mesh {
     pub attributes: HashMap<String, VertexAttributeValues>,
     pub indices: Vec<u32>,
@@ -101,18 +101,18 @@ impl VertexAttributeValues {
     pub fn size_in_bytes(&self) -> usize {
         match self {
             // float
-            VertexAttributeValues::Float32(values) => values.len() * mem::size_of::<GLfloat>(),
+            VertexAttributeValues::Float32(values) => values.len() * mem::size_of::<f32>(),
             VertexAttributeValues::Float32x2(values) => {
-                values[0].len() * values.len() * mem::size_of::<GLfloat>()
+                values[0].len() * values.len() * mem::size_of::<f32>()
             }
             VertexAttributeValues::Float32x3(values) => {
-                values[0].len() * values.len() * mem::size_of::<GLfloat>()
+                values[0].len() * values.len() * mem::size_of::<f32>()
             }
             VertexAttributeValues::Float32x4(values) => {
-                values[0].len() * values.len() * mem::size_of::<GLfloat>()
+                values[0].len() * values.len() * mem::size_of::<f32>()
             }
             // int
-            VertexAttributeValues::Sint32(values) => values.len() * mem::size_of::<GLint>(),
+            VertexAttributeValues::Sint32(values) => values.len() * mem::size_of::<i32>(),
         }
     }
 
@@ -137,21 +137,12 @@ impl VertexAttributeValues {
         }
     }
 
-    pub fn gl_type(&self) -> GLenum {
+    pub fn stride(&self) -> i32 {
         match self {
             Self::Float32(_) | Self::Float32x2(_) | Self::Float32x3(_) | Self::Float32x4(_) => {
-                gl::FLOAT
+                self.size() * mem::size_of::<f32>() as i32
             }
-            Self::Sint32(_) => gl::INT,
-        }
-    }
-
-    pub fn stride(&self) -> GLsizei {
-        match self {
-            Self::Float32(_) | Self::Float32x2(_) | Self::Float32x3(_) | Self::Float32x4(_) => {
-                self.size() * mem::size_of::<GLfloat>() as GLsizei
-            }
-            Self::Sint32(_) => self.size() * mem::size_of::<GLint>() as GLsizei,
+            Self::Sint32(_) => self.size() * mem::size_of::<i32>() as i32,
         }
     }
 
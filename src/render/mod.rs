@@ -1,11 +1,12 @@
 pub mod camera;
-pub mod mesh;
 pub mod shader;
 pub mod shaderwatch;
 pub mod ssbo;
 pub mod texture;
 pub mod vao;
 
+pub use country_slice::render::mesh;
+
 /*
 
 Binding points:
@@ -26,51 +26,117 @@ pub struct GLShaderStorageBuffer<T> {
 
 impl<T: Copy> GLShaderStorageBuffer<T> {
     pub fn new(data: &Vec<T>, buffer_size: usize, binding_point: u32) -> Self {
-        Self {
+        let mut buffer = Self {
             id: unsafe { create_storage_buffer::<T>(buffer_size) },
             buffer_size,
-            instance_num: data.len(),
+            instance_num: 0,
             binding_point,
             _marker: PhantomData,
-        }
+        };
+        buffer.update_after(0, data);
+        buffer
     }
 
-    pub fn update(&mut self, data: &[T]) {
-        unsafe {
-            assert!(data.len() <= self.buffer_size);
-
-            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.id);
-            let ptr = gl::MapBuffer(gl::SHADER_STORAGE_BUFFER, gl::WRITE_ONLY);
+    // How many elements fit before the buffer has to grow
+    pub fn capacity(&self) -> usize {
+        self.buffer_size
+    }
 
-            assert!(!ptr.is_null());
+    // Grows the buffer so that at least `size` elements fit, keeping what it holds.
+    // If that's more than a storage block can hold, it grows as much as it can, and returns false after logging an error
+    pub fn reserve(&mut self, size: usize) -> bool {
+        if size <= self.buffer_size {
+            return true;
+        }
 
-            let dst =
-                std::slice::from_raw_parts_mut(ptr as *mut T, self.buffer_size.min(data.len()));
-            dst.copy_from_slice(data);
-            gl::UnmapBuffer(gl::SHADER_STORAGE_BUFFER);
+        let max_size = max_storage_block_elements::<T>();
+        if size > max_size {
+            log::error!(
+                "SSBO {}: {} elements don't fit in a storage block, which holds up to {}",
+                self.id,
+                size,
+                max_size
+            );
+        }
 
-            self.instance_num = data.len();
+        let new_size = size.max(self.buffer_size * 2).min(max_size);
+        if new_size > self.buffer_size {
+            unsafe {
+                let id = create_storage_buffer::<T>(new_size);
+
+                gl::BindBuffer(gl::COPY_READ_BUFFER, self.id);
+                gl::BindBuffer(gl::COPY_WRITE_BUFFER, id);
+                gl::CopyBufferSubData(
+                    gl::COPY_READ_BUFFER,
+                    gl::COPY_WRITE_BUFFER,
+                    0,
+                    0,
+                    (std::mem::size_of::<T>() * self.buffer_size) as GLsizeiptr,
+                );
+
+                // Unbind
+                gl::BindBuffer(gl::COPY_READ_BUFFER, 0);
+                gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+
+                gl::DeleteBuffers(1, &self.id);
+                log::debug!(
+                    "SSBO {} grown from {} to {} elements, id: {}",
+                    self.id,
+                    self.buffer_size,
+                    new_size,
+                    id
+                );
+
+                self.id = id;
+                self.buffer_size = new_size;
+            }
         }
+
+        size <= self.buffer_size
     }
 
-    pub fn update_element(&mut self, data: T, index: usize) {
-        unsafe {
-            assert!(index < self.buffer_size);
+    // Keeps the first `offset` elements, and replaces everything after them with `data`.
+    // The buffer grows if `data` doesn't fit, elements that still don't fit are dropped
+    pub fn update_after(&mut self, offset: usize, data: &[T]) {
+        if offset > self.instance_num {
+            log::error!(
+                "SSBO {}: can't keep {} elements, it only holds {}",
+                self.id,
+                offset,
+                self.instance_num
+            );
+            return;
+        }
 
-            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.id);
+        let data = if self.reserve(offset + data.len()) {
+            data
+        } else {
+            let fitting = self.buffer_size - offset;
+            log::error!(
+                "SSBO {}: only {} of {} elements were uploaded",
+                self.id,
+                fitting,
+                data.len()
+            );
+            &data[..fitting]
+        };
 
-            let offset = (std::mem::size_of::<T>() * index) as GLsizeiptr;
-            let size = std::mem::size_of::<T>() as GLsizeiptr;
+        unsafe {
+            if !data.is_empty() {
+                gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.id);
 
-            gl::BufferSubData(
-                gl::SHADER_STORAGE_BUFFER,
-                offset,
-                size,
-                &data as *const T as *const std::ffi::c_void,
-            );
+                gl::BufferSubData(
+                    gl::SHADER_STORAGE_BUFFER,
+                    (std::mem::size_of::<T>() * offset) as GLsizeiptr,
+                    std::mem::size_of_val(data) as GLsizeiptr,
+                    data.as_ptr() as *const std::ffi::c_void,
+                );
 
-            // Unbind
-            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+                // Unbind
+                gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+            }
+
+            self.instance_num = offset + data.len();
         }
     }
 
@@ -87,6 +153,26 @@ impl<T: Copy> GLShaderStorageBuffer<T> {
         }
     }
 
+    // Copies the first `count` elements back from the GPU
+    pub fn read(&self, count: usize) -> Vec<T> {
+        let count = count.min(self.buffer_size);
+        let mut data: Vec<T> = Vec::with_capacity(count);
+        unsafe {
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.id);
+            gl::GetBufferSubData(
+                gl::SHADER_STORAGE_BUFFER,
+                0,
+                (std::mem::size_of::<T>() * count) as GLsizeiptr,
+                data.as_mut_ptr() as *mut std::ffi::c_void,
+            );
+            data.set_len(count);
+
+            // Unbind
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+        }
+        data
+    }
+
     pub fn gl_id(&self) -> u32 {
         self.id
     }
@@ -101,6 +187,15 @@ impl<T> Drop for GLShaderStorageBuffer<T> {
     }
 }
 
+// Largest storage block the GPU can bind, in elements of `T`
+fn max_storage_block_elements<T>() -> usize {
+    let mut max_bytes = 0;
+    unsafe {
+        gl::GetInteger64v(gl::MAX_SHADER_STORAGE_BLOCK_SIZE, &mut max_bytes);
+    }
+    max_bytes as usize / std::mem::size_of::<T>()
+}
+
 pub unsafe fn create_storage_buffer<T>(size: usize) -> u32 {
     let mut ssbo = 0; // shader storage buffer object
     gl::GenBuffers(1, &mut ssbo);
@@ -37,17 +37,18 @@ impl GlTextureRGBAf32 {
         Self { id: texture, dims }
     }
 
-    pub fn update(&mut self, raw_f32_pixels: &[f32]) {
+    // Updates the `dims` large part of the texture that starts at pixel `offset`
+    pub fn update_region(&mut self, offset: (i32, i32), dims: (i32, i32), raw_f32_pixels: &[f32]) {
         unsafe {
             gl::BindTexture(gl::TEXTURE_2D, self.id);
 
             gl::TexSubImage2D(
                 gl::TEXTURE_2D,
                 0,
-                0,
-                0,
-                self.dims.0,
-                self.dims.1,
+                offset.0,
+                offset.1,
+                dims.0,
+                dims.1,
                 gl::RGBA,
                 gl::FLOAT,
                 &raw_f32_pixels[0] as *const f32 as *const std::ffi::c_void,
@@ -1,4 +1,7 @@
-use super::{mesh::Mesh, shader::ShaderProgramId};
+use super::{
+    mesh::{Mesh, VertexAttributeValues},
+    shader::ShaderProgramId,
+};
 use gl::types::*;
 use std::ffi::CString;
 use std::mem;
@@ -106,7 +109,7 @@ fn build_vao(
                 gl::VertexAttribPointer(
                     layout as u32,       // This sets the location of the vertex attribute to (layout = 0)
                     attribute.size(), // specifies the size of the vertex attribute. The position attribute is a vec3 so it is composed of 3 values
-                    attribute.gl_type(), //specifies the type of the data
+                    gl_type(attribute), //specifies the type of the data
                     gl::FALSE,        // specifies if we want the data to be normalized.
                     attribute.stride(), // the stride tells us the space between consecutive vertex attributes
                     ptr::null(), // the offset of where the position data begins in the buffer. Since the position data is at the start of the data array this value is just 0.
@@ -119,3 +122,13 @@ fn build_vao(
     }
     (GLVertexArray(vao), vbos)
 }
+
+fn gl_type(attribute: &VertexAttributeValues) -> GLenum {
+    match attribute {
+        VertexAttributeValues::Float32(_)
+        | VertexAttributeValues::Float32x2(_)
+        | VertexAttributeValues::Float32x3(_)
+        | VertexAttributeValues::Float32x4(_) => gl::FLOAT,
+        VertexAttributeValues::Sint32(_) => gl::INT,
+    }
+}
@@ -3,9 +3,6 @@ use std::ptr;
 
 use bevy_ecs::prelude::World;
 
-use bevy_input::mouse::MouseButton;
-use bevy_input::Input;
-
 use glutin::{window::Window, ContextWrapper, PossiblyCurrent};
 
 use crate::asset_libraries::{
@@ -17,13 +14,12 @@ use crate::render::{
     shader::{GlUniform, ShaderProgram},
     vao::VAO,
 };
+use crate::geometry::chunks::ChunkWindow;
 use crate::resources::compute_path_mask::*;
-use crate::resources::curve_segments_pass::CURVE_BUFFER_SIZE;
 use crate::resources::CurveSegmentsComputePass;
-use crate::systems::mode_manager::{BrushMode, EraseLayer};
 use crate::window_events::WindowSize;
 use crate::{components::*, TerrainData};
-use crate::{ComputeArchesIndirect, ComputePathMask, CursorRaycast};
+use crate::ComputeArchesIndirect;
 
 use crate::utils::custom_macro::log_if_error;
 
@@ -40,11 +36,19 @@ pub fn render(ecs: &mut World, windowed_context: &mut ContextWrapper<PossiblyCur
     unsafe {
         gl::DepthMask(gl::TRUE);
 
+        // the bricks that didn't fit last frame are laid out again, in a larger buffer
+        ecs.get_resource_mut::<ComputeArchesIndirect>().unwrap().grow_to_fit();
+
         let indirect_test = ecs.get_resource::<ComputeArchesIndirect>().unwrap();
         let compute_curve_segments = ecs.get_resource::<CurveSegmentsComputePass>().unwrap();
         let path_mask = &ecs.get_resource::<ComputePathBlur>().unwrap().0;
         let assets_shader = ecs.get_resource::<AssetShaderLibrary>().unwrap();
 
+        // path mask and terrain textures hold the chunks of the window
+        let chunk_window = ecs.get_resource::<ChunkWindow>().unwrap();
+        let window_ws_min = chunk_window.min().to_array();
+        let window_ws_size = chunk_window.size().to_array();
+
         // CURVE SEGMNETS COMPUTE
         {
             compute_curve_segments.reset_cmd_buffer();
@@ -52,11 +56,12 @@ pub fn render(ecs: &mut World, windowed_context: &mut ContextWrapper<PossiblyCur
             compute_curve_segments.bind(
                 assets_shader,
                 path_mask.texture.id,
-                PATH_MASK_WS_DIMS,
+                window_ws_min,
+                window_ws_size,
                 _img_unit,
             );
 
-            gl::DispatchCompute(CURVE_BUFFER_SIZE as u32, 1, 1);
+            gl::DispatchCompute(compute_curve_segments.curves_buffer.instance_num as u32, 1, 1);
             gl::MemoryBarrier(gl::COMMAND_BARRIER_BIT | gl::SHADER_STORAGE_BARRIER_BIT);
         }
 
@@ -69,7 +74,8 @@ pub fn render(ecs: &mut World, windowed_context: &mut ContextWrapper<PossiblyCur
             assets_shader,
             &compute_curve_segments.segments_buffer,
             path_mask.texture.id,
-            PATH_MASK_WS_DIMS,
+            window_ws_min,
+            window_ws_size,
             _img_unit,
         ); // use shader & bind command buffer & bind transforms buffer & bind road mask
 
@@ -77,65 +83,11 @@ pub fn render(ecs: &mut World, windowed_context: &mut ContextWrapper<PossiblyCur
         gl::DispatchComputeIndirect(0);
         gl::MemoryBarrier(gl::COMMAND_BARRIER_BIT | gl::SHADER_STORAGE_BARRIER_BIT);
 
-        // COMPUTE PATHS PASS -----------------------------------------------------------------------
+        // PATH MASK is rasterized from the paths on the CPU, see `update_path_mask`
 
-        let path_mask = &ecs.get_resource::<ComputePathMask>().unwrap().0;
+        let path_mask = ecs.get_resource::<PathMask>().unwrap();
         let path_blur = &ecs.get_resource::<ComputePathBlur>().unwrap().0;
-        let mouse = ecs.get_resource::<CursorRaycast>().unwrap();
-        let mouse_button_input = ecs.get_resource::<Input<MouseButton>>().unwrap();
         let assets_shader = ecs.get_resource::<AssetShaderLibrary>().unwrap();
-        let _mode = ecs.get_resource::<BrushMode>().unwrap();
-        // Only update shader if LMB is pressed and we are in Path mode
-
-        if (matches!(_mode, BrushMode::Path) || matches!(_mode, BrushMode::Eraser(EraseLayer::All)))
-            && mouse_button_input.pressed(MouseButton::Left)
-        {
-            let shader = assets_shader.get(path_mask.compute_program).unwrap();
-            gl::UseProgram(shader.id());
-
-            match _mode {
-                BrushMode::Wall => panic!(),
-                BrushMode::Path => {
-                    log_if_error!(shader.set_gl_uniform("is_additive", GlUniform::Bool(true)))
-                }
-                BrushMode::Eraser(..) => {
-                    log_if_error!(shader.set_gl_uniform("is_additive", GlUniform::Bool(false)))
-                }
-            }
-
-            // connect shader's uniform variable to our texture
-            // instead of name can specify in shader the binding, for ex "layout(rgba32f, binding = 0)"
-            let uniform_name = CString::new("img_output").unwrap();
-            let tex_location =
-                gl::GetUniformLocation(shader.id(), uniform_name.as_ptr() as *const i8);
-            gl::Uniform1i(tex_location, _img_unit as i32);
-
-            // bind texture
-            gl::BindImageTexture(
-                _img_unit,
-                path_mask.texture.id,
-                0,
-                gl::FALSE,
-                0,
-                gl::READ_WRITE,
-                gl::RGBA32F,
-            );
-
-            log_if_error!(
-                shader.set_gl_uniform("Mouse_Position", GlUniform::Vec3(mouse.0.to_array()))
-            );
-            log_if_error!(shader.set_gl_uniform("path_mask_ws_dims", GlUniform::Vec2([20.0, 20.0])));
-            gl::DispatchCompute(
-                path_mask.texture.dims.0 as u32,
-                path_mask.texture.dims.1 as u32,
-                1,
-            );
-
-            _img_unit += 1;
-        }
-
-        // make sure writing to image has finished before read
-        gl::MemoryBarrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
 
         // BLUR PATH MASK -------------------------------------
 
@@ -220,10 +172,12 @@ pub fn render(ecs: &mut World, windowed_context: &mut ContextWrapper<PossiblyCur
             Option<&TransparencyPass>,
             Option<&IndirectDraw>,
             Option<&RoadComponent>,
+            Option<&SelectedWall>,
         )>();
         let assets_vao = ecs.get_resource::<AssetVAOLibrary>().unwrap();
         let assets_shader = ecs.get_resource::<AssetShaderLibrary>().unwrap();
         let indirect_test = ecs.get_resource::<ComputeArchesIndirect>().unwrap();
+        let compute_curve_segments = ecs.get_resource::<CurveSegmentsComputePass>().unwrap();
         let terrain_data = ecs.get_resource::<TerrainData>().unwrap();
 
         let mut transparent_pass = Vec::new();
@@ -239,6 +193,7 @@ pub fn render(ecs: &mut World, windowed_context: &mut ContextWrapper<PossiblyCur
             transparency,
             indirect_draw,
             road,
+            selected_wall,
         ) in query.iter(ecs)
         {
             let vao = assets_vao
@@ -272,6 +227,7 @@ pub fn render(ecs: &mut World, windowed_context: &mut ContextWrapper<PossiblyCur
                 //reset
                 gl::ActiveTexture(gl::TEXTURE0);
             }
+            set_chunk_window_uniforms(shader, window_ws_min, window_ws_size);
 
             // MEOWMEOWcheckforspecialtexture
             if debug_display_path_mask.is_some() {
@@ -310,6 +266,8 @@ pub fn render(ecs: &mut World, windowed_context: &mut ContextWrapper<PossiblyCur
             if indirect_draw.is_some() {
                 // used for disabling discarding of fragments
                 log_if_error!(shader.set_gl_uniform("is_arch", GlUniform::Bool(true)));
+                // arches are always masonry
+                log_if_error!(shader.set_gl_uniform("wall_tint", GlUniform::Vec3([1.0; 3])));
                 indirect_test
                     .transforms_buffer
                     .bind(&shader, "transforms_buffer");
@@ -327,16 +285,26 @@ pub fn render(ecs: &mut World, windowed_context: &mut ContextWrapper<PossiblyCur
                 ));
 
                 log_if_error!(shader
-                    .set_gl_uniform("wall_length", GlUniform::Float(instanced_wall.wall_length)));
+                    .set_gl_uniform("wall_height", GlUniform::Float(instanced_wall.wall_height)));
+
+                log_if_error!(shader.set_gl_uniform("wall_tint", GlUniform::Vec3(instanced_wall.tint)));
 
                 // used for disabling discarding of fragments
                 log_if_error!(shader.set_gl_uniform("is_arch", GlUniform::Bool(false)));
 
+                log_if_error!(shader.set_gl_uniform(
+                    "is_selected",
+                    GlUniform::Bool(selected_wall.is_some()),
+                ));
+
                 // bind to shader
                 instanced_wall
                     .instance_buffer
                     .bind(shader, "instanced_wall_data");
 
+                // the openings under the arches are cut out of the wall
+                compute_curve_segments.bind_arches(shader);
+
                 // bind compute shader texture
                 gl::BindTexture(gl::TEXTURE_2D, texture_buffer);
 
@@ -372,6 +340,7 @@ pub fn render(ecs: &mut World, windowed_context: &mut ContextWrapper<PossiblyCur
                 //reset
                 gl::ActiveTexture(gl::TEXTURE0);
             }
+            set_chunk_window_uniforms(shader, window_ws_min, window_ws_size);
 
             // atm, I'm just binding the road mask to anything in transparency pass (ATM, only shadows have transparency pass, so we can just bind the texture)
             // TODO: in the future, need to check for whether its a shadow
@@ -398,3 +367,9 @@ pub fn render(ecs: &mut World, windowed_context: &mut ContextWrapper<PossiblyCur
     }
     windowed_context.swap_buffers().unwrap();
 }
+
+// it's OK if the shader doesn't sample the terrain or the path mask, that's not an error
+unsafe fn set_chunk_window_uniforms(shader: &ShaderProgram, ws_min: [f32; 2], ws_size: [f32; 2]) {
+    let _result = shader.set_gl_uniform("chunk_window_ws_min", GlUniform::Vec2(ws_min));
+    let _result = shader.set_gl_uniform("chunk_window_ws_size", GlUniform::Vec2(ws_size));
+}
@@ -1,5 +1,4 @@
 use gl::types::GLsizeiptr;
-use glam::Vec3;
 
 use crate::{
     asset_libraries::{shader_library::AssetShaderLibrary, Handle},
@@ -12,6 +11,8 @@ use crate::{
 };
 
 const COMMAND_BUFFER_SIZE: usize = 1000;
+// The transforms buffer starts this large, and grows when the bricks don't fit, see `grow_to_fit`
+const TRANSFORMS_BUFFER_SIZE: usize = 10000;
 
 pub struct ComputeArchesIndirect {
     pub compute_program: Handle<ShaderProgram>,
@@ -19,8 +20,8 @@ pub struct ComputeArchesIndirect {
     pub cmd_buffer_binding_point: u32,
     //
     pub transforms_buffer: GLShaderStorageBuffer<glam::Mat4>,
-    //
-    pub curves_buffer: GLShaderStorageBuffer<CurveDataSSBO>,
+    // how many bricks the last dispatch laid out, including the ones that didn't fit in `transforms_buffer`
+    requested_bricks_buffer: GLShaderStorageBuffer<u32>,
 }
 
 impl ComputeArchesIndirect {
@@ -51,8 +52,12 @@ impl ComputeArchesIndirect {
                 compute_program: handle,
                 draw_indirect_cmd_buffer: ibo,
                 cmd_buffer_binding_point: 0,
-                transforms_buffer: GLShaderStorageBuffer::<glam::Mat4>::new(&vec![], 10000, 2),
-                curves_buffer: GLShaderStorageBuffer::<CurveDataSSBO>::new(&vec![], 1000, 3),
+                transforms_buffer: GLShaderStorageBuffer::<glam::Mat4>::new(
+                    &vec![],
+                    TRANSFORMS_BUFFER_SIZE,
+                    2,
+                ),
+                requested_bricks_buffer: GLShaderStorageBuffer::<u32>::new(&vec![0], 1, 7),
             }
         }
     }
@@ -62,6 +67,7 @@ impl ComputeArchesIndirect {
         assets_shader: &AssetShaderLibrary,
         segments_buffer: &GLShaderStorageBuffer<super::ArchSegmentDataSSBO>,
         path_mask: u32,
+        path_mask_ws_min: [f32; 2],
         path_mask_ws_dims: [f32; 2],
         path_mask_img_unit: u32,
     ) {
@@ -94,6 +100,9 @@ impl ComputeArchesIndirect {
             log_if_error!(
                 shader.set_gl_uniform("path_mask", GlUniform::Int(path_mask_img_unit as i32),)
             );
+            log_if_error!(
+                shader.set_gl_uniform("path_mask_ws_min", GlUniform::Vec2(path_mask_ws_min))
+            );
             log_if_error!(
                 shader.set_gl_uniform("path_mask_ws_dims", GlUniform::Vec2(path_mask_ws_dims))
             );
@@ -108,11 +117,11 @@ impl ComputeArchesIndirect {
                 gl::RGBA32F,
             );
 
-            // bind curve ssbo
-            //self.curves_buffer.bind(shader, "curves_buffer");
-
             // bind segments buffer
             segments_buffer.bind(&shader, "segments_buffer");
+
+            self.requested_bricks_buffer
+                .bind(shader, "requested_bricks_buffer");
         }
     }
 
@@ -135,16 +144,54 @@ impl ComputeArchesIndirect {
         }
     }
 
+    // Number of bricks the last dispatch wrote to the transforms buffer
+    pub fn read_instance_count(&self) -> u32 {
+        let mut cmd = DrawElementsIndirectCommand {
+            _count: 0,
+            _instance_count: 0,
+            _first_index: 0,
+            _base_vertex: 0,
+            _base_instance: 0,
+        };
+        unsafe {
+            gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, self.draw_indirect_cmd_buffer);
+            gl::GetBufferSubData(
+                gl::DRAW_INDIRECT_BUFFER,
+                0,
+                std::mem::size_of::<DrawElementsIndirectCommand>() as GLsizeiptr,
+                &mut cmd as *mut DrawElementsIndirectCommand as *mut std::ffi::c_void,
+            );
+        }
+        cmd._instance_count
+    }
+
+    // Grows the transforms buffer if the bricks of the last dispatch didn't fit in it, so that they're all drawn from
+    // the next dispatch on. Resets the count of laid out bricks, so it's called once before every dispatch
+    pub fn grow_to_fit(&mut self) {
+        let requested = self.requested_bricks_buffer.read(1)[0] as usize;
+        self.requested_bricks_buffer.update_after(0, &[0]);
+        if requested <= self.transforms_buffer.capacity() {
+            return;
+        }
+
+        log::error!(
+            "{} arch bricks were laid out, but only {} fit in the transforms buffer, growing it",
+            requested,
+            self.transforms_buffer.capacity()
+        );
+        self.transforms_buffer.reserve(requested);
+    }
+
     pub fn reset_transform_buffer(&self) {
         unsafe {
-            let data = &[glam::Mat4::IDENTITY; 10000];
+            let data = vec![glam::Mat4::IDENTITY; self.transforms_buffer.capacity()];
             gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.transforms_buffer.gl_id());
             let ptr = gl::MapBuffer(gl::SHADER_STORAGE_BUFFER, gl::WRITE_ONLY);
 
             assert!(!ptr.is_null());
 
             let dst = std::slice::from_raw_parts_mut(ptr as *mut glam::Mat4, data.len());
-            dst.copy_from_slice(data);
+            dst.copy_from_slice(&data);
             gl::UnmapBuffer(gl::SHADER_STORAGE_BUFFER);
         }
     }
@@ -160,46 +207,15 @@ pub struct DrawElementsIndirectCommand {
     pub _base_instance: u32,
 }
 
+// Where the points of a curve are in the points buffer of `CurveSegmentsComputePass`, see `upload_curves`,
+// and the `ArchStyle` of its wall
 #[derive(Clone, Copy)]
 #[repr(C)]
 pub struct CurveDataSSBO {
+    pub points_offset: u32,
     pub points_count: u32,
-    pub pad0: u32,
-    pub pad1: u32,
-    pub pad2: u32,
-    pub positions: [[f32; 4]; 1000], //buffer
-}
-
-impl CurveDataSSBO {
-    pub fn from(curve: &crate::geometry::curve::Curve) -> Self {
-        let points_count = curve.points.len() as u32;
-        let mut positions = [[0.0; 4]; 1000];
-
-        positions.iter_mut().enumerate().for_each(|(i, p)| {
-            *p = curve
-                .points
-                .get(i)
-                .unwrap_or(&Vec3::ZERO)
-                .extend(1.0)
-                .to_array()
-        });
-
-        Self {
-            points_count,
-            pad0: 0,
-            pad1: 0,
-            pad2: 0,
-            positions,
-        }
-    }
-
-    pub fn empty() -> Self {
-        CurveDataSSBO {
-            points_count: 0,
-            pad0: 0,
-            pad1: 0,
-            pad2: 0,
-            positions: [[0.0; 4]; 1000],
-        }
-    }
+    pub arch_shape: u32,
+    pub arch_voussoirs: u32,
+    pub arch_span: f32,
+    pub arch_rise: f32,
 }
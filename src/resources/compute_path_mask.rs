@@ -1,7 +1,167 @@
+use std::collections::HashMap;
+
+use glam::Vec2;
+
 use super::compute_textures::ComputeTexture;
+use crate::{
+    geometry::{
+        chunks::{ChunkCoord, ChunkWindow, CHUNK_SIZE},
+        curve::Curve,
+        path_mask::{rasterize_paths, PathMaskImage, BLUR_SIZE},
+    },
+    render::texture::GlTextureRGBAf32,
+};
+
+// Resolution of the path mask of a single chunk
+pub const PATH_TILE_DIMS: (i32, i32) = (512, 512);
+
+// Rasterized from the paths in `PathManager` on the CPU, one tile per chunk, see `update_path_mask`
+pub struct PathMask {
+    // greyscale, allocated for chunks that paths were drawn in
+    tiles: HashMap<ChunkCoord, Vec<f32>>,
+    // XZ points and width of the paths as they were last rasterized, by index, to find what changed since
+    rasterized: HashMap<usize, (Vec<Vec2>, f32)>,
+    // tiles of the chunks in the window, chunks without a tile are empty
+    pub texture: GlTextureRGBAf32,
+}
+
+impl PathMask {
+    pub fn new(window: &ChunkWindow) -> Self {
+        let mut texture = GlTextureRGBAf32::new(window.texture_dims(PATH_TILE_DIMS), None);
+        texture.clear();
+        Self {
+            tiles: HashMap::new(),
+            rasterized: HashMap::new(),
+            texture,
+        }
+    }
+
+    // Rasterizes the tiles under the paths that were added, changed or removed since the last time, `paths` are
+    // (index, centerline, width). Only tiles that changed are uploaded
+    pub fn rasterize(&mut self, paths: &[(usize, &Curve, f32)], window: &ChunkWindow) {
+        // the blurred mask changes this far around the pixels that changed
+        let blur_reach = BLUR_SIZE * CHUNK_SIZE / PATH_TILE_DIMS.0 as f32;
+
+        let mut coords = Vec::new();
+        let mut dirty = |points: &[Vec2], width: f32| {
+            if let Some((min, max)) = bounds(points) {
+                let reach = Vec2::splat(width / 2.0 + blur_reach);
+                coords.extend(ChunkCoord::covering(min - reach, max + reach));
+            }
+        };
+
+        let mut rasterized = HashMap::new();
+        for (index, curve, width) in paths {
+            let points: Vec<Vec2> = curve.points.iter().map(|p| Vec2::new(p.x, p.z)).collect();
+            match self.rasterized.remove(index) {
+                Some((previous, previous_width))
+                    if previous_width == *width && points.starts_with(&previous) =>
+                {
+                    // points were added while the path is drawn, only the new segments are dirty
+                    let from = previous.len().saturating_sub(1);
+                    if from + 1 < points.len() || previous.is_empty() {
+                        dirty(&points[from..], *width);
+                    }
+                }
+                Some((previous, previous_width)) => {
+                    dirty(&previous, previous_width);
+                    dirty(&points, *width);
+                }
+                None => dirty(&points, *width),
+            }
+            rasterized.insert(*index, (points, *width));
+        }
+        // the ones that are left were removed
+        for (previous, previous_width) in self.rasterized.values() {
+            dirty(previous, *previous_width);
+        }
+        self.rasterized = rasterized;
+
+        coords.sort_unstable_by_key(|c| (c.x, c.z));
+        coords.dedup();
+
+        for coord in coords {
+            // only the paths that reach into the tile
+            let (tile_min, tile_max) = (coord.min(), coord.min() + Vec2::splat(CHUNK_SIZE));
+            let in_tile: Vec<(&Curve, f32)> = paths
+                .iter()
+                .filter(|(index, _, width)| {
+                    bounds(&self.rasterized[index].0).is_some_and(|(min, max)| {
+                        let radius = Vec2::splat(width / 2.0);
+                        (min - radius).cmple(tile_max).all() && (max + radius).cmpge(tile_min).all()
+                    })
+                })
+                .map(|(_, curve, width)| (*curve, *width))
+                .collect();
+
+            let tile = rasterize_paths(&in_tile, PATH_TILE_DIMS, tile_min, Vec2::splat(CHUNK_SIZE));
+            // tiles that paths left are dropped once they're empty
+            let empty = tile.iter().all(|v| *v == 0.0);
+            if self.tiles.get(&coord) == Some(&tile) || (empty && !self.tiles.contains_key(&coord))
+            {
+                continue;
+            }
+
+            self.upload_tile(window, coord, Some(&tile));
+            if empty {
+                self.tiles.remove(&coord);
+            } else {
+                self.tiles.insert(coord, tile);
+            }
+        }
+    }
+
+    // After the window moved, every tile is somewhere else in the texture
+    pub fn upload_window(&mut self, window: &ChunkWindow) {
+        for coord in window.chunks() {
+            let tile = self.tiles.get(&coord).cloned();
+            self.upload_tile(window, coord, tile.as_ref());
+        }
+    }
+
+    // The tiles of the window in one image, laid out like `texture`
+    pub fn window_image(&self, window: &ChunkWindow) -> PathMaskImage {
+        let mut image = PathMaskImage::new(
+            window.texture_dims(PATH_TILE_DIMS),
+            window.min(),
+            window.size(),
+        );
+
+        for (coord, tile) in &self.tiles {
+            let offset = match window.tile_offset(*coord, PATH_TILE_DIMS) {
+                Some(offset) => offset,
+                None => continue,
+            };
+
+            for (row, pixels) in tile.chunks(PATH_TILE_DIMS.0 as usize).enumerate() {
+                let start = ((offset.1 + row as i32) * image.dims.0 + offset.0) as usize;
+                image.pixels[start..start + pixels.len()].copy_from_slice(pixels);
+            }
+        }
+
+        image
+    }
+
+    fn upload_tile(&mut self, window: &ChunkWindow, coord: ChunkCoord, tile: Option<&Vec<f32>>) {
+        let offset = match window.tile_offset(coord, PATH_TILE_DIMS) {
+            Some(offset) => offset,
+            None => return,
+        };
+
+        let raw_pixels: Vec<f32> = match tile {
+            Some(tile) => tile.iter().flat_map(|v| [*v, *v, *v, 1.0]).collect(),
+            None => [0.0, 0.0, 0.0, 1.0].repeat((PATH_TILE_DIMS.0 * PATH_TILE_DIMS.1) as usize),
+        };
+        self.texture
+            .update_region(offset, PATH_TILE_DIMS, &raw_pixels);
+    }
+}
 
-// path mask is a texture/plane centered on 0.0 with bounds from -10 to 10
-pub const PATH_MASK_WS_DIMS: [f32; 2] = [20.0, 20.0];
+// XZ bounding box
+fn bounds(points: &[Vec2]) -> Option<(Vec2, Vec2)> {
+    let mut points = points.iter().copied();
+    let first = points.next()?;
+    Some(points.fold((first, first), |(min, max), p| (min.min(p), max.max(p))))
+}
 
-pub struct ComputePathMask(pub ComputeTexture);
 pub struct ComputePathBlur(pub ComputeTexture);
@@ -11,10 +11,11 @@ pub struct ComputeTexture {
 impl ComputeTexture {
     pub fn init(
         compute_shader: &str,
+        dims: (i32, i32),
         shaderwatch: &mut ShaderWatch,
         assets_library: &mut AssetShaderLibrary,
     ) -> Self {
-        let texture = GlTextureRGBAf32::new((512, 512), None);
+        let texture = GlTextureRGBAf32::new(dims, None);
         let shader_program = ShaderProgram::new_compute(compute_shader).unwrap();
 
         shaderwatch.watch(&shader_program);
@@ -7,6 +7,7 @@ use gl::types::GLsizeiptr;
 
 use crate::{
     asset_libraries::{shader_library::AssetShaderLibrary, Handle},
+    geometry::{arch_style::ArchStyle, curve::Curve},
     render::{
         shader::{GlUniform, ShaderProgram},
         shaderwatch::ShaderWatch,
@@ -17,24 +18,16 @@ use crate::{
 
 use super::CurveDataSSBO;
 
-const COMMAND_BUFFER_SIZE: usize = 1000;
-pub const CURVE_BUFFER_SIZE: usize = 1000;
-
-#[derive(Clone, Copy)]
-#[repr(C)]
-pub struct ArchSegmentDataSSBO {
-    pub start: [f32; 2], // x and z coordinate
-    pub end: [f32; 2],
-}
+// Laid out the same way as in the shaders, see `geometry::arches` for the CPU version of the passes
+pub use crate::geometry::arches::ArchSegmentDataSSBO;
 
-impl Default for ArchSegmentDataSSBO {
-    fn default() -> Self {
-        ArchSegmentDataSSBO {
-            start: [0.0, 0.0],
-            end: [0.0, -1.0],
-        }
-    }
-}
+const COMMAND_BUFFER_SIZE: usize = 1000;
+// Sizes the buffers start with, they grow as walls are drawn
+const CURVE_BUFFER_SIZE: usize = 1000;
+const POINTS_BUFFER_SIZE: usize = 10000;
+const SEGMENTS_BUFFER_SIZE: usize = 1000;
+// Both passes run one invocation per curve or segment, and GL only guarantees this many work groups per dispatch
+pub const MAX_DISPATCH_GROUPS: usize = 65535;
 
 pub struct CurveSegmentsComputePass {
     pub compute_program: Handle<ShaderProgram>,
@@ -42,6 +35,7 @@ pub struct CurveSegmentsComputePass {
     pub cmd_buffer_binding_point: u32,
     //
     pub curves_buffer: GLShaderStorageBuffer<CurveDataSSBO>, // read from
+    pub points_buffer: GLShaderStorageBuffer<[f32; 4]>,      // read from
     pub segments_buffer: GLShaderStorageBuffer<ArchSegmentDataSSBO>, // write to
 }
 
@@ -75,19 +69,87 @@ impl CurveSegmentsComputePass {
                     CURVE_BUFFER_SIZE,
                     3,
                 ),
+                points_buffer: GLShaderStorageBuffer::<[f32; 4]>::new(
+                    &vec![],
+                    POINTS_BUFFER_SIZE,
+                    6,
+                ),
                 segments_buffer: GLShaderStorageBuffer::<ArchSegmentDataSSBO>::new(
                     &vec![],
-                    1000,
+                    SEGMENTS_BUFFER_SIZE,
                     4,
                 ),
             }
         }
     }
 
+    // Packs the points of all curves one after the other into the points buffer, and makes room in the segments
+    // buffer for all of their segments, so that the pass never runs out of it.
+    // Every curve has the arch style of its wall
+    pub fn upload_curves(&mut self, curves: &[(&Curve, &ArchStyle)]) {
+        let mut curves_data = Vec::with_capacity(curves.len());
+        let mut points = Vec::new();
+        for (curve, arch) in curves {
+            curves_data.push(CurveDataSSBO {
+                points_offset: points.len() as u32,
+                points_count: curve.points.len() as u32,
+                arch_shape: arch.shape as u32,
+                arch_voussoirs: arch.voussoirs,
+                arch_span: arch.span,
+                arch_rise: arch.rise,
+            });
+            points.extend(curve.points.iter().map(|p| p.extend(1.0).to_array()));
+        }
+
+        // curves whose points don't fit are left out, instead of reading past the points
+        if !self.points_buffer.reserve(points.len()) {
+            let capacity = self.points_buffer.capacity();
+            points.truncate(capacity);
+            curves_data.retain(|c| (c.points_offset + c.points_count) as usize <= capacity);
+            log::error!(
+                "Only {} of {} curves fit in the points buffer, the others get no arches",
+                curves_data.len(),
+                curves.len()
+            );
+        }
+
+        if curves_data.len() > MAX_DISPATCH_GROUPS {
+            log::error!(
+                "{} curves, only the first {} get arches",
+                curves_data.len(),
+                MAX_DISPATCH_GROUPS
+            );
+            curves_data.truncate(MAX_DISPATCH_GROUPS);
+        }
+
+        let segments: usize = curves_data
+            .iter()
+            .map(|c| (c.points_count as usize).saturating_sub(1))
+            .sum();
+        if segments > MAX_DISPATCH_GROUPS {
+            log::error!(
+                "The curves have {} segments, arches are only laid out over the first {} that cross a path",
+                segments,
+                MAX_DISPATCH_GROUPS
+            );
+        }
+        self.segments_buffer
+            .reserve(segments.min(MAX_DISPATCH_GROUPS));
+
+        self.curves_buffer.update_after(0, &curves_data);
+        self.points_buffer.update_after(0, &points);
+    }
+
+    // How many segments the pass can write, it stops adding segments past that
+    pub fn max_segments(&self) -> usize {
+        self.segments_buffer.capacity().min(MAX_DISPATCH_GROUPS)
+    }
+
     pub fn bind(
         &self,
         assets_shader: &AssetShaderLibrary,
         path_mask: u32,
+        path_mask_ws_min: [f32; 2],
         path_mask_ws_dims: [f32; 2],
         path_mask_img_unit: u32,
     ) {
@@ -117,9 +179,15 @@ impl CurveSegmentsComputePass {
             log_if_error!(
                 shader.set_gl_uniform("path_mask", GlUniform::Int(path_mask_img_unit as i32),)
             );
+            log_if_error!(
+                shader.set_gl_uniform("path_mask_ws_min", GlUniform::Vec2(path_mask_ws_min))
+            );
             log_if_error!(
                 shader.set_gl_uniform("path_mask_ws_dims", GlUniform::Vec2(path_mask_ws_dims))
             );
+            log_if_error!(
+                shader.set_gl_uniform("max_segments", GlUniform::Int(self.max_segments() as i32))
+            );
             // bind texture
             gl::BindImageTexture(
                 path_mask_img_unit,
@@ -131,14 +199,36 @@ impl CurveSegmentsComputePass {
                 gl::RGBA32F,
             );
 
-            // bind curve ssbo
+            // bind curve ssbos
             self.curves_buffer.bind(shader, "curves_buffer");
+            self.points_buffer.bind(shader, "curve_points_buffer");
 
             // bind segments buffer
             self.segments_buffer.bind(&shader, "output_segments_buffer");
         }
     }
 
+    // Lets the wall shader cut the openings under the arches the last dispatch found.
+    // Their count is the first field of the dispatch command
+    pub fn bind_arches(&self, shader: &ShaderProgram) {
+        unsafe {
+            self.segments_buffer.bind(shader, "arches_buffer");
+
+            let c_str = std::ffi::CString::new("arch_count_buffer").unwrap();
+            let block_index = gl::GetProgramResourceIndex(
+                shader.id(),
+                gl::SHADER_STORAGE_BLOCK,
+                c_str.as_ptr() as *const std::os::raw::c_char,
+            );
+            gl::ShaderStorageBlockBinding(shader.id(), block_index, self.cmd_buffer_binding_point);
+            gl::BindBufferBase(
+                gl::SHADER_STORAGE_BUFFER,
+                self.cmd_buffer_binding_point,
+                self.compute_indirect_cmd_buffer,
+            );
+        }
+    }
+
     pub fn reset_cmd_buffer(&self) {
         unsafe {
             gl::BindBuffer(
@@ -159,9 +249,31 @@ impl CurveSegmentsComputePass {
         }
     }
 
+    // Number of segments the last dispatch found
+    pub fn read_segment_count(&self) -> u32 {
+        let mut cmd = DispatchIndirectCommand {
+            _num_groups_x: 0,
+            _num_groups_y: 0,
+            _num_groups_z: 0,
+        };
+        unsafe {
+            gl::BindBuffer(
+                gl::DISPATCH_INDIRECT_BUFFER,
+                self.compute_indirect_cmd_buffer,
+            );
+            gl::GetBufferSubData(
+                gl::DISPATCH_INDIRECT_BUFFER,
+                0,
+                std::mem::size_of::<DispatchIndirectCommand>() as GLsizeiptr,
+                &mut cmd as *mut DispatchIndirectCommand as *mut std::ffi::c_void,
+            );
+        }
+        cmd._num_groups_x
+    }
+
     pub fn reset_segments_buffer(&self) {
         unsafe {
-            let data = &[ArchSegmentDataSSBO::default(); 1000];
+            let data = &vec![ArchSegmentDataSSBO::default(); self.segments_buffer.capacity()];
             gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.segments_buffer.gl_id());
             let ptr = gl::MapBuffer(gl::SHADER_STORAGE_BUFFER, gl::WRITE_ONLY);
 
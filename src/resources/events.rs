@@ -7,6 +7,11 @@ pub struct CurveChangedEvent {
 pub struct CurveDeletedEvent {
     pub curve_index: usize,
 }
+// Any path was added, changed or removed, the path mask has to be rasterized again
+pub struct PathsChangedEvent;
+// Terrain heights were generated again, walls and paths have to be moved onto the new ground
+pub struct TerrainChangedEvent;
+
 pub struct BrushModeJustChanged {
     pub to: BrushMode,
 }
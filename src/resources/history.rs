@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use crate::geometry::{
+    curve::Curve, spline_curve::SplineCurve, wall_opening::WallOpening, wall_style::WallStyle,
+};
+
+use super::{Path, PathManager, Wall, WallManager};
+
+// Oldest strokes are forgotten past this point
+const HISTORY_MAX_LEN: usize = 100;
+
+// Everything needed to restore a wall
+#[derive(Clone)]
+pub struct WallState {
+    pub curve: Curve,
+    pub spline: Option<SplineCurve>,
+    pub style: WallStyle,
+    pub seed: u64,
+    pub start_offset: f32,
+    pub openings: Vec<WallOpening>,
+}
+
+impl WallState {
+    pub fn of(wall: &Wall) -> Self {
+        Self {
+            curve: wall.curve.clone(),
+            spline: wall.spline.clone(),
+            style: wall.style,
+            seed: wall.seed,
+            start_offset: wall.start_offset,
+            openings: wall.openings.clone(),
+        }
+    }
+
+    fn same_as(&self, other: &Self) -> bool {
+        self.curve.points == other.curve.points
+            && self.spline.as_ref().map(|s| &s.control_points)
+                == other.spline.as_ref().map(|s| &s.control_points)
+            && self.style == other.style
+            && self.seed == other.seed
+            && self.start_offset == other.start_offset
+            && self.openings == other.openings
+    }
+}
+
+// `None` means the wall doesn't exist (or has less than 2 points, which doesn't construct a wall)
+pub struct WallChange {
+    pub index: usize,
+    pub before: Option<WallState>,
+    pub after: Option<WallState>,
+}
+
+// Everything needed to restore a path
+#[derive(Clone)]
+pub struct PathState {
+    pub curve: Curve,
+    pub width: f32,
+}
+
+impl PathState {
+    pub fn of(path: &Path) -> Self {
+        Self {
+            curve: path.curve.clone(),
+            width: path.width,
+        }
+    }
+
+    fn same_as(&self, other: &Self) -> bool {
+        self.curve.points == other.curve.points && self.width == other.width
+    }
+}
+
+// `None` means the path doesn't exist (or has less than 2 points, which isn't kept as a path)
+pub struct PathChange {
+    pub index: usize,
+    pub before: Option<PathState>,
+    pub after: Option<PathState>,
+}
+
+// A reversible operation, usually recorded from LMB press to LMB release
+pub struct Stroke {
+    pub walls: Vec<WallChange>,
+    pub paths: Vec<PathChange>,
+}
+
+// State of the canvas captured at the beginning and at the end of a stroke
+pub struct StrokeSnapshot {
+    pub walls: HashMap<usize, WallState>,
+    pub paths: HashMap<usize, PathState>,
+}
+
+impl StrokeSnapshot {
+    pub fn new(wall_manager: &WallManager, path_manager: &PathManager) -> Self {
+        Self {
+            walls: wall_manager
+                .walls
+                .iter()
+                .filter(|(_, w)| w.curve.points.len() >= 2)
+                .map(|(i, w)| (*i, WallState::of(w)))
+                .collect(),
+            paths: path_manager
+                .paths
+                .iter()
+                .filter(|(_, p)| p.curve.points.len() >= 2)
+                .map(|(i, p)| (*i, PathState::of(p)))
+                .collect(),
+        }
+    }
+}
+
+impl Stroke {
+    pub fn from_snapshots(before: StrokeSnapshot, after: StrokeSnapshot) -> Self {
+        Self {
+            walls: changes(&before.walls, &after.walls, WallState::same_as)
+                .into_iter()
+                .map(|(index, before, after)| WallChange {
+                    index,
+                    before,
+                    after,
+                })
+                .collect(),
+            paths: changes(&before.paths, &after.paths, PathState::same_as)
+                .into_iter()
+                .map(|(index, before, after)| PathChange {
+                    index,
+                    before,
+                    after,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.walls.is_empty() && self.paths.is_empty()
+    }
+}
+
+// (index, before, after) of everything that was added, removed or changed between the two snapshots
+fn changes<T: Clone>(
+    before: &HashMap<usize, T>,
+    after: &HashMap<usize, T>,
+    same: fn(&T, &T) -> bool,
+) -> Vec<(usize, Option<T>, Option<T>)> {
+    let mut indices: Vec<usize> = before.keys().chain(after.keys()).copied().collect();
+    indices.sort_unstable();
+    indices.dedup();
+
+    indices
+        .into_iter()
+        .filter_map(|index| {
+            let b = before.get(&index);
+            let a = after.get(&index);
+            let unchanged = match (b, a) {
+                (Some(b), Some(a)) => same(b, a),
+                (None, None) => true,
+                _ => false,
+            };
+
+            if unchanged {
+                None
+            } else {
+                Some((index, b.cloned(), a.cloned()))
+            }
+        })
+        .collect()
+}
+
+pub struct CommandHistory {
+    pub in_progress: Option<StrokeSnapshot>,
+    undo: Vec<Stroke>,
+    redo: Vec<Stroke>,
+}
+
+impl CommandHistory {
+    pub fn new() -> Self {
+        Self {
+            in_progress: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, stroke: Stroke) {
+        if stroke.is_empty() {
+            return;
+        }
+
+        self.undo.push(stroke);
+        if self.undo.len() > HISTORY_MAX_LEN {
+            self.undo.remove(0);
+        }
+
+        // A new stroke invalidates whatever was undone before it
+        self.redo.clear();
+    }
+
+    // Moves the latest stroke to the redo stack, and returns it so it can be reverted
+    pub fn undo(&mut self) -> Option<&Stroke> {
+        let stroke = self.undo.pop()?;
+        self.redo.push(stroke);
+        self.redo.last()
+    }
+
+    // Moves the latest undone stroke back to the undo stack, and returns it so it can be re-applied
+    pub fn redo(&mut self) -> Option<&Stroke> {
+        let stroke = self.redo.pop()?;
+        self.undo.push(stroke);
+        self.undo.last()
+    }
+
+    pub fn clear(&mut self) {
+        self.in_progress = None;
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
@@ -3,8 +3,12 @@ pub mod compute_path_mask;
 pub mod compute_textures;
 pub mod curve_segments_pass;
 pub mod events;
+pub mod history;
+pub mod path_manager;
 pub mod terrain;
+pub mod terrain_presets;
 pub mod wall_manager;
+pub mod wall_styles;
 
 //use bevy_app::AppBuilder;
 pub use compute_arches_indirect::*;
@@ -12,8 +16,12 @@ pub use compute_path_mask::*;
 pub use compute_textures::*;
 pub use curve_segments_pass::*;
 pub use events::*;
+pub use history::*;
+pub use path_manager::*;
 pub use terrain::*;
+pub use terrain_presets::*;
 pub use wall_manager::*;
+pub use wall_styles::*;
 
 //pub fn add_events(app: &mut AppBuilder) -> &mut AppBuilder {
 //    app.add_event::<CurveChangedEvent>()
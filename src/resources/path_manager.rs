@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use glam::Vec3;
+
+use crate::geometry::curve::Curve;
+
+// Width of new paths
+pub const PATH_WIDTH_DEFAULT: f32 = 0.9;
+
+pub struct Path {
+    // centerline, on the terrain
+    pub curve: Curve,
+    pub width: f32,
+}
+
+impl Path {
+    pub fn from(curve: Curve, width: f32) -> Self {
+        Self { curve, width }
+    }
+}
+
+// Paths are kept as curves, the path mask is rasterized from them whenever they change (see `update_path_mask`)
+pub struct PathManager {
+    pub paths: HashMap<usize, Path>,
+    // path that's being drawn
+    pub active: Option<usize>,
+
+    pub max_index: usize,
+}
+
+impl PathManager {
+    pub fn new() -> Self {
+        Self {
+            paths: HashMap::new(),
+            active: None,
+            max_index: 0,
+        }
+    }
+
+    pub fn new_path(&mut self, curve: Curve, width: f32) -> usize {
+        self.max_index += 1;
+        self.paths.insert(self.max_index, Path::from(curve, width));
+        self.max_index
+    }
+
+    // Restores a path under a specific index, e.g. when undoing its deletion
+    pub fn insert_path(&mut self, index: usize, curve: Curve, width: f32) {
+        self.max_index = self.max_index.max(index);
+        self.paths.insert(index, Path::from(curve, width));
+    }
+
+    // Path covering `pos` in XZ, the one with the closest centerline if they overlap
+    pub fn path_at(&self, pos: Vec3) -> Option<usize> {
+        self.paths
+            .iter()
+            .filter_map(|(i, p)| {
+                p.curve
+                    .closest_point(pos)
+                    .filter(|c| c.separation <= p.width / 2.0)
+                    .map(|c| (*i, c.separation))
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(i, _)| i)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Path> {
+        self.paths.get(&index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Path> {
+        self.paths.get_mut(&index)
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Path> {
+        if self.active == Some(index) {
+            self.active = None;
+        }
+        self.paths.remove(&index)
+    }
+
+    pub fn clear(&mut self) {
+        self.paths.clear();
+        self.active = None;
+    }
+}
@@ -1,107 +1,424 @@
-use bracket_noise::prelude::FastNoise;
+use std::collections::{HashMap, HashSet};
 
+use bracket_noise::prelude::{FastNoise, FractalType, NoiseType};
+use glam::{Vec2, Vec3};
+
+use crate::geometry::{
+    chunks::{ChunkCoord, ChunkWindow, CHUNK_SIZE},
+    curve::Curve,
+    heightfield_raycast::{raycast_heightfield, RayHit, RaycastSettings},
+    terrain_settings::{NoiseKind, TerrainSettings},
+};
 use crate::render::texture::GlTextureRGBAf32;
+use crate::utils::heightmap::Heightmap;
+
+// Resolution of the heights of a single chunk, the terrain is smooth so it needs less than the path mask
+pub const TERRAIN_TILE_DIMS: (i32, i32) = (256, 256);
+
+// Pixels around a pixel that smoothing averages, in every direction
+const SMOOTH_KERNEL_RADIUS: i32 = 2;
+
+// The warp noise is sampled this far away for the second axis, so the axes are pushed independently
+const WARP_SECOND_AXIS_OFFSET: f32 = 1000.0;
+
+// Rays give up after this distance, in meters
+const RAYCAST_MAX_DISTANCE: f32 = 250.0;
+// Terrain steeper than this, e.g. sculpted cliffs, can be hit a bit late by rays at grazing angles
+const RAYCAST_MAX_SLOPE: f32 = 3.0;
 
+// What the terrain brush does to the heights under it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TerrainOp {
+    Raise,
+    Lower,
+    Smooth,
+    Flatten,
+}
+
+// Heights are stored in a grid that's split into one tile per chunk. Tiles are generated from the noise
+// when they are first needed, and then sculpted or imported, see `height_at` for how the grid is sampled
 pub struct TerrainData {
-    perlin: bracket_noise::prelude::FastNoise,
-    pub amp: f32,
+    // change through `set_settings`, except for the amplitude and terracing which don't affect the noise
+    pub settings: TerrainSettings,
+    noise: FastNoise,
+    warp: FastNoise,
     pub offset: glam::Vec2,
     pub min_y: f32,
     pub max_y: f32,
+    // one height per pixel, for chunks that were in the window or were edited
+    tiles: HashMap<ChunkCoord, Vec<f32>>,
+    // tiles that were sculpted or imported, generating the terrain again would lose them
+    edited: HashSet<ChunkCoord>,
+    // terrain stays flat until it's generated for the first time
+    flat: bool,
+    // tiles of the chunks in the window
     pub texture: GlTextureRGBAf32,
 }
 
 impl TerrainData {
-    pub fn recalculate_texture(&mut self) {
-        let (raw_pixels, min, max) =
-            Self::raw_pixels_f32(&self.perlin, self.texture.dims, self.offset, self.amp);
+    // Generates the heights again, e.g. after the amplitude or the offset changed. Sculpted and imported heights are lost
+    pub fn recalculate_texture(&mut self, window: &ChunkWindow) {
+        self.flat = false;
+        self.tiles.clear();
+        self.edited.clear();
+        self.min_y = 0.0;
+        self.max_y = 0.0;
 
-        self.min_y = min;
-        self.max_y = max;
+        self.upload_window(window);
+    }
 
-        self.texture.update(&raw_pixels);
+    // Generates the tiles the window is missing, and uploads all of them
+    pub fn upload_window(&mut self, window: &ChunkWindow) {
+        for coord in window.chunks() {
+            self.tile_mut(coord);
+            self.upload_tile(window, coord);
+        }
     }
 
+    fn upload_tile(&mut self, window: &ChunkWindow, coord: ChunkCoord) {
+        let offset = match window.tile_offset(coord, TERRAIN_TILE_DIMS) {
+            Some(offset) => offset,
+            None => return,
+        };
+
+        let raw_pixels: Vec<f32> = self.tiles[&coord]
+            .iter()
+            .flat_map(|h| [*h, *h, *h, 1.0])
+            .collect();
+        self.texture
+            .update_region(offset, TERRAIN_TILE_DIMS, &raw_pixels);
+    }
+
+    // Takes effect on the next `recalculate_texture`
+    pub fn set_settings(&mut self, settings: TerrainSettings) {
+        self.settings = settings;
+        self.noise = noise_from(&settings);
+        self.warp = warp_noise_from(&settings);
+    }
+
+    // Bilinear between the pixels of the grid, the same way the GPU samples the terrain texture
     pub fn height_at(&self, x: f32, y: f32) -> f32 {
-        self.perlin.get_noise(self.offset.x + x, self.offset.y + y) * self.amp
+        let p = (Vec2::new(x, y) - grid_origin()) / pixel_size() - Vec2::splat(0.5);
+        let (gx, gz) = (p.x.floor() as i32, p.y.floor() as i32);
+        let (tx, tz) = (p.x - gx as f32, p.y - gz as f32);
+
+        let top = lerp(self.pixel(gx, gz), self.pixel(gx + 1, gz), tx);
+        let bottom = lerp(self.pixel(gx, gz + 1), self.pixel(gx + 1, gz + 1), tx);
+        lerp(top, bottom, tz)
+    }
+
+    // Where the ray first hits the terrain, `None` when it misses, e.g. points at the sky
+    pub fn raycast(&self, origin: Vec3, direction: Vec3) -> Option<RayHit> {
+        let pixel = pixel_size().min_element();
+        let settings = RaycastSettings {
+            min_step: pixel,
+            max_step: CHUNK_SIZE / 4.0,
+            max_slope: RAYCAST_MAX_SLOPE,
+            max_distance: RAYCAST_MAX_DISTANCE,
+            tolerance: pixel * 0.01,
+        };
+
+        raycast_heightfield(
+            origin,
+            direction,
+            (self.min_y, self.max_y),
+            &settings,
+            |x, z| self.height_at(x, z),
+        )
     }
 
-    #[allow(dead_code)]
-    pub fn raw_empty_f32(texture_dims: (i32, i32)) -> (Vec<f32>, f32, f32) {
-        (
-            vec![0.0; (texture_dims.1 * texture_dims.0 * 4) as usize],
-            0.0,
-            0.0,
+    // Same curve in XZ, with every point moved onto the terrain
+    pub fn project(&self, curve: &Curve) -> Curve {
+        Curve::from(
+            curve
+                .points
+                .iter()
+                .map(|p| Vec3::new(p.x, self.height_at(p.x, p.z), p.z))
+                .collect(),
         )
     }
 
-    #[allow(dead_code)]
-    pub fn raw_pixels_f32(
-        noise: &FastNoise,
-        texture_dims: (i32, i32),
-        offset: glam::Vec2,
-        amp: f32,
-    ) -> (Vec<f32>, f32, f32) {
-        let size = (20.0, 20.0);
-        let mut raw_pixels = Vec::new();
+    // Applies `op` to the pixels within `radius` from `center`, with a smooth falloff towards the edge.
+    // `amount` is in meters for raise and lower, and is how far heights are pulled (0..1) for smooth and flatten.
+    // Returns the chunks whose tiles changed
+    pub fn sculpt(
+        &mut self,
+        op: TerrainOp,
+        center: Vec2,
+        radius: f32,
+        amount: f32,
+        flatten_to: f32,
+    ) -> Vec<ChunkCoord> {
+        let (from, to) = (
+            pixel_of(center - Vec2::splat(radius)),
+            pixel_of(center + Vec2::splat(radius)),
+        );
 
-        let mut min_value = 0.0;
-        let mut max_value = 0.0;
+        // all heights are read before any of them changes, smoothing needs the neighbours as they were
+        let mut changes = Vec::new();
+        for gz in from.1..=to.1 {
+            for gx in from.0..=to.0 {
+                let distance = pixel_center(gx, gz).distance(center);
+                if distance > radius {
+                    continue;
+                }
 
-        for y in 0..texture_dims.1 {
-            let p_y = (y as f32 / texture_dims.1 as f32) * size.1 - size.1 / 2.0;
+                let falloff = 1.0 - distance / radius;
+                let weight = falloff * falloff * (3.0 - 2.0 * falloff);
+                let height = self.pixel(gx, gz);
 
-            for x in 0..texture_dims.0 {
-                let p_x = (x as f32 / texture_dims.0 as f32) * size.0 - size.0 / 2.0;
+                let new_height = match op {
+                    TerrainOp::Raise => height + amount * weight,
+                    TerrainOp::Lower => height - amount * weight,
+                    TerrainOp::Smooth => {
+                        let r = SMOOTH_KERNEL_RADIUS;
+                        let sum: f32 = (-r..=r)
+                            .flat_map(|z| (-r..=r).map(move |x| (x, z)))
+                            .map(|(x, z)| self.pixel(gx + x, gz + z))
+                            .sum();
+                        let average = sum / ((r * 2 + 1) * (r * 2 + 1)) as f32;
+                        lerp(height, average, (amount * weight).min(1.0))
+                    }
+                    TerrainOp::Flatten => lerp(height, flatten_to, (amount * weight).min(1.0)),
+                };
+                changes.push((gx, gz, new_height));
+            }
+        }
 
-                let n = noise.get_noise(p_x + offset.x, p_y + offset.y) * amp;
-                raw_pixels.extend([n, n, n, 1.0]);
+        let mut changed = Vec::new();
+        for (gx, gz, height) in changes {
+            let (coord, index) = tile_pixel(gx, gz);
+            self.tile_mut(coord)[index] = height;
+            self.min_y = self.min_y.min(height);
+            self.max_y = self.max_y.max(height);
+            if !changed.contains(&coord) {
+                changed.push(coord);
+            }
+        }
+        self.edited.extend(changed.iter().copied());
 
-                if n < min_value {
-                    min_value = n;
-                }
+        changed
+    }
 
-                if n > max_value {
-                    max_value = n;
-                }
+    // Uploads tiles that were sculpted, the ones outside of the window are uploaded when they get in it
+    pub fn upload_tiles(&mut self, window: &ChunkWindow, coords: &[ChunkCoord]) {
+        for coord in coords {
+            self.upload_tile(window, *coord);
+        }
+    }
+
+    // Heights of the chunks in the window, one per pixel
+    pub fn heightmap(&self, window: &ChunkWindow) -> Heightmap {
+        let dims = window.texture_dims(TERRAIN_TILE_DIMS);
+        let first = window_first_pixel(window);
+
+        let heights = (0..dims.1)
+            .flat_map(|z| (0..dims.0).map(move |x| (x, z)))
+            .map(|(x, z)| self.pixel(first.0 + x, first.1 + z))
+            .collect();
+
+        Heightmap::new((dims.0 as usize, dims.1 as usize), heights)
+    }
+
+    // Stretches `heightmap` over the chunks in the window, the rest of the terrain stays as it is
+    pub fn set_heightmap(&mut self, window: &ChunkWindow, heightmap: &Heightmap) {
+        let dims = window.texture_dims(TERRAIN_TILE_DIMS);
+        let first = window_first_pixel(window);
+
+        for z in 0..dims.1 {
+            for x in 0..dims.0 {
+                let height = heightmap.sample(
+                    x as f32 / (dims.0 - 1) as f32,
+                    z as f32 / (dims.1 - 1) as f32,
+                );
+
+                let (coord, index) = tile_pixel(first.0 + x, first.1 + z);
+                self.tile_mut(coord)[index] = height;
+                self.min_y = self.min_y.min(height);
+                self.max_y = self.max_y.max(height);
+            }
+        }
+        self.edited.extend(window.chunks());
+
+        self.upload_window(window);
+    }
+
+    pub fn has_edits(&self) -> bool {
+        !self.edited.is_empty()
+    }
+
+    // Heights of the tiles that were sculpted or imported, to save them with the scene
+    pub fn edited_tiles(&self) -> Vec<(ChunkCoord, Vec<f32>)> {
+        let mut coords: Vec<_> = self.edited.iter().copied().collect();
+        coords.sort_unstable_by_key(|c| (c.x, c.z));
+        coords
+            .into_iter()
+            .map(|coord| (coord, self.tiles[&coord].clone()))
+            .collect()
+    }
+
+    // Puts back tiles from `edited_tiles`, over the generated ones
+    pub fn set_edited_tiles(&mut self, window: &ChunkWindow, tiles: Vec<(ChunkCoord, Vec<f32>)>) {
+        for (coord, tile) in tiles {
+            if tile.len() != (TERRAIN_TILE_DIMS.0 * TERRAIN_TILE_DIMS.1) as usize {
+                log::warn!(
+                    "Scene file: skipping the terrain of chunk ({}, {}), it has {} heights instead of {}x{}",
+                    coord.x,
+                    coord.z,
+                    tile.len(),
+                    TERRAIN_TILE_DIMS.0,
+                    TERRAIN_TILE_DIMS.1
+                );
+                continue;
             }
+
+            for h in &tile {
+                self.min_y = self.min_y.min(*h);
+                self.max_y = self.max_y.max(*h);
+            }
+            self.tiles.insert(coord, tile);
+            self.edited.insert(coord);
+            self.upload_tile(window, coord);
         }
+    }
 
-        (raw_pixels, min_value, max_value)
+    // Height of a pixel of the grid, chunks without a tile have the heights their tile would be generated with
+    fn pixel(&self, gx: i32, gz: i32) -> f32 {
+        let (coord, index) = tile_pixel(gx, gz);
+        match self.tiles.get(&coord) {
+            Some(tile) => tile[index],
+            None => self.generated_height(pixel_center(gx, gz)),
+        }
     }
 
-    pub fn new() -> Self {
-        let mut noise = bracket_noise::prelude::FastNoise::seeded(45);
-        noise.set_noise_type(bracket_noise::prelude::NoiseType::PerlinFractal);
-        noise.set_fractal_type(bracket_noise::prelude::FractalType::FBM);
-        noise.set_fractal_octaves(3);
-        noise.set_fractal_gain(1.0);
-        noise.set_fractal_lacunarity(3.0);
-        noise.set_frequency(0.05);
-        noise.set_seed(0);
+    fn generated_height(&self, p: Vec2) -> f32 {
+        if self.flat {
+            return 0.0;
+        }
 
-        let amp = 1.3;
-        let offset = glam::Vec2::ZERO;
-        let texture_dims = (512, 512);
+        let mut p = p + self.offset;
+        if self.settings.warp_amplitude > 0.0 {
+            let warp = Vec2::new(
+                self.warp.get_noise(p.x, p.y),
+                self.warp
+                    .get_noise(p.x + WARP_SECOND_AXIS_OFFSET, p.y + WARP_SECOND_AXIS_OFFSET),
+            );
+            p += warp * self.settings.warp_amplitude;
+        }
+
+        let height = self.noise.get_noise(p.x, p.y) * self.settings.amplitude;
+        self.settings.terrace(height)
+    }
 
-        // generate texture
-        let min_y;
-        let max_y;
+    fn tile_mut(&mut self, coord: ChunkCoord) -> &mut Vec<f32> {
+        if !self.tiles.contains_key(&coord) {
+            let first = (coord.x * TERRAIN_TILE_DIMS.0, coord.z * TERRAIN_TILE_DIMS.1);
+            let tile: Vec<f32> = (0..TERRAIN_TILE_DIMS.1)
+                .flat_map(|z| (0..TERRAIN_TILE_DIMS.0).map(move |x| (x, z)))
+                .map(|(x, z)| self.generated_height(pixel_center(first.0 + x, first.1 + z)))
+                .collect();
 
-        let (raw_pixels, min, max) = Self::raw_empty_f32(texture_dims); //Self::raw_pixels_f32(&noise, texture_dims, offset, amp);
+            for h in &tile {
+                self.min_y = self.min_y.min(*h);
+                self.max_y = self.max_y.max(*h);
+            }
+            self.tiles.insert(coord, tile);
+        }
+
+        self.tiles.get_mut(&coord).unwrap()
+    }
 
-        min_y = min;
-        max_y = max;
+    pub fn new(window: &ChunkWindow) -> Self {
+        let settings = TerrainSettings::default();
+        let texture_dims = window.texture_dims(TERRAIN_TILE_DIMS);
 
+        // flat until it's generated
+        let raw_pixels = [0.0, 0.0, 0.0, 1.0].repeat((texture_dims.0 * texture_dims.1) as usize);
         let texture = GlTextureRGBAf32::new(texture_dims, Some(&raw_pixels));
 
         Self {
-            perlin: noise,
-            min_y,
-            max_y,
+            settings,
+            noise: noise_from(&settings),
+            warp: warp_noise_from(&settings),
+            offset: Vec2::ZERO,
+            min_y: 0.0,
+            max_y: 0.0,
+            tiles: HashMap::new(),
+            edited: HashSet::new(),
+            flat: true,
             texture,
-            amp,
-            offset,
         }
     }
 }
+
+// Fractal noise for more than one octave, cellular noise is never fractal
+fn noise_from(settings: &TerrainSettings) -> FastNoise {
+    let fractal = settings.octaves > 1;
+    let noise_type = match settings.noise {
+        NoiseKind::Perlin if fractal => NoiseType::PerlinFractal,
+        NoiseKind::Perlin => NoiseType::Perlin,
+        NoiseKind::Simplex if fractal => NoiseType::SimplexFractal,
+        NoiseKind::Simplex => NoiseType::Simplex,
+        NoiseKind::Value if fractal => NoiseType::ValueFractal,
+        NoiseKind::Value => NoiseType::Value,
+        NoiseKind::Cellular => NoiseType::Cellular,
+    };
+
+    let mut noise = FastNoise::seeded(settings.seed);
+    noise.set_noise_type(noise_type);
+    noise.set_fractal_type(FractalType::FBM);
+    noise.set_fractal_octaves(settings.octaves as i32);
+    noise.set_fractal_gain(settings.gain);
+    noise.set_fractal_lacunarity(settings.lacunarity);
+    noise.set_frequency(settings.frequency);
+    noise
+}
+
+fn warp_noise_from(settings: &TerrainSettings) -> FastNoise {
+    let mut noise = FastNoise::seeded(settings.seed.wrapping_add(1));
+    noise.set_noise_type(NoiseType::Simplex);
+    noise.set_frequency(settings.warp_frequency);
+    noise
+}
+
+// The grid starts at the corner of chunk (0, 0), pixels of a chunk's tile are laid out like the ones in the texture
+fn grid_origin() -> Vec2 {
+    ChunkCoord::new(0, 0).min()
+}
+
+fn pixel_size() -> Vec2 {
+    Vec2::new(
+        CHUNK_SIZE / TERRAIN_TILE_DIMS.0 as f32,
+        CHUNK_SIZE / TERRAIN_TILE_DIMS.1 as f32,
+    )
+}
+
+// Texels are sampled at their centers
+fn pixel_center(gx: i32, gz: i32) -> Vec2 {
+    grid_origin() + (Vec2::new(gx as f32, gz as f32) + Vec2::splat(0.5)) * pixel_size()
+}
+
+fn pixel_of(p: Vec2) -> (i32, i32) {
+    let p = ((p - grid_origin()) / pixel_size()).floor();
+    (p.x as i32, p.y as i32)
+}
+
+// Chunk a pixel is in, and its index in the chunk's tile
+fn tile_pixel(gx: i32, gz: i32) -> (ChunkCoord, usize) {
+    let (w, h) = TERRAIN_TILE_DIMS;
+    (
+        ChunkCoord::new(gx.div_euclid(w), gz.div_euclid(h)),
+        (gz.rem_euclid(h) * w + gx.rem_euclid(w)) as usize,
+    )
+}
+
+fn window_first_pixel(window: &ChunkWindow) -> (i32, i32) {
+    (
+        (window.center.x - window.radius) * TERRAIN_TILE_DIMS.0,
+        (window.center.z - window.radius) * TERRAIN_TILE_DIMS.1,
+    )
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
@@ -0,0 +1,57 @@
+use crate::geometry::terrain_settings::{TerrainSettings, TerrainSettingsPresets};
+
+pub const TERRAIN_PRESETS_PATH: &str = "presets/terrain.json";
+
+// Settings presets for the procedural terrain, see `terrain_preset_manager`
+pub struct TerrainPresets {
+    pub presets: Vec<(String, TerrainSettings)>,
+    pub active: usize,
+}
+
+impl TerrainPresets {
+    pub fn new() -> Self {
+        let mut presets = Self {
+            presets: vec![(String::from("default"), TerrainSettings::default())],
+            active: 0,
+        };
+        presets.reload();
+        presets
+    }
+
+    // Keeps the previous presets if the file can't be loaded
+    pub fn reload(&mut self) {
+        match TerrainSettingsPresets::load(TERRAIN_PRESETS_PATH) {
+            Ok(loaded) => {
+                log::info!(
+                    "Loaded {} terrain presets from {}",
+                    loaded.presets.len(),
+                    TERRAIN_PRESETS_PATH
+                );
+                self.presets = loaded.presets;
+                self.active = self.active.min(self.presets.len() - 1);
+            }
+            Err(err) => log::error!("{}", err),
+        }
+    }
+
+    // Replaces the active preset, and writes all of them back to the file
+    pub fn save_active(&mut self, settings: TerrainSettings) -> Result<(), String> {
+        self.presets[self.active].1 = settings;
+        TerrainSettingsPresets {
+            presets: self.presets.clone(),
+        }
+        .save(TERRAIN_PRESETS_PATH)
+    }
+
+    pub fn active_name(&self) -> &str {
+        &self.presets[self.active].0
+    }
+
+    pub fn active_settings(&self) -> TerrainSettings {
+        self.presets[self.active].1
+    }
+
+    pub fn cycle(&mut self) {
+        self.active = (self.active + 1) % self.presets.len();
+    }
+}
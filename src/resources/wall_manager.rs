@@ -1,33 +1,96 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use bevy_ecs::prelude::{Commands, Entity};
-
-use crate::geometry::curve::Curve;
+use glam::Vec3;
+
+use super::TerrainData;
+use crate::geometry::{
+    curve::{Curve, WallCurve},
+    spline_curve::SplineCurve,
+    wall_constructor::BrickLayout,
+    wall_junction::JunctionGraph,
+    wall_opening::WallOpening,
+    wall_style::WallStyle,
+};
 
 pub const RESAMPLING: f32 = 0.2;
 pub const SMOOTHING_STEPS: usize = 50;
 
 pub struct Wall {
+    // for everything that works on points, e.g. junctions, picking and arches. It's the polyline of `spline` if there's one
     pub curve: Curve,
+    // walls drawn with `StrokeFitting::Spline` are built along it, see `shape`
+    pub spline: Option<SplineCurve>,
+    pub style: WallStyle,
+    // brick layout seed, stays the same when the curve changes so existing bricks don't reshuffle
+    pub seed: u64,
+    // grows when the wall is drawn further from its first point, so the bricks it had stay where they were
+    pub start_offset: f32,
+    // gates, doors and windows, placed with the opening tool
+    pub openings: Vec<WallOpening>,
     pub curve_preview_entity: Option<Entity>,
     pub wall_entity: Option<Entity>,
     pub shadow_entity: Option<Entity>,
 }
 
 impl Wall {
-    pub fn from(v: Curve) -> Self {
+    pub fn from(v: Curve, style: WallStyle, seed: u64) -> Self {
         Self {
             curve: v,
+            spline: None,
+            style,
+            seed,
+            start_offset: 0.0,
+            openings: Vec::new(),
             curve_preview_entity: None,
             wall_entity: None,
             shadow_entity: None,
         }
     }
+
+    // What the bricks and the shadow are laid along
+    pub fn shape(&self) -> &dyn WallCurve {
+        match &self.spline {
+            Some(spline) => spline,
+            None => &self.curve,
+        }
+    }
+
+    // Builds the wall along `spline` from now on, with its control points moved onto the terrain
+    pub fn set_spline(&mut self, spline: &SplineCurve, terrain: &TerrainData) {
+        let control_points = Curve::from(spline.control_points.clone());
+        let spline = SplineCurve::from(terrain.project(&control_points).points);
+        self.curve = terrain.project(&spline.polyline());
+        self.spline = Some(spline);
+    }
+
+    // What the randomness of the bricks comes from when the wall isn't joined, see `WallManager::layout`
+    pub fn layout(&self) -> BrickLayout {
+        BrickLayout {
+            seed: self.seed,
+            rows_seed: self.seed,
+            start_offset: self.start_offset,
+        }
+    }
+
+    // Moves the wall onto the terrain, e.g. after it changed
+    pub fn project(&mut self, terrain: &TerrainData) {
+        match self.spline.take() {
+            Some(spline) => self.set_spline(&spline, terrain),
+            None => self.curve = terrain.project(&self.curve),
+        }
+    }
 }
 
 pub struct WallManager {
     pub temp_curve: Option<InProgressCurve>,
     pub walls: HashMap<usize, Wall>,
+    // kept up to date in `walls_update`
+    pub junctions: JunctionGraph,
+    // picked in select mode, see `select_wall`
+    pub selected: Option<usize>,
+    // rows seed of the walls as of the last `update_junctions`, see `BrickLayout::rows_seed`
+    rows_seeds: HashMap<usize, u64>,
 
     pub max_index: usize,
 }
@@ -37,11 +100,14 @@ impl WallManager {
         Self {
             temp_curve: None,
             walls: HashMap::new(),
+            junctions: JunctionGraph::new(),
+            selected: None,
+            rows_seeds: HashMap::new(),
             max_index: 0,
         }
     }
 
-    pub fn new_wall(&mut self, curve: Curve) -> usize {
+    pub fn new_wall(&mut self, curve: Curve, style: WallStyle, seed: u64) -> usize {
         self.max_index += 1;
 
         self.temp_curve = Some(InProgressCurve::new(
@@ -49,11 +115,72 @@ impl WallManager {
             self.max_index,
             AddPointsTo::End,
         ));
-        self.walls.insert(self.max_index, Wall::from(curve));
+        self.walls
+            .insert(self.max_index, Wall::from(curve, style, seed));
 
         self.max_index
     }
 
+    // Restores a wall under a specific index, e.g. when undoing its deletion
+    pub fn insert_wall(&mut self, index: usize, curve: Curve, style: WallStyle, seed: u64) {
+        self.max_index = self.max_index.max(index);
+        self.walls.insert(index, Wall::from(curve, style, seed));
+    }
+
+    // Detects the junctions of the walls that changed again, see `JunctionGraph::update`.
+    // Returns the walls that have to be constructed again, also the ones whose rows of bricks changed with the junctions
+    pub fn update_junctions(&mut self, changed: &HashSet<usize>) -> HashSet<usize> {
+        let curves: Vec<_> = self
+            .walls
+            .iter()
+            .map(|(i, w)| (*i, &w.curve, &w.style))
+            .collect();
+        let mut affected = self.junctions.update(&curves, changed);
+
+        let walls = &self.walls;
+        self.rows_seeds.retain(|i, _| walls.contains_key(i));
+        let groups: HashSet<usize> = changed
+            .union(&affected)
+            .filter(|i| self.walls.contains_key(i))
+            .flat_map(|i| self.junctions.interlocked_with(*i))
+            .collect();
+        for i in groups {
+            let rows_seed = self.rows_seed(i);
+            if self.rows_seeds.insert(i, rows_seed) != Some(rows_seed) {
+                affected.insert(i);
+            }
+        }
+        affected
+    }
+
+    // Walls that interlock take their rows from the one with the lowest index, so rerolling it changes all of them
+    fn rows_seed(&self, index: usize) -> u64 {
+        let first = self.junctions.interlocked_with(index)[0];
+        self.walls
+            .get(&first)
+            .or_else(|| self.walls.get(&index))
+            .map_or(0, |w| w.seed)
+    }
+
+    // What the randomness of a wall's bricks comes from, joined the way `update_junctions` last found it
+    pub fn layout(&self, index: usize) -> Option<BrickLayout> {
+        let wall = self.walls.get(&index)?;
+        Some(BrickLayout {
+            rows_seed: self.rows_seeds.get(&index).copied().unwrap_or(wall.seed),
+            ..wall.layout()
+        })
+    }
+
+    // Closest wall to `pos` in XZ, if it's within `max_dist`
+    pub fn closest_wall(&self, pos: Vec3, max_dist: f32) -> Option<usize> {
+        self.walls
+            .iter()
+            .filter_map(|(i, w)| w.curve.closest_point(pos).map(|c| (*i, c.separation)))
+            .filter(|(_, separation)| *separation <= max_dist)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(i, _)| i)
+    }
+
     //pub fn last(&self) -> Option<&Wall> {
     //    self.walls.get(&self.max_index)
     //}
@@ -67,16 +194,22 @@ impl WallManager {
     }
 
     pub fn remove_entry(&mut self, index: usize, commands: &mut Commands) {
-        let wall_to_remove = self.get(index).expect(&format!(
-            "Remove entry failed: index {} doesnt exist",
-            index
-        ));
+        let wall_to_remove = match self.get(index) {
+            Some(wall) => wall,
+            None => {
+                log::warn!("Remove entry: wall {} is already gone", index);
+                return;
+            }
+        };
 
         despawn_if_exists(wall_to_remove.curve_preview_entity, commands);
         despawn_if_exists(wall_to_remove.wall_entity, commands);
         despawn_if_exists(wall_to_remove.shadow_entity, commands);
 
         self.walls.remove_entry(&index);
+        if self.selected == Some(index) {
+            self.selected = None;
+        }
     }
 }
 
@@ -95,6 +228,8 @@ pub struct InProgressCurve {
     pub curve: Curve,
     pub index: usize,
     pub mode: AddPointsTo,
+    // first point and `Wall::start_offset` of the wall before drawing in front of it
+    pub start: Option<(Vec3, f32)>,
 }
 
 impl InProgressCurve {
@@ -103,6 +238,7 @@ impl InProgressCurve {
             curve: from,
             index,
             mode,
+            start: None,
         }
     }
 }
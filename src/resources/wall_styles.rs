@@ -0,0 +1,48 @@
+use crate::geometry::wall_style::{WallStyle, WallStylePresets};
+
+pub const WALL_STYLES_PATH: &str = "presets/wall_styles.json";
+
+// Style presets available to the wall brush, new walls are created with the active one
+pub struct WallStyles {
+    pub presets: Vec<(String, WallStyle)>,
+    pub active: usize,
+}
+
+impl WallStyles {
+    pub fn new() -> Self {
+        let mut styles = Self {
+            presets: vec![(String::from("default"), WallStyle::default())],
+            active: 0,
+        };
+        styles.reload();
+        styles
+    }
+
+    // Keeps the previous presets if the file can't be loaded
+    pub fn reload(&mut self) {
+        match WallStylePresets::load(WALL_STYLES_PATH) {
+            Ok(loaded) => {
+                log::info!(
+                    "Loaded {} wall styles from {}",
+                    loaded.presets.len(),
+                    WALL_STYLES_PATH
+                );
+                self.presets = loaded.presets;
+                self.active = self.active.min(self.presets.len() - 1);
+            }
+            Err(err) => log::error!("{}", err),
+        }
+    }
+
+    pub fn active_name(&self) -> &str {
+        &self.presets[self.active].0
+    }
+
+    pub fn active_style(&self) -> WallStyle {
+        self.presets[self.active].1
+    }
+
+    pub fn cycle(&mut self) {
+        self.active = (self.active + 1) % self.presets.len();
+    }
+}
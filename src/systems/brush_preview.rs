@@ -1,10 +1,16 @@
 use bevy_app::EventReader;
 use bevy_ecs::prelude::*;
+use glam::Vec3;
 
 use crate::{
     components::{CursorRaycast, Transform},
-    resources::events::BrushModeJustChanged,
-    systems::mode_manager::BrushMode,
+    resources::{events::BrushModeJustChanged, PATH_WIDTH_DEFAULT},
+    systems::{
+        eraser::{EraserBrush, ERASER_RADIUS_DEFAULT},
+        mode_manager::{BrushMode, EraseLayer},
+        path::PathBrush,
+        sculpt_terrain::{TerrainBrush, BRUSH_CIRCLE_RADIUS},
+    },
 };
 
 // Component
@@ -12,31 +18,61 @@ use crate::{
 pub enum BrushPreview {
     Wall,
     Path,
-    Eraser,
+    Eraser(EraseLayer),
+    // same for every terrain operation
+    Terrain,
 }
 
 pub fn brush_preview(
-    query: Query<(Entity, &BrushPreview)>,
+    mut query: Query<(Entity, &BrushPreview, Option<&mut Transform>)>,
     mut ev_mode_changed: EventReader<BrushModeJustChanged>,
     cursor_ws_cache: Res<CursorRaycast>,
+    eraser_brush: Res<EraserBrush>,
+    path_brush: Res<PathBrush>,
+    terrain_brush: Res<TerrainBrush>,
     mut commands: Commands,
 ) {
+    let eraser_scale = Vec3::splat(eraser_brush.radius / ERASER_RADIUS_DEFAULT);
+    let path_scale = Vec3::splat(path_brush.width / PATH_WIDTH_DEFAULT);
+    let terrain_scale = Vec3::splat(terrain_brush.radius / BRUSH_CIRCLE_RADIUS);
+
     if let Some(BrushModeJustChanged { to }) = ev_mode_changed.iter().last() {
+        // selecting and placing openings have no brush
         let keep = match to {
-            BrushMode::Wall => BrushPreview::Wall,
-            BrushMode::Path => BrushPreview::Path,
-            BrushMode::Eraser(_) => BrushPreview::Eraser,
+            BrushMode::Wall => Some(BrushPreview::Wall),
+            BrushMode::Path => Some(BrushPreview::Path),
+            BrushMode::Eraser(layer) => Some(BrushPreview::Eraser(*layer)),
+            BrushMode::Terrain(_) => Some(BrushPreview::Terrain),
+            BrushMode::Select | BrushMode::Opening(_) => None,
         };
 
-        for (ent, brush) in query.iter() {
-            if *brush == keep {
+        for (ent, brush, _) in query.iter_mut() {
+            if keep.as_ref() == Some(brush) {
+                let scale = match brush {
+                    BrushPreview::Eraser(_) => eraser_scale,
+                    BrushPreview::Path => path_scale,
+                    BrushPreview::Terrain => terrain_scale,
+                    BrushPreview::Wall => Vec3::ONE,
+                };
                 commands
                     .entity(ent)
-                    .insert(Transform::from_translation(cursor_ws_cache.0));
+                    .insert(Transform::from_translation_scale(cursor_ws_cache.0, scale));
             } else {
                 // without Transform, the mesh will not render and its FollowMouse component will not be updated either
                 commands.entity(ent).remove::<Transform>();
             }
         }
     }
+
+    // eraser, path and terrain previews match the brush size
+    if eraser_brush.is_changed() || path_brush.is_changed() || terrain_brush.is_changed() {
+        for (_, brush, transform) in query.iter_mut() {
+            match (brush, transform) {
+                (BrushPreview::Eraser(_), Some(mut transform)) => transform.scale = eraser_scale,
+                (BrushPreview::Path, Some(mut transform)) => transform.scale = path_scale,
+                (BrushPreview::Terrain, Some(mut transform)) => transform.scale = terrain_scale,
+                _ => {}
+            }
+        }
+    }
 }
@@ -3,7 +3,8 @@ use bevy_ecs::prelude::*;
 use bevy_input::{keyboard::KeyCode, Input};
 
 use crate::resources::{
-    events::CurveDeletedEvent, ComputePathMask, CurveSegmentsComputePass, WallManager,
+    events::{CurveDeletedEvent, PathsChangedEvent},
+    CommandHistory, CurveSegmentsComputePass, PathManager, WallManager,
 };
 
 // Clear walls
@@ -16,18 +17,23 @@ pub fn clear_canvas(
     wall_manager: Res<WallManager>,
     mut ev_curve_deleted: EventWriter<CurveDeletedEvent>,
     compute_indirect: ResMut<CurveSegmentsComputePass>,
-    mut compute_path_mask: ResMut<ComputePathMask>,
+    mut path_manager: ResMut<PathManager>,
+    mut ev_paths_changed: EventWriter<PathsChangedEvent>,
+    mut history: ResMut<CommandHistory>,
 ) {
     if keys.pressed(KeyCode::Back) {
         for (k, _) in &wall_manager.walls {
             ev_curve_deleted.send(CurveDeletedEvent { curve_index: *k });
         }
 
-        // Clear our the path mask
-        compute_path_mask.0.texture.clear();
+        // Clear our the paths, the path mask is rasterized again from nothing
+        path_manager.clear();
+        ev_paths_changed.send(PathsChangedEvent);
 
         // Clear our the curve segments SSBO
         compute_indirect.reset_segments_buffer();
         compute_indirect.reset_cmd_buffer();
+
+        history.clear();
     }
 }
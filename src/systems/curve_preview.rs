@@ -1,5 +1,6 @@
 use bevy_app::EventReader;
 use bevy_ecs::prelude::*;
+use gl::types::GLenum;
 
 use crate::{
     asset_libraries::{mesh_library::AssetMeshLibrary, shader_library::AssetShaderLibrary, Handle},
@@ -34,8 +35,9 @@ pub fn curve_preview(
             update_curve_debug_mesh(&wall.curve, mesh_handle, &mut assets_mesh);
         } else {
             // if not, make a new entity
-            wall.curve_preview_entity = Some(new_curve_entity(
-                &mut assets_mesh,
+            wall.curve_preview_entity = Some(new_line_entity(
+                assets_mesh.add(Mesh::new().into()),
+                gl::LINE_STRIP,
                 &assets_shader,
                 &mut commands,
             ));
@@ -43,12 +45,13 @@ pub fn curve_preview(
     }
 }
 
-fn new_curve_entity(
-    assets_mesh: &mut ResMut<AssetMeshLibrary>,
+// Also draws the handles of the selected wall, see `wall_handles_preview`
+pub fn new_line_entity(
+    curve_mesh_handle: Handle<Mesh>,
+    draw_mode: GLenum,
     assets_shader: &Res<AssetShaderLibrary>,
     commands: &mut Commands,
 ) -> Entity {
-    let curve_mesh_handle = assets_mesh.add(Mesh::new().into());
     let shader = assets_shader
         .get_handle_by_name("vertex_color_shader")
         .unwrap();
@@ -61,7 +64,7 @@ fn new_curve_entity(
             transform: Transform::identity(),
         })
         .insert(TransientMesh(curve_mesh_handle))
-        .insert(GLDrawMode(gl::LINE_STRIP))
+        .insert(GLDrawMode(draw_mode))
         .id()
 }
 
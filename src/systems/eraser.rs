@@ -1,30 +1,97 @@
 use crate::{
     components::CursorRaycast,
-    geometry::curve::Curve,
+    geometry::{
+        curve::{Curve, WallCurve},
+        wall_opening::openings_within,
+    },
     resources::{
-        events::{CurveChangedEvent, CurveDeletedEvent},
+        events::{CurveChangedEvent, CurveDeletedEvent, PathsChangedEvent},
         wall_manager::RESAMPLING,
-        WallManager,
+        PathManager, TerrainData, WallManager,
     },
 };
-use bevy_app::EventWriter;
+use bevy_app::{EventReader, EventWriter};
 use bevy_ecs::prelude::*;
-use bevy_input::{mouse::MouseButton, Input};
-use glam::{Vec2, Vec3};
+use bevy_input::{
+    keyboard::KeyCode,
+    mouse::{MouseButton, MouseWheel},
+    Input,
+};
+use glam::Vec3;
+
+use super::mode_manager::{ctrl_pressed, BrushMode, EraseLayer};
+
+// Radius of the eraser preview mesh at scale 1
+pub const ERASER_RADIUS_DEFAULT: f32 = 0.75 * 0.9;
+const ERASER_RADIUS_MIN: f32 = 0.1;
+const ERASER_RADIUS_MAX: f32 = 4.0;
+// radius is multiplied by this for every step of the mouse wheel
+const ERASER_RADIUS_STEP: f32 = 1.15;
+
+// The eraser sweeps a capsule from where the cursor was on the previous frame, so fast strokes don't skip anything
+pub struct EraserBrush {
+    pub radius: f32,
+    // part of the stroke covered this frame
+    pub from: Vec3,
+    pub to: Vec3,
+}
+
+impl EraserBrush {
+    pub fn new() -> Self {
+        Self {
+            radius: ERASER_RADIUS_DEFAULT,
+            from: Vec3::ZERO,
+            to: Vec3::ZERO,
+        }
+    }
+}
+
+// Follows the stroke, Ctrl + mouse wheel changes the radius
+pub fn eraser_brush(
+    mode: Res<BrushMode>,
+    mut brush: ResMut<EraserBrush>,
+    mut mouse_wheel_ev: EventReader<MouseWheel>,
+    keys: Res<Input<KeyCode>>,
+    cursor_ws: Res<CursorRaycast>,
+    mouse_button_input: Res<Input<MouseButton>>,
+) {
+    if !matches!(*mode, BrushMode::Eraser(..)) {
+        return;
+    }
+
+    if ctrl_pressed(&keys) {
+        let steps: f32 = mouse_wheel_ev.iter().map(|ev| ev.y).sum();
+        if steps != 0.0 {
+            brush.radius = (brush.radius * ERASER_RADIUS_STEP.powf(steps))
+                .clamp(ERASER_RADIUS_MIN, ERASER_RADIUS_MAX);
+        }
+    }
 
-use super::mode_manager::BrushMode;
+    if mouse_button_input.just_pressed(MouseButton::Left) {
+        brush.from = cursor_ws.0;
+        brush.to = cursor_ws.0;
+    } else if mouse_button_input.pressed(MouseButton::Left) {
+        brush.from = brush.to;
+        brush.to = cursor_ws.0;
+    }
+}
 
+#[allow(clippy::too_many_arguments)]
 pub fn eraser(
     _mode: Res<BrushMode>,
     mut ev_curve_changed: EventWriter<CurveChangedEvent>,
     mut ev_curve_deleted: EventWriter<CurveDeletedEvent>,
+    mut ev_paths_changed: EventWriter<PathsChangedEvent>,
     mut wall_manager: ResMut<WallManager>,
-    cursor_ws: Res<CursorRaycast>,
+    mut path_manager: ResMut<PathManager>,
+    terrain: Res<TerrainData>,
+    brush: Res<EraserBrush>,
     mouse_button_input: Res<Input<MouseButton>>,
 ) {
-    if !matches!(*_mode, BrushMode::Eraser(..)) {
-        return;
-    }
+    let layer = match *_mode {
+        BrushMode::Eraser(layer) => layer,
+        _ => return,
+    };
 
     if !mouse_button_input.pressed(MouseButton::Left) {
         return;
@@ -32,68 +99,59 @@ pub fn eraser(
 
     puffin::profile_function!();
 
-    const ERASE_BRUSH_SIZE: f32 = 0.75 * 0.9;
-    let cursor_ws = cursor_ws.0;
+    if matches!(layer, EraseLayer::All | EraseLayer::Wall) {
+        erase_walls(
+            &brush,
+            &terrain,
+            &mut wall_manager,
+            &mut ev_curve_changed,
+            &mut ev_curve_deleted,
+        );
+    }
 
-    let mut g_cc = Vec::new(); //new curves
-    for (curve_index, wall) in wall_manager.walls.iter_mut() {
-        let mut new_curves = vec![Vec::new()];
-        let mut new_curve_last_index = 0;
-
-        let curve = &wall.curve;
-        for j in 0..(curve.points.len() - 1) {
-            // segment
-            let p1 = curve.points[j];
-            let p2 = curve.points[j + 1];
-
-            // TODO: intersect not just with the latest mouse position withi radius, but an interpolation from the previous frame
-            let p1_is_inside = cursor_ws.distance(p1) < ERASE_BRUSH_SIZE;
-            let p2_is_inside = cursor_ws.distance(p2) < ERASE_BRUSH_SIZE;
-
-            match (p1_is_inside, p2_is_inside) {
-                (false, false) => new_curves[new_curve_last_index].push(p1),
-                (false, true) => {
-                    new_curves[new_curve_last_index].push(p1);
-
-                    let intersection = circle_segment_intersection(
-                        vec3_xz(p1),
-                        vec3_xz(p2),
-                        vec3_xz(cursor_ws),
-                        ERASE_BRUSH_SIZE,
-                    );
-                    new_curves[new_curve_last_index].push(vec2_x0y(intersection.unwrap()));
-
-                    // this is the end of the curve outside the brush stroke
-                    new_curves.push(Vec::new());
-                    new_curve_last_index += 1;
-                }
-                (true, false) => {
-                    // this is the beginning of the curve outside the brush stroke
-                    let intersection = circle_segment_intersection(
-                        vec3_xz(p1),
-                        vec3_xz(p2),
-                        vec3_xz(cursor_ws),
-                        ERASE_BRUSH_SIZE,
-                    );
-                    new_curves[new_curve_last_index].push(vec2_x0y(intersection.unwrap()));
-                }
-                (true, true) => {} // delete segments that are fully inside
-            }
+    if matches!(layer, EraseLayer::All | EraseLayer::Path) && erase_paths(&brush, &mut path_manager)
+    {
+        ev_paths_changed.send(PathsChangedEvent);
+    }
+}
 
-            // if its the last segment
-            if j == curve.points.len() - 2 && !p2_is_inside {
-                new_curves[new_curve_last_index].push(p2);
-            }
-        }
+// Parts of the curve outside the brush stroke, with where they were along it, `None` if the stroke doesn't touch it
+fn remaining_parts(curve: &Curve, brush: &EraserBrush) -> Option<Vec<(Curve, (f32, f32))>> {
+    let mut erased = curve.ranges_within_capsule(brush.from, brush.to, brush.radius);
+    // ends that were cut by the brush in the previous frame are just touching it
+    erased.retain(|(from, to)| to - from > 0.001);
+    if erased.is_empty() {
+        return None;
+    }
 
-        // check if no degenerate curves
-        let mut cc = Vec::new();
-        for n in new_curves {
-            let c = Curve::from(n);
+    let mut cc = Vec::new();
+    let mut start = 0.0;
+    for (from, to) in erased.into_iter().chain([(curve.length, curve.length)]) {
+        if from > start {
+            let c = curve.slice(start, from);
+            // check if no degenerate curves
             if c.length > 0.0 {
-                cc.push(c);
+                cc.push((c, (start, from)));
             }
         }
+        start = to;
+    }
+    Some(cc)
+}
+
+fn erase_walls(
+    brush: &EraserBrush,
+    terrain: &TerrainData,
+    wall_manager: &mut WallManager,
+    ev_curve_changed: &mut EventWriter<CurveChangedEvent>,
+    ev_curve_deleted: &mut EventWriter<CurveDeletedEvent>,
+) {
+    let mut g_cc = Vec::new(); //new curves
+    for (curve_index, wall) in wall_manager.walls.iter() {
+        let cc = match remaining_parts(&wall.curve, brush) {
+            Some(cc) => cc,
+            None => continue,
+        };
 
         // if no curves left, send an evene to delete this curve completely
         if cc.is_empty() {
@@ -106,76 +164,74 @@ pub fn eraser(
     }
 
     for (curve_index, cc) in g_cc {
+        // openings are kept on the pieces they're entirely in
+        let length = wall_manager.get(curve_index).unwrap().curve.length;
+        let openings = wall_manager.get(curve_index).unwrap().openings.clone();
+        // pieces of a spline wall are cut out of the spline, where they are along its polyline
+        let spline = wall_manager.get(curve_index).unwrap().spline.clone();
+
         // Update curves
-        for j in 0..cc.len() {
+        for (j, (piece, (from, to))) in cc.into_iter().enumerate() {
+            let piece_openings = openings_within(&openings, from / length, to / length);
+            let piece_spline = spline.as_ref().map(|spline| {
+                let scale = spline.length() / length;
+                spline.slice(from * scale, to * scale)
+            });
             if j == 0 {
-                wall_manager.get_mut(curve_index).unwrap().curve =
-                    cc[0].clone().resample(RESAMPLING);
+                let wall = wall_manager.get_mut(curve_index).unwrap();
+                wall.curve = piece.resample(RESAMPLING);
+                wall.spline = None;
+                wall.openings = piece_openings;
+                // the bricks stay where they were if the start of the wall was erased
+                wall.start_offset -= from;
+                if let Some(piece_spline) = piece_spline {
+                    wall.set_spline(&piece_spline, terrain);
+                }
 
                 ev_curve_changed.send(CurveChangedEvent {
                     curve_index: curve_index,
                 });
             } else {
-                let index = wall_manager.new_wall(cc[j].clone().resample(RESAMPLING));
+                // pieces split off a wall keep its style, but their bricks start over anyway
+                let style = wall_manager.get(curve_index).unwrap().style;
+                let index =
+                    wall_manager.new_wall(piece.resample(RESAMPLING), style, fastrand::u64(..));
+                let wall = wall_manager.get_mut(index).unwrap();
+                wall.openings = piece_openings;
+                if let Some(piece_spline) = piece_spline {
+                    wall.set_spline(&piece_spline, terrain);
+                }
                 ev_curve_changed.send(CurveChangedEvent { curve_index: index });
             }
         }
     }
 }
 
-fn vec2_x0y(v: Vec2) -> Vec3 {
-    Vec3::new(v.x, 0.0, v.y)
-}
-
-fn vec3_xz(v: Vec3) -> Vec2 {
-    Vec2::new(v.x, v.z)
-}
-
-fn circle_segment_intersection(
-    seg_start: Vec2,
-    seg_end: Vec2,
-    circle_center: Vec2,
-    circle_radius: f32,
-) -> Option<Vec2> {
-    /*
-       let ray = (seg_end - seg_start).normalize();
-       let f = seg_start - circle_center;
-       let r = circle_radius;
-
-       let a = ray.dot(ray);
-       let b = 2.0 * f.dot(ray);
-       let c = f.dot(f) - r * r;
-
-       let discriminant = b * b - 4.0 * a * c;
-
-       if discriminant < 0.0 {
-           None
-       } else {
-           let t = (-b - discriminant.sqrt()) / (2.0 * a);
-           Some(seg_start + ray * t)
-       }
-    */
-
-    let subdivs = 50;
-
-    let mut min_d = (circle_radius - seg_start.distance(circle_center)).abs();
-
-    for i in 1..(subdivs + 1) {
-        let t = (i as f32) / (subdivs as f32);
-        let p = seg_start.lerp(seg_end, t);
-
-        let d = (circle_radius - p.distance(circle_center)).abs();
-        //dbg!(t);
-        //dbg!(circle_radius);
-        //dbg!(p.distance(circle_center));
-
-        if d < min_d {
-            min_d = d;
-        } else {
-            // if distance started growing, then its the closest we are to intersection
-            return Some(p);
+// Cuts the paths' centerlines like walls, pieces keep the width of the path they were cut from.
+// Returns whether any path changed
+fn erase_paths(brush: &EraserBrush, path_manager: &mut PathManager) -> bool {
+    let cut: Vec<(usize, Vec<Curve>)> = path_manager
+        .paths
+        .iter()
+        .filter_map(|(i, p)| {
+            remaining_parts(&p.curve, brush)
+                .map(|cc| (*i, cc.into_iter().map(|(piece, _)| piece).collect()))
+        })
+        .collect();
+
+    for (index, cc) in &cut {
+        let width = path_manager.get(*index).unwrap().width;
+        let mut pieces = cc.iter().cloned();
+        match pieces.next() {
+            Some(first) => path_manager.get_mut(*index).unwrap().curve = first,
+            None => {
+                path_manager.remove(*index);
+            }
+        }
+        for piece in pieces {
+            path_manager.new_path(piece, width);
         }
     }
 
-    Some(seg_end)
+    !cut.is_empty()
 }
@@ -0,0 +1,177 @@
+use bevy_ecs::prelude::*;
+use bevy_input::{keyboard::KeyCode, Input};
+
+use crate::{
+    asset_libraries::mesh_library::AssetMeshLibrary,
+    geometry::{
+        arches::{arch_brick, arch_bricks, arch_segments, BlurredPathMask},
+        chunks::ChunkWindow,
+        instanced_wall::InstancedWall,
+        wall_constructor::Brick,
+    },
+    resources::{PathMask, TerrainData, WallManager},
+    systems::mode_manager::ctrl_pressed,
+    utils::export_gltf::{export_walls_as_glb, GltfExportMode},
+};
+
+pub const EXPORT_GLB_PATH: &str = "walls.glb";
+
+// Ctrl+G exports instanced bricks, Ctrl+M exports a single merged mesh. Arches are exported along with the walls
+pub fn export_walls(
+    keys: Res<Input<KeyCode>>,
+    wall_manager: Res<WallManager>,
+    path_mask: Res<PathMask>,
+    terrain: Res<TerrainData>,
+    chunk_window: Res<ChunkWindow>,
+    assets_mesh: Res<AssetMeshLibrary>,
+) {
+    if !ctrl_pressed(&keys) {
+        return;
+    }
+
+    let mode = if keys.just_pressed(KeyCode::G) {
+        GltfExportMode::Instanced
+    } else if keys.just_pressed(KeyCode::M) {
+        GltfExportMode::Merged
+    } else {
+        return;
+    };
+
+    puffin::profile_function!();
+
+    let walls = wall_bricks(&wall_manager);
+    if walls.is_empty() {
+        log::warn!("glTF export: there are no walls to export");
+        return;
+    }
+
+    let brick_mesh = assets_mesh
+        .get_by_name("brick")
+        .expect("glTF export: brick mesh is missing");
+
+    let arches = export_arches(&wall_manager, &path_mask, &terrain, &chunk_window);
+
+    match export_walls_as_glb(EXPORT_GLB_PATH, brick_mesh, &walls, &arches, mode) {
+        Ok(()) => log::info!("Walls exported to {}", EXPORT_GLB_PATH),
+        Err(err) => log::error!("{}", err),
+    }
+}
+
+// Bricks are not kept around on the CPU, but `WallConstructor` is deterministic, so just re-generate them,
+// joined the same way `walls_update` joins them
+fn wall_bricks(wall_manager: &WallManager) -> Vec<(usize, Vec<Brick>)> {
+    // Sort by index, so that walls are exported in the order they were drawn
+    let mut indices: Vec<_> = wall_manager.walls.keys().copied().collect();
+    indices.sort_unstable();
+
+    indices
+        .into_iter()
+        .filter_map(|i| {
+            let wall = &wall_manager.walls[&i];
+            let layout = wall_manager.layout(i)?;
+            if wall.curve.points.len() < 2 {
+                None
+            } else {
+                Some((
+                    i,
+                    InstancedWall::bricks(
+                        wall.shape(),
+                        &wall.style,
+                        &layout,
+                        &wall_manager.junctions.joints(i),
+                        &wall.openings,
+                    ),
+                ))
+            }
+        })
+        .collect()
+}
+
+// Arches are laid out on the GPU, so they are built again with the CPU version of the passes.
+// The GPU lifts them onto the terrain in the vertex shader, here every brick is lifted by the height under its middle
+fn export_arches(
+    wall_manager: &WallManager,
+    path_mask: &PathMask,
+    terrain: &TerrainData,
+    chunk_window: &ChunkWindow,
+) -> Vec<Brick> {
+    let mask = path_mask.window_image(chunk_window);
+    let blurred = BlurredPathMask(&mask);
+
+    // in the order walls were drawn, so that re-exporting gives the same colors
+    let mut indices: Vec<_> = wall_manager.walls.keys().copied().collect();
+    indices.sort_unstable();
+
+    let segments: Vec<_> = indices
+        .into_iter()
+        .map(|i| &wall_manager.walls[&i])
+        .filter(|wall| wall.style.construction.builder().has_arches())
+        .flat_map(|wall| arch_segments(&wall.curve, &wall.style.arch, &blurred))
+        .collect();
+
+    arch_bricks(&segments, &blurred)
+        .into_iter()
+        .map(|transform| {
+            let mut brick = arch_brick(transform);
+            let position = brick.transform.translation;
+            brick.transform.translation.y += terrain.height_at(position.x, position.z);
+            brick
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use glam::Vec3;
+
+    use super::*;
+    use crate::geometry::{curve::Curve, wall_junction::WallJoints, wall_style::WallStyle};
+
+    fn straight(from: Vec3, to: Vec3) -> Curve {
+        Curve::from((0..=20).map(|i| from.lerp(to, i as f32 / 20.0)).collect())
+    }
+
+    #[test]
+    fn joined_walls_export_like_they_render() {
+        let mut wall_manager = WallManager::new();
+        let style = WallStyle::default();
+        // a corner
+        let a = wall_manager.new_wall(straight(Vec3::ZERO, Vec3::new(4.0, 0.0, 0.0)), style, 1);
+        let b = wall_manager.new_wall(
+            straight(Vec3::new(4.0, 0.0, 0.0), Vec3::new(4.0, 0.0, 4.0)),
+            style,
+            2,
+        );
+        let changed: HashSet<usize> = [a, b].iter().copied().collect();
+        wall_manager.update_junctions(&changed);
+
+        let exported = wall_bricks(&wall_manager);
+        assert_eq!(exported.len(), 2);
+        for (i, bricks) in exported {
+            let wall = wall_manager.get(i).unwrap();
+            let joints = wall_manager.junctions.joints(i);
+            let layout = wall_manager.layout(i).unwrap();
+            let rendered = InstancedWall::bricks(wall.shape(), &wall.style, &layout, &joints, &[]);
+            assert_eq!(bricks, rendered);
+            // rows line up with the first wall of the corner
+            assert_eq!(layout.rows_seed, 1);
+
+            let unjoined = InstancedWall::bricks(
+                wall.shape(),
+                &wall.style,
+                &layout,
+                &WallJoints::default(),
+                &[],
+            );
+            assert_ne!(bricks, unjoined, "wall {} isn't joined", i);
+        }
+
+        // rerolling the first wall changes the rows of the other one too
+        wall_manager.get_mut(a).unwrap().seed = 3;
+        let rebuilt = wall_manager.update_junctions(&[a].iter().copied().collect());
+        assert!(rebuilt.contains(&b));
+        assert_eq!(wall_manager.layout(b).unwrap().rows_seed, 3);
+    }
+}
@@ -0,0 +1,72 @@
+use bevy_app::EventWriter;
+use bevy_ecs::prelude::*;
+use bevy_input::{keyboard::KeyCode, Input};
+
+use crate::{
+    geometry::chunks::ChunkWindow,
+    resources::{events::TerrainChangedEvent, TerrainData},
+    systems::mode_manager::ctrl_pressed,
+    utils::heightmap::Heightmap,
+};
+
+pub const HEIGHTMAP_PNG_PATH: &str = "heightmap.png";
+pub const HEIGHTMAP_RAW_PATH: &str = "heightmap.r32";
+
+// Ctrl+H writes the heights of the chunks in the window, as a 16-bit PNG and as raw f32
+pub fn export_heightmap(
+    keys: Res<Input<KeyCode>>,
+    terrain: Res<TerrainData>,
+    window: Res<ChunkWindow>,
+) {
+    if !(ctrl_pressed(&keys) && keys.just_pressed(KeyCode::H)) {
+        return;
+    }
+
+    puffin::profile_function!();
+
+    let heightmap = terrain.heightmap(&window);
+    for path in [HEIGHTMAP_PNG_PATH, HEIGHTMAP_RAW_PATH] {
+        match heightmap.save(path) {
+            Ok(()) => log::info!("Heightmap exported to {}", path),
+            Err(err) => log::error!("{}", err),
+        }
+    }
+}
+
+// Ctrl+I stretches a heightmap over the chunks in the window. The raw one is lossless, so it's preferred over the PNG
+pub fn import_heightmap(
+    keys: Res<Input<KeyCode>>,
+    mut terrain: ResMut<TerrainData>,
+    window: Res<ChunkWindow>,
+    mut ev_terrain_changed: EventWriter<TerrainChangedEvent>,
+) {
+    if !(ctrl_pressed(&keys) && keys.just_pressed(KeyCode::I)) {
+        return;
+    }
+
+    puffin::profile_function!();
+
+    let path = if std::path::Path::new(HEIGHTMAP_RAW_PATH).exists() {
+        HEIGHTMAP_RAW_PATH
+    } else {
+        HEIGHTMAP_PNG_PATH
+    };
+
+    let heightmap = match Heightmap::load(path) {
+        Ok(heightmap) => heightmap,
+        Err(err) => {
+            log::error!("{}", err);
+            return;
+        }
+    };
+
+    terrain.set_heightmap(&window, &heightmap);
+    ev_terrain_changed.send(TerrainChangedEvent);
+
+    log::info!(
+        "Heightmap {}x{} imported from {}",
+        heightmap.dims.0,
+        heightmap.dims.1,
+        path
+    );
+}
@@ -0,0 +1,156 @@
+use bevy_app::EventWriter;
+use bevy_ecs::prelude::*;
+use bevy_input::{keyboard::KeyCode, mouse::MouseButton, Input};
+
+use crate::{
+    resources::{
+        events::{CurveChangedEvent, CurveDeletedEvent, PathsChangedEvent},
+        history::*,
+        PathManager, TerrainData, WallManager,
+    },
+    systems::mode_manager::{ctrl_pressed, BrushMode},
+};
+
+// Runs in the "opengl" stage, i.e. before brushes had a chance to modify anything this frame
+pub fn history_begin_stroke(
+    mode: Res<BrushMode>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    wall_manager: Res<WallManager>,
+    path_manager: Res<PathManager>,
+    mut history: ResMut<CommandHistory>,
+) {
+    if !mouse_button_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    // terrain edits aren't recorded, and the curves they move follow the terrain when they are restored anyway.
+    // Selecting doesn't change anything, edits of the selected wall and openings are recorded as they happen
+    if matches!(
+        *mode,
+        BrushMode::Terrain(_) | BrushMode::Select | BrushMode::Opening(_)
+    ) {
+        return;
+    }
+
+    puffin::profile_function!();
+
+    history.in_progress = Some(StrokeSnapshot::new(&wall_manager, &path_manager));
+}
+
+pub fn history_end_stroke(
+    mouse_button_input: Res<Input<MouseButton>>,
+    wall_manager: Res<WallManager>,
+    path_manager: Res<PathManager>,
+    mut history: ResMut<CommandHistory>,
+) {
+    if !mouse_button_input.just_released(MouseButton::Left) {
+        return;
+    }
+
+    if let Some(before) = history.in_progress.take() {
+        puffin::profile_function!();
+
+        let after = StrokeSnapshot::new(&wall_manager, &path_manager);
+
+        history.push(Stroke::from_snapshots(before, after));
+    }
+}
+
+// Ctrl+Z & Ctrl+Y
+#[allow(clippy::too_many_arguments)]
+pub fn undo_redo(
+    keys: Res<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    mut history: ResMut<CommandHistory>,
+    mut wall_manager: ResMut<WallManager>,
+    mut path_manager: ResMut<PathManager>,
+    mut ev_curve_changed: EventWriter<CurveChangedEvent>,
+    mut ev_curve_deleted: EventWriter<CurveDeletedEvent>,
+    mut ev_paths_changed: EventWriter<PathsChangedEvent>,
+    terrain: Res<TerrainData>,
+) {
+    // Don't touch the history mid-stroke
+    if !ctrl_pressed(&keys) || mouse_button_input.pressed(MouseButton::Left) {
+        return;
+    }
+
+    let (stroke, forward) = if keys.just_pressed(KeyCode::Z) {
+        (history.undo(), false)
+    } else if keys.just_pressed(KeyCode::Y) {
+        (history.redo(), true)
+    } else {
+        return;
+    };
+
+    let stroke = match stroke {
+        Some(stroke) => stroke,
+        None => {
+            log::info!("Nothing to {}", if forward { "redo" } else { "undo" });
+            return;
+        }
+    };
+
+    puffin::profile_function!();
+
+    // the terrain could have changed since the curves were recorded
+    for change in &stroke.walls {
+        let target = if forward {
+            &change.after
+        } else {
+            &change.before
+        };
+
+        match target {
+            Some(state) => {
+                if let Some(wall) = wall_manager.get_mut(change.index) {
+                    wall.curve = state.curve.clone();
+                    wall.style = state.style;
+                    wall.seed = state.seed;
+                } else {
+                    wall_manager.insert_wall(
+                        change.index,
+                        state.curve.clone(),
+                        state.style,
+                        state.seed,
+                    );
+                }
+                if let Some(wall) = wall_manager.get_mut(change.index) {
+                    wall.openings = state.openings.clone();
+                    wall.start_offset = state.start_offset;
+                    wall.spline = state.spline.clone();
+                    wall.project(&terrain);
+                }
+                ev_curve_changed.send(CurveChangedEvent {
+                    curve_index: change.index,
+                });
+            }
+            None => {
+                if wall_manager.get(change.index).is_some() {
+                    ev_curve_deleted.send(CurveDeletedEvent {
+                        curve_index: change.index,
+                    });
+                }
+            }
+        }
+    }
+
+    for change in &stroke.paths {
+        let target = if forward {
+            &change.after
+        } else {
+            &change.before
+        };
+
+        match target {
+            Some(state) => {
+                path_manager.insert_path(change.index, terrain.project(&state.curve), state.width)
+            }
+            None => {
+                path_manager.remove(change.index);
+            }
+        }
+    }
+    if !stroke.paths.is_empty() {
+        ev_paths_changed.send(PathsChangedEvent);
+    }
+}
@@ -2,16 +2,20 @@ use bevy_app::EventReader;
 use bevy_core::Time;
 use bevy_ecs::prelude::*;
 use bevy_input::{
+    keyboard::KeyCode,
     mouse::{MouseButton, MouseWheel},
     Input,
 };
 use dolly::prelude::{Arm, Position, YawPitch};
 use glam::Mat4;
 
-use crate::{render::camera::MainCamera, window_events::CursorMoved};
+use crate::{
+    render::camera::MainCamera, systems::mode_manager::ctrl_pressed, window_events::CursorMoved,
+};
 
 pub fn main_camera_update(
     mouse_button_input: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
     mut mouse_wheel_ev: EventReader<MouseWheel>,
     mut cursor: EventReader<CursorMoved>,
 
@@ -54,7 +58,9 @@ pub fn main_camera_update(
         }
     }
 
-    if let Some(mouse_wheel) = mouse_wheel_ev.iter().last() {
+    // Ctrl + mouse wheel is for brushes
+    let mouse_wheel = mouse_wheel_ev.iter().last();
+    if let Some(mouse_wheel) = mouse_wheel.filter(|_| !ctrl_pressed(&keys)) {
         // TODO: longer the wheel is used, it should get exp
         // TODO: add smoothness that only affects the offset of the arm but not the parent stuff, that gets nauseous! (or smoothing that only applies in one axis)
         // TODO: fork bevy and add ConstranedSmooth? that you can specify the axis of smoothing and ChildSmoothing, which only applies it to children?
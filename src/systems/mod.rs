@@ -2,11 +2,18 @@ pub mod brush_preview;
 pub mod clear_canvas;
 pub mod curve_preview;
 pub mod eraser;
+pub mod export;
+pub mod heightmap_io;
+pub mod history;
 pub mod main_camera;
 pub mod mode_manager;
 pub mod mouse_raycast;
+pub mod path;
 pub mod render;
+pub mod scene_io;
+pub mod sculpt_terrain;
 pub mod startup;
+pub mod stream_chunks;
 pub mod transient_mesh;
 pub mod update_terrain;
 pub mod wall;
@@ -15,11 +22,18 @@ pub use brush_preview::*;
 pub use clear_canvas::*;
 pub use curve_preview::*;
 pub use eraser::*;
+pub use export::*;
+pub use heightmap_io::*;
+pub use history::*;
 pub use main_camera::*;
 pub use mode_manager::*;
 pub use mouse_raycast::*;
+pub use path::*;
 pub use render::*;
+pub use scene_io::*;
+pub use sculpt_terrain::*;
 pub use startup::*;
+pub use stream_chunks::*;
 pub use transient_mesh::*;
 pub use update_terrain::*;
 pub use wall::*;
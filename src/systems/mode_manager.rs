@@ -2,13 +2,17 @@ use bevy_app::EventWriter;
 use bevy_ecs::prelude::*;
 use bevy_input::{keyboard::KeyCode, Input};
 
-use crate::resources::events::BrushModeJustChanged;
+use crate::{
+    geometry::wall_opening::OpeningKind,
+    resources::{events::BrushModeJustChanged, TerrainOp},
+};
 
-#[derive(Debug)]
+// What the eraser removes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum EraseLayer {
     All,
-    _Wall,
-    _Path,
+    Wall,
+    Path,
 }
 
 #[derive(Debug)]
@@ -16,6 +20,11 @@ pub enum BrushMode {
     Wall,
     Path,
     Eraser(EraseLayer),
+    Terrain(TerrainOp),
+    // picks walls to edit, see `select_wall`
+    Select,
+    // cuts gates, doors and windows into walls, see `place_opening`
+    Opening(OpeningKind),
 }
 
 impl Default for BrushMode {
@@ -24,9 +33,22 @@ impl Default for BrushMode {
     }
 }
 
+// How the wall brush turns the raw stroke into a wall curve
+#[derive(Debug, Default)]
+pub enum StrokeFitting {
+    #[default]
+    Smooth,
+    Spline,
+}
+
+pub fn ctrl_pressed(keys: &Input<KeyCode>) -> bool {
+    keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl)
+}
+
 pub fn mode_manager(
     mut mode: ResMut<BrushMode>,
     mut ev_mode_changed: EventWriter<BrushModeJustChanged>,
+    mut stroke_fitting: ResMut<StrokeFitting>,
     keys: Res<Input<KeyCode>>,
     //mut assets_mesh: ResMut<AssetMeshLibrary>,
 ) {
@@ -44,10 +66,58 @@ pub fn mode_manager(
         });
     }
 
-    if keys.just_pressed(KeyCode::Key3) {
-        *mode = BrushMode::Eraser(EraseLayer::All);
+    for (key, layer) in [
+        (KeyCode::Key3, EraseLayer::All),
+        (KeyCode::Key4, EraseLayer::Wall),
+        (KeyCode::Key5, EraseLayer::Path),
+    ] {
+        if keys.just_pressed(key) {
+            *mode = BrushMode::Eraser(layer);
+            ev_mode_changed.send(BrushModeJustChanged {
+                to: BrushMode::Eraser(layer),
+            });
+        }
+    }
+
+    for (key, op) in [
+        (KeyCode::Key6, TerrainOp::Raise),
+        (KeyCode::Key7, TerrainOp::Lower),
+        (KeyCode::Key8, TerrainOp::Smooth),
+        (KeyCode::Key9, TerrainOp::Flatten),
+    ] {
+        if keys.just_pressed(key) {
+            *mode = BrushMode::Terrain(op);
+            ev_mode_changed.send(BrushModeJustChanged {
+                to: BrushMode::Terrain(op),
+            });
+        }
+    }
+
+    if keys.just_pressed(KeyCode::Key0) {
+        *mode = BrushMode::Select;
+        ev_mode_changed.send(BrushModeJustChanged {
+            to: BrushMode::Select,
+        });
+    }
+
+    // pressing O again switches to the next kind of opening
+    if keys.just_pressed(KeyCode::O) {
+        let kind = match *mode {
+            BrushMode::Opening(kind) => kind.next(),
+            _ => OpeningKind::Gate,
+        };
+        *mode = BrushMode::Opening(kind);
         ev_mode_changed.send(BrushModeJustChanged {
-            to: BrushMode::Eraser(EraseLayer::All),
+            to: BrushMode::Opening(kind),
         });
+        log::info!("Opening: {}", kind.name());
+    }
+
+    if keys.just_pressed(KeyCode::C) {
+        *stroke_fitting = match *stroke_fitting {
+            StrokeFitting::Smooth => StrokeFitting::Spline,
+            StrokeFitting::Spline => StrokeFitting::Smooth,
+        };
+        log::info!("Stroke fitting: {:?}", *stroke_fitting);
     }
 }
@@ -32,28 +32,12 @@ pub fn mouse_raycast(
         &main_camera.camera,
     );
 
-    // solve for the terrain min_y & max_y
-    // ray-plane intersction, solving for (P + d * ray).y = 0.0
-    // (camera_ws + dist * ray).y = terrain.y;
-    let upper_bound_p = cursor_ws + ray * ((terrain.max_y - cursor_ws.y) / ray.y);
-    let lower_bound_p = cursor_ws + ray * ((terrain.min_y - cursor_ws.y) / ray.y);
-
-    //TODO: at grazing angles, needs more subdivision OR make it adaptive per ray length
-    let steps = 1000;
-    let step_size = (lower_bound_p - upper_bound_p).length() / (steps as f32);
-
-    // raymarch along camera ray
-    let mut p = upper_bound_p;
-    for _ in 0..steps {
-        let new_p = p + ray * step_size;
-
-        if new_p.y < terrain.height_at(new_p.x, new_p.z) {
-            // intersection with the terrain is found
-            break;
-        }
-
-        p = new_p;
-    }
+    // the cursor stays where it last was on the terrain while it points at the sky
+    let hit = match terrain.raycast(cursor_ws, ray) {
+        Some(hit) => hit,
+        None => return,
+    };
+    let p = hit.position;
 
     *cursor_ws_cache = CursorRaycast(p);
 
@@ -0,0 +1,45 @@
+use bevy_app::EventWriter;
+use bevy_ecs::prelude::*;
+use bevy_input::{keyboard::KeyCode, mouse::MouseButton, Input};
+
+use crate::{
+    components::CursorRaycast,
+    resources::{events::PathsChangedEvent, history::*, PathManager},
+    systems::mode_manager::BrushMode,
+};
+
+// With the path brush, Delete removes the path under the cursor
+pub fn delete_path(
+    mode: Res<BrushMode>,
+    keys: Res<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    cursor_ws: Res<CursorRaycast>,
+    mut path_manager: ResMut<PathManager>,
+    mut history: ResMut<CommandHistory>,
+    mut ev_paths_changed: EventWriter<PathsChangedEvent>,
+) {
+    if !matches!(*mode, BrushMode::Path)
+        || !keys.just_pressed(KeyCode::Delete)
+        || mouse_button_input.pressed(MouseButton::Left)
+    {
+        return;
+    }
+
+    let index = match path_manager.path_at(cursor_ws.0) {
+        Some(index) => index,
+        None => return,
+    };
+
+    let path = path_manager.remove(index).unwrap();
+    history.push(Stroke {
+        walls: Vec::new(),
+        paths: vec![PathChange {
+            index,
+            before: Some(PathState::of(&path)),
+            after: None,
+        }],
+    });
+
+    log::debug!("Path index {} has been removed", index);
+    ev_paths_changed.send(PathsChangedEvent);
+}
@@ -0,0 +1,107 @@
+use bevy_app::{EventReader, EventWriter};
+use bevy_ecs::prelude::*;
+use bevy_input::{
+    keyboard::KeyCode,
+    mouse::{MouseButton, MouseWheel},
+    Input,
+};
+use glam::Vec3;
+
+use crate::{
+    geometry::curve::Curve,
+    resources::{events::PathsChangedEvent, PathManager, TerrainData, PATH_WIDTH_DEFAULT},
+    systems::mode_manager::{ctrl_pressed, BrushMode},
+    CursorRaycast,
+};
+
+const PATH_WIDTH_MIN: f32 = 0.2;
+const PATH_WIDTH_MAX: f32 = 4.0;
+// width is multiplied by this for every step of the mouse wheel
+const PATH_WIDTH_STEP: f32 = 1.15;
+
+// Points closer than this to the previous one aren't added to the path
+const PATH_POINT_SPACING: f32 = 0.1;
+
+pub struct PathBrush {
+    // width of the paths it draws
+    pub width: f32,
+    // where the current stroke started, its path is only made once the cursor moved away from there
+    start: Option<Vec3>,
+}
+
+impl PathBrush {
+    pub fn new() -> Self {
+        Self {
+            width: PATH_WIDTH_DEFAULT,
+            start: None,
+        }
+    }
+}
+
+// Every stroke draws a new path, Ctrl + mouse wheel changes the width of the next one
+#[allow(clippy::too_many_arguments)]
+pub fn draw_path(
+    mode: Res<BrushMode>,
+    mut brush: ResMut<PathBrush>,
+    mut path_manager: ResMut<PathManager>,
+    mut ev_paths_changed: EventWriter<PathsChangedEvent>,
+    mut mouse_wheel_ev: EventReader<MouseWheel>,
+    keys: Res<Input<KeyCode>>,
+    terrain: Res<TerrainData>,
+    cursor_ws: Res<CursorRaycast>,
+    mouse_button_input: Res<Input<MouseButton>>,
+) {
+    if !matches!(*mode, BrushMode::Path) {
+        return;
+    }
+
+    if ctrl_pressed(&keys) {
+        let steps: f32 = mouse_wheel_ev.iter().map(|ev| ev.y).sum();
+        if steps != 0.0 {
+            brush.width =
+                (brush.width * PATH_WIDTH_STEP.powf(steps)).clamp(PATH_WIDTH_MIN, PATH_WIDTH_MAX);
+        }
+    }
+
+    // Snap the cursor-terrain raycast position onto the terrain
+    let mut cursor_ws = cursor_ws.0;
+    cursor_ws.y = terrain.height_at(cursor_ws.x, cursor_ws.z);
+
+    if mouse_button_input.just_pressed(MouseButton::Left) {
+        brush.start = Some(cursor_ws);
+        path_manager.active = None;
+    } else if mouse_button_input.pressed(MouseButton::Left) {
+        let path = match path_manager
+            .active
+            .and_then(|i| path_manager.paths.get_mut(&i))
+        {
+            Some(path) => path,
+            None => {
+                // a click without a stroke doesn't make a path, so there's nothing for the history to record
+                if let Some(start) = brush.start {
+                    if cursor_ws.distance(start) > PATH_POINT_SPACING {
+                        let index =
+                            path_manager.new_path(Curve::from(vec![start, cursor_ws]), brush.width);
+                        path_manager.active = Some(index);
+                        ev_paths_changed.send(PathsChangedEvent);
+                    }
+                }
+                return;
+            }
+        };
+
+        if path
+            .curve
+            .points
+            .last()
+            .map(|pt| cursor_ws.distance(*pt) > PATH_POINT_SPACING)
+            .unwrap_or(true)
+        {
+            path.curve.add(cursor_ws);
+            ev_paths_changed.send(PathsChangedEvent);
+        }
+    } else if mouse_button_input.just_released(MouseButton::Left) {
+        brush.start = None;
+        path_manager.active = None;
+    }
+}
@@ -0,0 +1,7 @@
+pub mod delete_path;
+pub mod draw_path;
+pub mod update_path_mask;
+
+pub use delete_path::*;
+pub use draw_path::*;
+pub use update_path_mask::*;
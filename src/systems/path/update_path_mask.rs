@@ -0,0 +1,29 @@
+use bevy_app::EventReader;
+use bevy_ecs::prelude::*;
+
+use crate::{
+    geometry::chunks::ChunkWindow,
+    resources::{events::PathsChangedEvent, PathManager, PathMask},
+};
+
+// Rasterizes the paths that changed into the path mask tiles.
+// Uploads the texture, so it has to run in a single-threaded stage
+pub fn update_path_mask(
+    mut ev_paths_changed: EventReader<PathsChangedEvent>,
+    path_manager: Res<PathManager>,
+    window: Res<ChunkWindow>,
+    mut path_mask: ResMut<PathMask>,
+) {
+    if ev_paths_changed.iter().count() == 0 {
+        return;
+    }
+
+    puffin::profile_function!();
+
+    let paths: Vec<_> = path_manager
+        .paths
+        .iter()
+        .map(|(index, p)| (*index, &p.curve, p.width))
+        .collect();
+    path_mask.rasterize(&paths, &window);
+}
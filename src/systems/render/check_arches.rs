@@ -0,0 +1,73 @@
+use bevy_ecs::prelude::*;
+use bevy_input::{keyboard::KeyCode, Input};
+
+use crate::{
+    geometry::{
+        arches::{arch_bricks, arch_segments, compare_arch_bricks, BlurredPathMask},
+        chunks::ChunkWindow,
+    },
+    resources::{ComputeArchesIndirect, CurveSegmentsComputePass, PathMask, WallManager},
+    systems::mode_manager::ctrl_pressed,
+};
+
+// Per matrix element, and per coordinate of the arches. The blur can sample a neighbouring pixel on the GPU, its `cos` and `sin` round differently
+const ARCH_CHECK_TOLERANCE: f32 = 1e-3;
+
+// Ctrl+A compares the arches the GPU laid out last frame with the CPU version of the passes (see `geometry::arches`)
+// and logs the differences. The GPU reads the mask blurred a frame later, so check while nothing is being edited
+pub fn check_arches(
+    keys: Res<Input<KeyCode>>,
+    wall_manager: Res<WallManager>,
+    path_mask: Res<PathMask>,
+    chunk_window: Res<ChunkWindow>,
+    curve_segments: Res<CurveSegmentsComputePass>,
+    compute_arches: Res<ComputeArchesIndirect>,
+) {
+    if !(ctrl_pressed(&keys) && keys.just_pressed(KeyCode::A)) {
+        return;
+    }
+
+    let mask = path_mask.window_image(&chunk_window);
+    let blurred = BlurredPathMask(&mask);
+
+    let segments: Vec<_> = wall_manager
+        .walls
+        .values()
+        .filter(|wall| wall.style.construction.builder().has_arches())
+        .flat_map(|wall| arch_segments(&wall.curve, &wall.style.arch, &blurred))
+        .collect();
+    let bricks = arch_bricks(&segments, &blurred);
+
+    let gpu_segments = curve_segments
+        .segments_buffer
+        .read(curve_segments.read_segment_count() as usize);
+    let gpu_bricks = compute_arches
+        .transforms_buffer
+        .read(compute_arches.read_instance_count() as usize);
+
+    let missing = segments
+        .iter()
+        .filter(|segment| {
+            !gpu_segments
+                .iter()
+                .any(|gpu| gpu.abs_diff_eq(segment, ARCH_CHECK_TOLERANCE))
+        })
+        .count();
+    if missing > 0 || gpu_segments.len() != segments.len() {
+        log::warn!(
+            "Arch check: the GPU found {} arches, {} of the {} expected ones are missing",
+            gpu_segments.len(),
+            missing,
+            segments.len()
+        );
+    }
+
+    match compare_arch_bricks(&bricks, &gpu_bricks, ARCH_CHECK_TOLERANCE) {
+        Ok(()) => log::info!(
+            "Arch check: the {} bricks of {} arches match",
+            bricks.len(),
+            segments.len()
+        ),
+        Err(err) => log::warn!("Arch check: {}", err),
+    }
+}
@@ -1,8 +1,10 @@
+pub mod check_arches;
 pub mod shader_update;
 pub mod ssbo_delete;
 pub mod update_curve_ssbo;
 pub mod vao_update;
 
+pub use check_arches::*;
 pub use shader_update::*;
 pub use ssbo_delete::*;
 pub use update_curve_ssbo::*;
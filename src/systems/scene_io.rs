@@ -0,0 +1,145 @@
+use bevy_app::EventWriter;
+use bevy_ecs::prelude::*;
+use bevy_input::{keyboard::KeyCode, Input};
+
+use crate::{
+    geometry::{chunks::ChunkWindow, curve::Curve, spline_curve::SplineCurve},
+    resources::{
+        events::{CurveChangedEvent, CurveDeletedEvent, PathsChangedEvent},
+        CommandHistory, PathManager, TerrainData, WallManager,
+    },
+    systems::mode_manager::ctrl_pressed,
+    utils::scene_file::*,
+};
+
+pub const SCENE_FILE_PATH: &str = "scene.json";
+
+// Ctrl+S
+pub fn save_scene(
+    keys: Res<Input<KeyCode>>,
+    wall_manager: Res<WallManager>,
+    path_manager: Res<PathManager>,
+    terrain: Res<TerrainData>,
+) {
+    if !(ctrl_pressed(&keys) && keys.just_pressed(KeyCode::S)) {
+        return;
+    }
+
+    puffin::profile_function!();
+
+    // Sort by index, so that walls are restored in the order they were drawn
+    let mut indices: Vec<_> = wall_manager.walls.keys().collect();
+    indices.sort();
+
+    let mut path_indices: Vec<_> = path_manager.paths.keys().collect();
+    path_indices.sort();
+
+    let scene = SceneFile {
+        walls: indices
+            .iter()
+            .map(|i| SceneWall {
+                points: match &wall_manager.walls[i].spline {
+                    Some(spline) => spline.control_points.clone(),
+                    None => wall_manager.walls[i].curve.points.clone(),
+                },
+                spline: wall_manager.walls[i].spline.is_some(),
+                style: wall_manager.walls[i].style,
+                seed: wall_manager.walls[i].seed,
+                start_offset: wall_manager.walls[i].start_offset,
+                openings: wall_manager.walls[i].openings.clone(),
+            })
+            .collect(),
+        paths: path_indices
+            .iter()
+            .map(|i| ScenePath {
+                points: path_manager.paths[i].curve.points.clone(),
+                width: path_manager.paths[i].width,
+            })
+            .collect(),
+        terrain: SceneTerrain {
+            settings: terrain.settings,
+            offset: terrain.offset,
+            tiles: terrain.edited_tiles(),
+        },
+    };
+
+    match scene.save(SCENE_FILE_PATH) {
+        Ok(()) => log::info!("Scene saved to {}", SCENE_FILE_PATH),
+        Err(err) => log::error!("{}", err),
+    }
+}
+
+// Ctrl+L
+#[allow(clippy::too_many_arguments)]
+pub fn load_scene(
+    keys: Res<Input<KeyCode>>,
+    mut wall_manager: ResMut<WallManager>,
+    mut path_manager: ResMut<PathManager>,
+    mut terrain: ResMut<TerrainData>,
+    window: Res<ChunkWindow>,
+    mut history: ResMut<CommandHistory>,
+    mut ev_curve_changed: EventWriter<CurveChangedEvent>,
+    mut ev_curve_deleted: EventWriter<CurveDeletedEvent>,
+    mut ev_paths_changed: EventWriter<PathsChangedEvent>,
+) {
+    if !(ctrl_pressed(&keys) && keys.just_pressed(KeyCode::L)) {
+        return;
+    }
+
+    puffin::profile_function!();
+
+    let scene = match SceneFile::load(SCENE_FILE_PATH) {
+        Ok(scene) => scene,
+        Err(err) => {
+            log::error!("{}", err);
+            return;
+        }
+    };
+
+    // Remove the current walls, same as `clear_canvas`
+    for k in wall_manager.walls.keys() {
+        ev_curve_deleted.send(CurveDeletedEvent { curve_index: *k });
+    }
+
+    // Curves are projected onto the loaded terrain, scenes from before walls followed it have flat curves
+    terrain.set_settings(scene.terrain.settings);
+    terrain.offset = scene.terrain.offset;
+    terrain.recalculate_texture(&window);
+    terrain.set_edited_tiles(&window, scene.terrain.tiles);
+
+    // Rebuild walls under new indices, `walls_update` will construct their bricks and shadows
+    for wall in scene.walls {
+        let curve = terrain.project(&Curve::from(wall.points));
+        if curve.points.len() < 2 || curve.length <= 0.0 {
+            log::warn!("Scene file: skipping a degenerate wall");
+            continue;
+        }
+
+        let index = wall_manager.new_wall(curve, wall.style, wall.seed);
+        if let Some(new_wall) = wall_manager.get_mut(index) {
+            new_wall.openings = wall.openings;
+            new_wall.start_offset = wall.start_offset;
+            if wall.spline {
+                new_wall.set_spline(&SplineCurve::from(new_wall.curve.points.clone()), &terrain);
+            }
+        }
+        ev_curve_changed.send(CurveChangedEvent { curve_index: index });
+    }
+
+    path_manager.clear();
+    for path in scene.paths {
+        let curve = terrain.project(&Curve::from(path.points));
+        if curve.points.len() < 2 || curve.length <= 0.0 {
+            log::warn!("Scene file: skipping a degenerate path");
+            continue;
+        }
+
+        path_manager.new_path(curve, path.width);
+    }
+    ev_paths_changed.send(PathsChangedEvent);
+
+    // Strokes recorded so far refer to walls that don't exist anymore
+    history.clear();
+
+    log::info!("Scene loaded from {}", SCENE_FILE_PATH);
+}
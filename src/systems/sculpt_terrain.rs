@@ -0,0 +1,102 @@
+use bevy_app::{EventReader, EventWriter};
+use bevy_core::Time;
+use bevy_ecs::prelude::*;
+use bevy_input::{
+    keyboard::KeyCode,
+    mouse::{MouseButton, MouseWheel},
+    Input,
+};
+use glam::Vec2;
+
+use crate::{
+    components::CursorRaycast,
+    geometry::chunks::ChunkWindow,
+    resources::{events::TerrainChangedEvent, TerrainData, TerrainOp},
+    systems::mode_manager::{ctrl_pressed, BrushMode},
+};
+
+// Radius of the brush circle preview mesh at scale 1
+pub const BRUSH_CIRCLE_RADIUS: f32 = 0.75 * 0.9;
+
+const TERRAIN_BRUSH_RADIUS_DEFAULT: f32 = 2.0;
+const TERRAIN_BRUSH_RADIUS_MIN: f32 = 0.3;
+const TERRAIN_BRUSH_RADIUS_MAX: f32 = 10.0;
+// radius is multiplied by this for every step of the mouse wheel
+const TERRAIN_BRUSH_RADIUS_STEP: f32 = 1.15;
+
+// meters per second at the center of the brush
+const RAISE_SPEED: f32 = 0.8;
+// how fast smoothing and flattening pull the heights, per second
+const BLEND_SPEED: f32 = 3.0;
+
+pub struct TerrainBrush {
+    pub radius: f32,
+    // height under the cursor when the stroke started
+    pub flatten_to: f32,
+}
+
+impl TerrainBrush {
+    pub fn new() -> Self {
+        Self {
+            radius: TERRAIN_BRUSH_RADIUS_DEFAULT,
+            flatten_to: 0.0,
+        }
+    }
+}
+
+// Raises, lowers, smooths or flattens the terrain while LMB is held, Ctrl + mouse wheel changes the radius
+#[allow(clippy::too_many_arguments)]
+pub fn sculpt_terrain(
+    mode: Res<BrushMode>,
+    mut brush: ResMut<TerrainBrush>,
+    mut terrain: ResMut<TerrainData>,
+    window: Res<ChunkWindow>,
+    mut ev_terrain_changed: EventWriter<TerrainChangedEvent>,
+    mut mouse_wheel_ev: EventReader<MouseWheel>,
+    keys: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    cursor_ws: Res<CursorRaycast>,
+    mouse_button_input: Res<Input<MouseButton>>,
+) {
+    let op = match *mode {
+        BrushMode::Terrain(op) => op,
+        _ => return,
+    };
+
+    if ctrl_pressed(&keys) {
+        let steps: f32 = mouse_wheel_ev.iter().map(|ev| ev.y).sum();
+        if steps != 0.0 {
+            brush.radius = (brush.radius * TERRAIN_BRUSH_RADIUS_STEP.powf(steps))
+                .clamp(TERRAIN_BRUSH_RADIUS_MIN, TERRAIN_BRUSH_RADIUS_MAX);
+        }
+    }
+
+    let center = Vec2::new(cursor_ws.0.x, cursor_ws.0.z);
+    if mouse_button_input.just_pressed(MouseButton::Left) {
+        brush.flatten_to = terrain.height_at(center.x, center.y);
+    }
+
+    if !mouse_button_input.pressed(MouseButton::Left) {
+        return;
+    }
+
+    puffin::profile_function!();
+
+    let speed = match op {
+        TerrainOp::Raise | TerrainOp::Lower => RAISE_SPEED,
+        TerrainOp::Smooth | TerrainOp::Flatten => BLEND_SPEED,
+    };
+
+    let changed = terrain.sculpt(
+        op,
+        center,
+        brush.radius,
+        speed * time.delta_seconds(),
+        brush.flatten_to,
+    );
+
+    if !changed.is_empty() {
+        terrain.upload_tiles(&window, &changed);
+        ev_terrain_changed.send(TerrainChangedEvent);
+    }
+}
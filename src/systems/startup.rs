@@ -3,10 +3,11 @@ use bevy_ecs::{component::Component, prelude::*};
 use crate::asset_libraries::Handle;
 use crate::components::*;
 use crate::systems::brush_preview::BrushPreview;
+use crate::systems::mode_manager::EraseLayer;
 use crate::systems::signifiers::SignfierContinueWall;
 use crate::utils::load_json::load_json_as_mesh;
 
-use crate::geometry::plane::Plane;
+use crate::geometry::{chunks::ChunkWindow, plane::Plane};
 use crate::{
     asset_libraries::{mesh_library::AssetMeshLibrary, shader_library::AssetShaderLibrary, Asset},
     render::{mesh::Mesh, shader::ShaderProgram, shaderwatch::ShaderWatch},
@@ -50,6 +51,13 @@ pub fn startup(ecs: &mut World) {
         "brush_circle",
         ecs,
     );
+    let brush_circle_terrain = load_mesh_into_library(
+        load_json_as_mesh("meshes/brush_circle.json")
+            .unwrap()
+            .add_color_self([0.35, 0.45, 0.2]),
+        "brush_circle_terrain",
+        ecs,
+    );
     let brush_circle_cross = load_mesh_into_library(
         load_json_as_mesh("meshes/brush_circle_cross.json")
             .unwrap()
@@ -57,6 +65,21 @@ pub fn startup(ecs: &mut World) {
         "brush_circle_cross",
         ecs,
     );
+    // erasers that only remove walls or paths are tinted like what they remove
+    let brush_circle_cross_wall = load_mesh_into_library(
+        load_json_as_mesh("meshes/brush_circle_cross.json")
+            .unwrap()
+            .add_color_self([0.55, 0.2, 0.1]),
+        "brush_circle_cross_wall",
+        ecs,
+    );
+    let brush_circle_cross_path = load_mesh_into_library(
+        load_json_as_mesh("meshes/brush_circle_cross.json")
+            .unwrap()
+            .add_color_self([0.75, 0.65, 0.4]),
+        "brush_circle_cross_path",
+        ecs,
+    );
 
     //let mut terrain_test = load_json_as_mesh("meshes/plane.json").unwrap();
     //terrain_test.add_color([0.35; 3]);
@@ -85,7 +108,7 @@ pub fn startup(ecs: &mut World) {
     //    "terrain_shader",
     //    ecs,
     //);
-    // this shader shows the compute_path_mask.comp as a texture
+    // this shader shows the path mask as a texture
     let _test = load_shader_into_library(
         "shaders/texture_test.vert",
         "shaders/texture_test.frag",
@@ -128,13 +151,18 @@ pub fn startup(ecs: &mut World) {
         transform: Transform::identity(),
     });
 
-    ecs.spawn()
-        .insert_bundle(DrawableMeshBundle {
-            mesh: road_pebbles,
-            shader: road_shader,
-            transform: Transform::identity(),
-        })
-        .insert(RoadComponent);
+    // the pebbles cover a single chunk, there's one for every chunk of the window, see `stream_chunks`
+    let window = *ecs.get_resource::<ChunkWindow>().unwrap();
+    for coord in window.chunks() {
+        let center = coord.center();
+        ecs.spawn()
+            .insert_bundle(DrawableMeshBundle {
+                mesh: road_pebbles,
+                shader: road_shader,
+                transform: Transform::from_translation(glam::Vec3::new(center.x, 0.0, center.y)),
+            })
+            .insert(RoadComponent);
+    }
 
     /*
     ecs.spawn().insert_bundle(DrawableMeshBundle {
@@ -176,10 +204,22 @@ pub fn startup(ecs: &mut World) {
         .insert(BrushPreview::Path)
         .insert(FollowMouse);
 
+    for (mesh, layer) in [
+        (brush_circle_cross, EraseLayer::All),
+        (brush_circle_cross_wall, EraseLayer::Wall),
+        (brush_circle_cross_path, EraseLayer::Path),
+    ] {
+        ecs.spawn()
+            .insert(mesh)
+            .insert(vert_color)
+            .insert(BrushPreview::Eraser(layer))
+            .insert(FollowMouse);
+    }
+
     ecs.spawn()
-        .insert(brush_circle_cross)
+        .insert(brush_circle_terrain)
         .insert(vert_color)
-        .insert(BrushPreview::Eraser)
+        .insert(BrushPreview::Terrain)
         .insert(FollowMouse);
 
     // signifiers
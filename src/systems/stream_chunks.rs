@@ -0,0 +1,43 @@
+use bevy_ecs::prelude::*;
+use dolly::prelude::Position;
+use glam::Vec3;
+
+use crate::{
+    components::{RoadComponent, Transform},
+    geometry::chunks::{ChunkCoord, ChunkWindow},
+    render::camera::MainCamera,
+    resources::{PathMask, TerrainData},
+};
+
+// Chunks kept on the GPU around the one the camera looks at, in every direction
+pub const CHUNK_WINDOW_RADIUS: i32 = 1;
+
+// Moves the chunk window along with the camera, terrain and path mask tiles are streamed into it.
+// Uploads textures, so it has to run in a single-threaded stage
+pub fn stream_chunks(
+    mut main_camera: ResMut<MainCamera>,
+    mut window: ResMut<ChunkWindow>,
+    mut terrain: ResMut<TerrainData>,
+    mut path_mask: ResMut<PathMask>,
+    mut roads: Query<&mut Transform, With<RoadComponent>>,
+) {
+    // point the camera orbits around
+    let target = main_camera.camera_rig.driver_mut::<Position>().position;
+    let center = ChunkCoord::of(Vec3::new(target.x, target.y, target.z));
+    if center == window.center {
+        return;
+    }
+
+    puffin::profile_function!();
+
+    window.center = center;
+    log::debug!("Chunk window moved to {:?}", center);
+
+    terrain.upload_window(&window);
+    path_mask.upload_window(&window);
+
+    for (mut transform, coord) in roads.iter_mut().zip(window.chunks()) {
+        let center = coord.center();
+        transform.translation = Vec3::new(center.x, 0.0, center.y);
+    }
+}
@@ -1,24 +1,124 @@
+use bevy_app::{EventReader, EventWriter};
 use bevy_ecs::prelude::*;
 use bevy_input::{keyboard::KeyCode, Input};
 use glam::Vec2;
 
-use crate::TerrainData;
+use crate::{
+    geometry::chunks::ChunkWindow,
+    resources::{
+        events::{CurveChangedEvent, TerrainChangedEvent},
+        wall_manager::WallManager,
+        PathManager, TerrainPresets, TERRAIN_PRESETS_PATH,
+    },
+    systems::mode_manager::ctrl_pressed,
+    TerrainData,
+};
 
-pub fn update_terrain(mut terrain: ResMut<TerrainData>, keys: Res<Input<KeyCode>>) {
+pub fn update_terrain(
+    mut terrain: ResMut<TerrainData>,
+    window: Res<ChunkWindow>,
+    keys: Res<Input<KeyCode>>,
+    mut ev_terrain_changed: EventWriter<TerrainChangedEvent>,
+) {
     puffin::profile_function!();
 
+    let mut changed = false;
+
     if keys.pressed(KeyCode::Space) {
         terrain.offset += Vec2::new(0.06, 0.06);
-        terrain.recalculate_texture();
+        changed = true;
     }
 
     if keys.pressed(KeyCode::Q) {
-        terrain.amp += 0.03;
-        terrain.recalculate_texture();
+        terrain.settings.amplitude += 0.03;
+        changed = true;
     }
 
     if keys.pressed(KeyCode::E) {
-        terrain.amp -= 0.03;
-        terrain.recalculate_texture();
+        terrain.settings.amplitude -= 0.03;
+        changed = true;
+    }
+
+    if changed {
+        warn_if_edited(&terrain);
+        terrain.recalculate_texture(&window);
+        ev_terrain_changed.send(TerrainChangedEvent);
+    }
+}
+
+// P switches to the next terrain preset, Ctrl+P reloads the presets file,
+// Ctrl+U saves the current settings (e.g. after Q or E) over the active preset
+pub fn terrain_preset_manager(
+    keys: Res<Input<KeyCode>>,
+    mut presets: ResMut<TerrainPresets>,
+    mut terrain: ResMut<TerrainData>,
+    window: Res<ChunkWindow>,
+    mut ev_terrain_changed: EventWriter<TerrainChangedEvent>,
+) {
+    if keys.just_pressed(KeyCode::U) && ctrl_pressed(&keys) {
+        match presets.save_active(terrain.settings) {
+            Ok(()) => log::info!(
+                "Terrain preset '{}' saved to {}",
+                presets.active_name(),
+                TERRAIN_PRESETS_PATH
+            ),
+            Err(err) => log::error!("{}", err),
+        }
+        return;
+    }
+
+    if !keys.just_pressed(KeyCode::P) {
+        return;
+    }
+
+    if ctrl_pressed(&keys) {
+        presets.reload();
+    } else {
+        presets.cycle();
+    }
+
+    log::info!("Terrain preset: {}", presets.active_name());
+
+    terrain.set_settings(presets.active_settings());
+    warn_if_edited(&terrain);
+    terrain.recalculate_texture(&window);
+    ev_terrain_changed.send(TerrainChangedEvent);
+}
+
+// Generating the terrain again drops the heights that were sculpted or imported, and that can't be undone
+fn warn_if_edited(terrain: &TerrainData) {
+    if terrain.has_edits() {
+        log::warn!("Terrain: generated again, the sculpted and imported heights are discarded. Load a saved scene (Ctrl+L) to get them back");
+    }
+}
+
+// Curves store points on the terrain, so they are projected again whenever the terrain changes.
+// This isn't recorded in the history, undo projects the curves it restores instead
+pub fn conform_to_terrain(
+    mut ev_terrain_changed: EventReader<TerrainChangedEvent>,
+    terrain: Res<TerrainData>,
+    mut wall_manager: ResMut<WallManager>,
+    mut path_manager: ResMut<PathManager>,
+    mut ev_curve_changed: EventWriter<CurveChangedEvent>,
+) {
+    if ev_terrain_changed.iter().count() == 0 {
+        return;
+    }
+
+    puffin::profile_function!();
+
+    // sculpting only changes the terrain around the brush, walls elsewhere don't have to be constructed again
+    for (index, wall) in wall_manager.walls.iter_mut() {
+        let before = wall.curve.points.clone();
+        wall.project(&terrain);
+        if wall.curve.points != before {
+            ev_curve_changed.send(CurveChangedEvent {
+                curve_index: *index,
+            });
+        }
+    }
+
+    for path in path_manager.paths.values_mut() {
+        path.curve = terrain.project(&path.curve);
     }
 }
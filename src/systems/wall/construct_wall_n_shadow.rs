@@ -1,17 +1,17 @@
+use std::collections::HashSet;
+
 use bevy_app::EventReader;
 use bevy_ecs::prelude::*;
 
 use crate::{
     asset_libraries::{mesh_library::AssetMeshLibrary, shader_library::AssetShaderLibrary, Handle},
     components::{drawable::DrawableMeshBundle, transform::Transform},
-    geometry::{instanced_wall::*, shadow_decal::ShadowDecal, wall_constructor::*},
+    geometry::{instanced_wall::*, shadow_decal::ShadowDecal},
     render::mesh::Mesh,
     resources::{events::CurveChangedEvent, WallManager},
-    systems::mode_manager::{BrushMode, EraseLayer},
 };
 
 pub fn walls_update(
-    _mode: Res<BrushMode>,
     mut ev_curve_changed: EventReader<CurveChangedEvent>,
 
     mut wall_manager: ResMut<WallManager>,
@@ -21,17 +21,35 @@ pub fn walls_update(
     assets_shader: Res<AssetShaderLibrary>,
     mut commands: Commands,
 ) {
-    if !matches!(*_mode, BrushMode::Wall) && !matches!(*_mode, BrushMode::Eraser(EraseLayer::All)) {
+    puffin::profile_function!();
+
+    let changed: HashSet<usize> = ev_curve_changed.iter().map(|ev| ev.curve_index).collect();
+    if changed.is_empty() {
         return;
     }
 
-    puffin::profile_function!();
+    // walls whose junctions changed have to be constructed again as well
+    let affected = {
+        puffin::profile_scope!("junctions");
+        wall_manager.update_junctions(&changed)
+    };
 
-    for ev in ev_curve_changed.iter() {
-        let changed_wall = wall_manager.get_mut(ev.curve_index).expect(&format!(
-            "Wall construction failed: couldn't get Wall index {}",
-            ev.curve_index,
-        ));
+    let mut to_construct: Vec<usize> = changed.union(&affected).copied().collect();
+    to_construct.sort_unstable();
+
+    for index in to_construct {
+        let joints = wall_manager.junctions.joints(index);
+        let layout = wall_manager.layout(index);
+        let (changed_wall, layout) = match (wall_manager.get_mut(index), layout) {
+            (Some(wall), Some(layout)) => (wall, layout),
+            _ => {
+                log::error!(
+                    "Wall construction failed: couldn't get Wall index {}",
+                    index
+                );
+                continue;
+            }
+        };
 
         if changed_wall.curve.points.len() < 2 {
             continue;
@@ -40,23 +58,29 @@ pub fn walls_update(
         // Calculate brick transforms
         {
             puffin::profile_scope!("construct wall");
-            let bricks = WallConstructor::from_curve(&changed_wall.curve);
-
-            if bricks.is_empty() {
-                log::warn!("WallConstructor returned empty wall");
-            }
 
             if let Some(wall_entity) = changed_wall.wall_entity {
                 // update the wall
                 let mut wall_component = query.get_mut(wall_entity).unwrap();
-                wall_component.update(changed_wall.curve.length, bricks);
+                wall_component.update(
+                    changed_wall.shape(),
+                    &changed_wall.style,
+                    &layout,
+                    &joints,
+                    &changed_wall.openings,
+                );
             } else {
                 //create a wall
                 log::info!("creating wall..");
 
                 changed_wall.wall_entity = Some(create_wall(
-                    changed_wall.curve.length,
-                    bricks,
+                    InstancedWall::from(
+                        changed_wall.shape(),
+                        &changed_wall.style,
+                        &layout,
+                        &joints,
+                        &changed_wall.openings,
+                    ),
                     &assets_mesh,
                     &assets_shader,
                     &mut commands,
@@ -69,10 +93,10 @@ pub fn walls_update(
             if let Some(shadow_entity) = changed_wall.shadow_entity {
                 let (_shadow_component, mesh_handle) = query3.get_mut(shadow_entity).unwrap();
                 let mesh = assets_mesh.get_mut(*mesh_handle).unwrap();
-                ShadowDecal::update(&changed_wall.curve, mesh);
+                ShadowDecal::update(changed_wall.shape(), mesh);
             } else {
-                changed_wall.shadow_entity = Some(ShadowDecal::new(
-                    &changed_wall.curve,
+                changed_wall.shadow_entity = Some(ShadowDecal::spawn(
+                    changed_wall.shape(),
                     &mut assets_mesh,
                     &assets_shader,
                     &mut commands,
@@ -83,13 +107,11 @@ pub fn walls_update(
 }
 
 fn create_wall(
-    curve_length: f32,
-    bricks: Vec<Brick>,
+    wall_component: InstancedWall,
     assets_mesh: &ResMut<AssetMeshLibrary>,
     assets_shader: &Res<AssetShaderLibrary>,
     commands: &mut Commands,
 ) -> Entity {
-    let wall_component = InstancedWall::from(curve_length, bricks);
     let brick_mesh_handle = assets_mesh.get_handle_by_name("brick").unwrap();
 
     commands
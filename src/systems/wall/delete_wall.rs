@@ -1,16 +1,31 @@
-use bevy_app::EventReader;
+use bevy_app::{EventReader, EventWriter};
 use bevy_ecs::prelude::*;
 
-use crate::resources::{events::CurveDeletedEvent, wall_manager::WallManager};
+use crate::resources::{
+    events::{CurveChangedEvent, CurveDeletedEvent},
+    wall_manager::WallManager,
+};
 
 pub fn delete_wall(
     mut ev_curve_deleted: EventReader<CurveDeletedEvent>,
+    mut ev_curve_changed: EventWriter<CurveChangedEvent>,
     mut wall_manager: ResMut<WallManager>,
 
     mut commands: Commands,
 ) {
+    let mut joined = Vec::new();
     for ev in ev_curve_deleted.iter() {
         log::debug!("Wall index {} entry has been removed", ev.curve_index);
         wall_manager.remove_entry(ev.curve_index, &mut commands);
+        joined.extend(wall_manager.junctions.joined_to(ev.curve_index));
+    }
+
+    // walls that were joined to a removed wall lose their junction
+    joined.sort_unstable();
+    joined.dedup();
+    for index in joined {
+        if wall_manager.get(index).is_some() {
+            ev_curve_changed.send(CurveChangedEvent { curve_index: index });
+        }
     }
 }
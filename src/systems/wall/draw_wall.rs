@@ -3,19 +3,26 @@ use bevy_ecs::prelude::*;
 use bevy_input::{mouse::MouseButton, Input};
 
 use crate::{
-    geometry::curve::Curve,
-    resources::{events::CurveChangedEvent, wall_manager::*},
-    systems::mode_manager::BrushMode,
+    geometry::{
+        curve::Curve,
+        spline_curve::{SplineCurve, SPLINE_CONTROL_POINT_SPACING},
+    },
+    resources::{events::CurveChangedEvent, wall_manager::*, TerrainData, WallStyles},
+    systems::mode_manager::{BrushMode, StrokeFitting},
     CursorRaycast,
 };
 
 pub const CONTINUE_CURVE_DIST_THRESHOLD: f32 = 0.2;
 
+#[allow(clippy::too_many_arguments)]
 pub fn draw_wall(
     _mode: Res<BrushMode>,
+    stroke_fitting: Res<StrokeFitting>,
 
     mut ev_curve_changed: EventWriter<CurveChangedEvent>,
     mut wall_manager: ResMut<WallManager>,
+    wall_styles: Res<WallStyles>,
+    terrain: Res<TerrainData>,
     cursor_ws: Res<CursorRaycast>,
 
     mouse_button_input: Res<Input<MouseButton>>,
@@ -24,9 +31,9 @@ pub fn draw_wall(
         return;
     }
 
-    // Remove y component from the cursor-terrain raycast position
+    // Snap the cursor-terrain raycast position onto the terrain, the raymarch stops a bit above it
     let mut cursor_ws = cursor_ws.0;
-    cursor_ws.y = 0.0;
+    cursor_ws.y = terrain.height_at(cursor_ws.x, cursor_ws.z);
 
     puffin::profile_function!();
     // If LMB was just pressed, start a new curve
@@ -50,11 +57,22 @@ pub fn draw_wall(
         }
 
         let index = if let Some((idx, curve, mode)) = continue_curve {
-            let temp_curve = curve.resample(0.05); // resample to prevent deflation of the curve, when we start drawing
-            wall_manager.temp_curve = Some(InProgressCurve::new(temp_curve, idx, mode));
+            let start = match mode {
+                AddPointsTo::End => None,
+                AddPointsTo::Beginning => {
+                    Some((curve.points[0], wall_manager.walls[&idx].start_offset))
+                }
+            };
+            let temp_curve = match *stroke_fitting {
+                StrokeFitting::Smooth => curve.resample(0.05), // resample to prevent deflation of the curve, when we start drawing
+                StrokeFitting::Spline => curve,                // splines don't deflate
+            };
+            let mut in_progress = InProgressCurve::new(temp_curve, idx, mode);
+            in_progress.start = start;
+            wall_manager.temp_curve = Some(in_progress);
             idx
         } else {
-            wall_manager.new_wall(Curve::new())
+            wall_manager.new_wall(Curve::new(), wall_styles.active_style(), fastrand::u64(..))
         };
 
         ev_curve_changed.send(CurveChangedEvent { curve_index: index });
@@ -65,6 +83,7 @@ pub fn draw_wall(
 
         let draw_mode = &temp_curve.mode;
         let active_curve_index = temp_curve.index;
+        let start = temp_curve.start;
         let active_curve = &mut temp_curve.curve;
 
         let intersection = cursor_ws;
@@ -97,10 +116,28 @@ pub fn draw_wall(
             }
 
             if active_curve.points.len() > 2 {
-                let clone_temp_curve = active_curve.clone();
-                wall_manager.get_mut(active_curve_index).unwrap().curve = clone_temp_curve
-                    .smooth(SMOOTHING_STEPS)
-                    .resample(RESAMPLING);
+                let stroke = active_curve.clone();
+                let wall = wall_manager.get_mut(active_curve_index).unwrap();
+                // smoothing and fitting move points off the terrain
+                match *stroke_fitting {
+                    StrokeFitting::Smooth => {
+                        let smoothed = stroke.smooth(SMOOTHING_STEPS).resample(RESAMPLING);
+                        wall.curve = terrain.project(&smoothed);
+                        wall.spline = None;
+                    }
+                    StrokeFitting::Spline => wall.set_spline(
+                        &SplineCurve::fit(&stroke.points, SPLINE_CONTROL_POINT_SPACING),
+                        &terrain,
+                    ),
+                }
+                // bricks are counted from before the part drawn in front of the wall
+                if let Some((first_point, start_offset)) = start {
+                    let added = wall
+                        .curve
+                        .closest_point(first_point)
+                        .map_or(0.0, |c| c.distance);
+                    wall.start_offset = start_offset + added;
+                }
             }
 
             ev_curve_changed.send(CurveChangedEvent {
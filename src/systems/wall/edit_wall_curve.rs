@@ -0,0 +1,284 @@
+use bevy_app::EventWriter;
+use bevy_ecs::prelude::*;
+use bevy_input::{mouse::MouseButton, Input};
+use glam::Vec3;
+
+use crate::{
+    asset_libraries::{mesh_library::AssetMeshLibrary, shader_library::AssetShaderLibrary, Handle},
+    components::CursorRaycast,
+    geometry::curve::Curve,
+    render::mesh::Mesh,
+    resources::{
+        events::CurveChangedEvent, history::*, wall_manager::RESAMPLING, TerrainData, WallManager,
+    },
+    systems::curve_preview::new_line_entity,
+};
+
+// Every this many curve points has a handle, besides the last one
+const HANDLE_EVERY: usize = 5;
+// How far from the cursor a handle can be picked, in XZ
+pub const HANDLE_PICK_DISTANCE: f32 = 0.3;
+// Dragging a handle moves the curve this far along it in both directions, less and less further from the handle
+const DRAG_RADIUS: f32 = 2.0;
+const DRAG_SMOOTHING_STEPS: usize = 10;
+
+// Handles are pins from the curve on the ground to a bit above the wall, with a cross at their foot
+const HANDLE_CROSS_SIZE: f32 = 0.08;
+const HANDLE_ABOVE_WALL: f32 = 0.15;
+const HANDLE_COLOR: [f32; 3] = [1.0, 0.6, 0.15];
+const HANDLE_DRAGGED_COLOR: [f32; 3] = [1.0, 1.0, 1.0];
+
+struct HandleDrag {
+    wall: usize,
+    point: usize,
+    // the curve is dragged from this one every frame, and the whole drag is recorded as one change
+    before: WallState,
+}
+
+// Control points of the selected wall, see `drag_wall_handle`
+pub struct WallCurveEdit {
+    dragging: Option<HandleDrag>,
+    handles: Option<(Entity, Handle<Mesh>)>,
+    // what the handles mesh was built from
+    shown: Option<(usize, Vec<Vec3>, Option<usize>)>,
+}
+
+impl WallCurveEdit {
+    pub fn new() -> Self {
+        Self {
+            dragging: None,
+            handles: None,
+            shown: None,
+        }
+    }
+
+    // Starts dragging the handle of the selected wall under the cursor, returns false if there's none
+    pub fn begin_drag(&mut self, wall_manager: &WallManager, cursor: Vec3) -> bool {
+        let index = match wall_manager.selected {
+            Some(index) => index,
+            None => return false,
+        };
+        let wall = wall_manager.get(index).unwrap();
+
+        let closest = handle_points(&wall.curve)
+            .into_iter()
+            .map(|i| (i, distance_xz(wall.curve.points[i], cursor)))
+            .filter(|(_, distance)| *distance <= HANDLE_PICK_DISTANCE)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+        match closest {
+            Some((point, _)) => {
+                self.dragging = Some(HandleDrag {
+                    wall: index,
+                    point,
+                    before: WallState::of(wall),
+                });
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// While LMB is held, the dragged handle follows the cursor over the terrain and pulls the curve around it along.
+// When it's released, the curve is resampled and the drag is recorded
+pub fn drag_wall_handle(
+    mouse_button_input: Res<Input<MouseButton>>,
+    cursor_ws: Res<CursorRaycast>,
+    terrain: Res<TerrainData>,
+    mut curve_edit: ResMut<WallCurveEdit>,
+    mut wall_manager: ResMut<WallManager>,
+    mut history: ResMut<CommandHistory>,
+    mut ev_curve_changed: EventWriter<CurveChangedEvent>,
+) {
+    let drag = match &curve_edit.dragging {
+        Some(drag) => drag,
+        None => return,
+    };
+
+    let index = drag.wall;
+    let wall = match wall_manager.get_mut(index) {
+        Some(wall) => wall,
+        None => {
+            curve_edit.dragging = None;
+            return;
+        }
+    };
+
+    if mouse_button_input.pressed(MouseButton::Left) {
+        let curve = terrain.project(&drag_curve(&drag.before.curve, drag.point, cursor_ws.0));
+        if curve.points != wall.curve.points {
+            wall.curve = curve;
+            // handles move the points of the polyline, the wall isn't built along its spline anymore
+            wall.spline = None;
+            ev_curve_changed.send(CurveChangedEvent { curve_index: index });
+        }
+        return;
+    }
+
+    let before = drag.before.clone();
+    curve_edit.dragging = None;
+    if wall.curve.points == before.curve.points {
+        return;
+    }
+
+    // stretched segments get back to the usual spacing
+    wall.curve = terrain.project(&wall.curve.clone().resample(RESAMPLING));
+    history.push(Stroke {
+        walls: vec![WallChange {
+            index,
+            before: Some(before),
+            after: Some(WallState::of(wall)),
+        }],
+        paths: Vec::new(),
+    });
+
+    ev_curve_changed.send(CurveChangedEvent { curve_index: index });
+}
+
+// Shows the handles of the selected wall
+pub fn wall_handles_preview(
+    wall_manager: Res<WallManager>,
+    mut curve_edit: ResMut<WallCurveEdit>,
+    mut assets_mesh: ResMut<AssetMeshLibrary>,
+    assets_shader: Res<AssetShaderLibrary>,
+    mut commands: Commands,
+) {
+    let selected = wall_manager
+        .selected
+        .and_then(|index| wall_manager.get(index).map(|wall| (index, wall)));
+
+    let (index, wall) = match selected {
+        Some(selected) => selected,
+        None => {
+            // the mesh is deleted along with the entity, see `TransientMesh`
+            if let Some((ent, _)) = curve_edit.handles.take() {
+                commands.entity(ent).despawn();
+            }
+            curve_edit.shown = None;
+            return;
+        }
+    };
+
+    let dragged = curve_edit.dragging.as_ref().map(|drag| drag.point);
+    if let Some((shown_index, shown_points, shown_dragged)) = &curve_edit.shown {
+        if *shown_index == index && *shown_points == wall.curve.points && *shown_dragged == dragged
+        {
+            return;
+        }
+    }
+
+    let mesh = handles_mesh(&wall.curve, wall.style.wall_height, dragged);
+    match curve_edit.handles {
+        Some((_, mesh_handle)) => *assets_mesh.get_mut(mesh_handle).unwrap() = mesh,
+        None => {
+            let mesh_handle = assets_mesh.add(mesh.into());
+            let ent = new_line_entity(mesh_handle, gl::LINES, &assets_shader, &mut commands);
+            curve_edit.handles = Some((ent, mesh_handle));
+        }
+    }
+    curve_edit.shown = Some((index, wall.curve.points.clone(), dragged));
+}
+
+// Indices of the curve points that have a handle
+fn handle_points(curve: &Curve) -> Vec<usize> {
+    let mut points: Vec<usize> = (0..curve.points.len()).step_by(HANDLE_EVERY).collect();
+    let last = curve.points.len().saturating_sub(1);
+    if !points.is_empty() && points.last() != Some(&last) {
+        points.push(last);
+    }
+    points
+}
+
+// Moves `point` under `target` in XZ, and the points around it with a smooth falloff along the curve.
+// The moved region is smoothed afterwards, except for the dragged point and the ends of the curve
+fn drag_curve(curve: &Curve, point: usize, target: Vec3) -> Curve {
+    let delta = Vec3::new(
+        target.x - curve.points[point].x,
+        0.0,
+        target.z - curve.points[point].z,
+    );
+
+    let handle_distance = curve.points_u[point] * curve.length;
+    let weights: Vec<f32> = curve
+        .points_u
+        .iter()
+        .map(|u| {
+            let t = 1.0 - (u * curve.length - handle_distance).abs() / DRAG_RADIUS;
+            if t > 0.0 {
+                t * t * (3.0 - 2.0 * t)
+            } else {
+                0.0
+            }
+        })
+        .collect();
+
+    let mut points: Vec<Vec3> = curve
+        .points
+        .iter()
+        .zip(&weights)
+        .map(|(p, weight)| *p + delta * *weight)
+        .collect();
+
+    for _ in 0..DRAG_SMOOTHING_STEPS {
+        let previous = points.clone();
+        for i in 1..points.len().saturating_sub(1) {
+            if i == point {
+                continue;
+            }
+
+            let average = (previous[i - 1] + previous[i + 1]) / 2.0;
+            points[i] += (average - previous[i]) * 0.5 * weights[i];
+        }
+    }
+
+    Curve::from(points)
+}
+
+fn handles_mesh(curve: &Curve, wall_height: f32, dragged: Option<usize>) -> Mesh {
+    let lift = Vec3::Y * (wall_height + HANDLE_ABOVE_WALL);
+
+    let mut positions = Vec::new();
+    let mut colors = Vec::new();
+
+    // the curve, above the wall
+    for segment in curve.points.windows(2) {
+        positions.extend([segment[0] + lift, segment[1] + lift]);
+        colors.extend([HANDLE_COLOR; 2]);
+    }
+
+    for i in handle_points(curve) {
+        let p = curve.points[i];
+        let color = if Some(i) == dragged {
+            HANDLE_DRAGGED_COLOR
+        } else {
+            HANDLE_COLOR
+        };
+
+        positions.extend([
+            p,
+            p + lift,
+            p - Vec3::X * HANDLE_CROSS_SIZE,
+            p + Vec3::X * HANDLE_CROSS_SIZE,
+            p - Vec3::Z * HANDLE_CROSS_SIZE,
+            p + Vec3::Z * HANDLE_CROSS_SIZE,
+        ]);
+        colors.extend([color; 6]);
+    }
+
+    let mut mesh = Mesh::new();
+    mesh.set_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        positions
+            .iter()
+            .map(|p| [p.x, p.y + 0.01, p.z])
+            .collect::<Vec<[f32; 3]>>(),
+    );
+    mesh.set_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh.set_indices((0..positions.len() as u32).collect());
+    mesh
+}
+
+fn distance_xz(a: Vec3, b: Vec3) -> f32 {
+    (a.x - b.x).hypot(a.z - b.z)
+}
@@ -1,9 +1,19 @@
 pub mod construct_wall_n_shadow;
 pub mod delete_wall;
 pub mod draw_wall;
+pub mod edit_wall_curve;
+pub mod place_opening;
+pub mod reroll_wall;
+pub mod select_wall;
 pub mod signifiers;
+pub mod wall_style;
 
 pub use construct_wall_n_shadow::*;
 pub use delete_wall::*;
 pub use draw_wall::*;
+pub use edit_wall_curve::*;
+pub use place_opening::*;
+pub use reroll_wall::*;
+pub use select_wall::*;
 pub use signifiers::*;
+pub use wall_style::*;
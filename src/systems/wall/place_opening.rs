@@ -0,0 +1,107 @@
+use bevy_app::EventWriter;
+use bevy_ecs::prelude::*;
+use bevy_input::{keyboard::KeyCode, mouse::MouseButton, Input};
+use glam::Vec3;
+
+use crate::{
+    components::CursorRaycast,
+    geometry::wall_opening::{WallOpening, MIN_OPENING_WIDTH},
+    resources::{events::CurveChangedEvent, history::*, Wall, WallManager},
+    systems::{mode_manager::*, wall::select_wall::SELECT_PICK_DISTANCE},
+};
+
+// Wall the opening tool's drag started on, and where along it (in u)
+#[derive(Default)]
+pub struct OpeningDrag(Option<(usize, f32)>);
+
+// In opening mode, dragging along a wall with LMB cuts an opening from where the drag started to where it ended,
+// replacing the openings it overlaps. Ctrl on release leaves out the lintel or the posts. Clicking an opening
+// removes it. The change is pushed to the history here, it's made after `history_end_stroke` took its snapshot
+#[allow(clippy::too_many_arguments)]
+pub fn place_opening(
+    mode: Res<BrushMode>,
+    keys: Res<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    cursor_ws: Res<CursorRaycast>,
+    mut wall_manager: ResMut<WallManager>,
+    mut drag: ResMut<OpeningDrag>,
+    mut history: ResMut<CommandHistory>,
+    mut ev_curve_changed: EventWriter<CurveChangedEvent>,
+) {
+    let kind = match *mode {
+        BrushMode::Opening(kind) => kind,
+        _ => {
+            drag.0 = None;
+            return;
+        }
+    };
+
+    if mouse_button_input.just_pressed(MouseButton::Left) {
+        drag.0 = wall_manager
+            .closest_wall(cursor_ws.0, SELECT_PICK_DISTANCE)
+            .and_then(|index| u_at(wall_manager.get(index)?, cursor_ws.0).map(|u| (index, u)));
+    }
+
+    if !mouse_button_input.just_released(MouseButton::Left) {
+        return;
+    }
+
+    let (index, from) = match drag.0.take() {
+        Some(drag) => drag,
+        None => return,
+    };
+    // the wall could have been removed during the drag
+    let wall = match wall_manager.get_mut(index) {
+        Some(wall) => wall,
+        None => return,
+    };
+    let to = u_at(wall, cursor_ws.0).unwrap_or(from);
+    let before = WallState::of(wall);
+
+    if (to - from).abs() * wall.curve.length < MIN_OPENING_WIDTH {
+        let clicked = wall
+            .openings
+            .iter()
+            .position(|o| o.from <= from && from <= o.to);
+        match clicked {
+            Some(i) => {
+                let removed = wall.openings.remove(i);
+                log::info!("Removed a {} from wall {}", removed.kind.name(), index);
+            }
+            None => {
+                log::debug!("Drag along a wall to place an opening");
+                return;
+            }
+        }
+    } else {
+        let mut opening = WallOpening::new(kind, from, to);
+        if ctrl_pressed(&keys) {
+            opening.lintel = false;
+            opening.posts = false;
+        }
+        wall.openings.retain(|o| !o.overlaps(&opening));
+        wall.openings.push(opening);
+        log::info!("Added a {} to wall {}", kind.name(), index);
+    }
+
+    history.push(Stroke {
+        walls: vec![WallChange {
+            index,
+            before: Some(before),
+            after: Some(WallState::of(wall)),
+        }],
+        paths: Vec::new(),
+    });
+
+    ev_curve_changed.send(CurveChangedEvent { curve_index: index });
+}
+
+// Where the point of the wall's curve closest to `pos` is along it, in u
+fn u_at(wall: &Wall, pos: Vec3) -> Option<f32> {
+    if wall.curve.length <= 0.0 {
+        return None;
+    }
+    wall.curve
+        .closest_point(pos)
+        .map(|c| c.distance / wall.curve.length)
+}
@@ -0,0 +1,52 @@
+use bevy_app::EventWriter;
+use bevy_ecs::prelude::*;
+use bevy_input::{keyboard::KeyCode, mouse::MouseButton, Input};
+
+use crate::{
+    components::CursorRaycast,
+    resources::{events::CurveChangedEvent, history::*, WallManager},
+    systems::mode_manager::ctrl_pressed,
+};
+
+// How far from the cursor a wall can be picked
+pub const REROLL_PICK_DISTANCE: f32 = 0.5;
+
+// R gives the selected wall, or the wall under the cursor, a new brick layout
+pub fn reroll_wall(
+    keys: Res<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    cursor_ws: Res<CursorRaycast>,
+    mut wall_manager: ResMut<WallManager>,
+    mut history: ResMut<CommandHistory>,
+    mut ev_curve_changed: EventWriter<CurveChangedEvent>,
+) {
+    if !keys.just_pressed(KeyCode::R)
+        || ctrl_pressed(&keys)
+        || mouse_button_input.pressed(MouseButton::Left)
+    {
+        return;
+    }
+
+    let index = match wall_manager
+        .selected
+        .or_else(|| wall_manager.closest_wall(cursor_ws.0, REROLL_PICK_DISTANCE))
+    {
+        Some(index) => index,
+        None => return,
+    };
+
+    let wall = wall_manager.get_mut(index).unwrap();
+    let before = WallState::of(wall);
+    wall.seed = fastrand::u64(..);
+
+    history.push(Stroke {
+        walls: vec![WallChange {
+            index,
+            before: Some(before),
+            after: Some(WallState::of(wall)),
+        }],
+        paths: Vec::new(),
+    });
+
+    ev_curve_changed.send(CurveChangedEvent { curve_index: index });
+}
@@ -0,0 +1,160 @@
+use bevy_app::EventWriter;
+use bevy_ecs::prelude::*;
+use bevy_input::{keyboard::KeyCode, mouse::MouseButton, Input};
+use glam::Vec3;
+
+use crate::{
+    components::{CursorRaycast, SelectedWall},
+    geometry::{
+        curve::{Curve, WallCurve},
+        spline_curve::SplineCurve,
+    },
+    resources::{
+        events::{CurveChangedEvent, CurveDeletedEvent},
+        history::*,
+        TerrainData, WallManager,
+    },
+    systems::{
+        mode_manager::{ctrl_pressed, BrushMode},
+        wall::edit_wall_curve::WallCurveEdit,
+    },
+};
+
+// How far from the cursor a wall can be picked
+pub const SELECT_PICK_DISTANCE: f32 = 0.5;
+// Duplicates are moved this far to the side of the original, in meters
+const DUPLICATE_OFFSET: f32 = 1.0;
+
+// In select mode, LMB drags the handle of the selected wall under the cursor, or otherwise picks the wall under
+// the cursor, or clears the selection if there's none. The selection is dropped when leaving select mode
+pub fn select_wall(
+    mode: Res<BrushMode>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    cursor_ws: Res<CursorRaycast>,
+    mut wall_manager: ResMut<WallManager>,
+    mut curve_edit: ResMut<WallCurveEdit>,
+) {
+    if !matches!(*mode, BrushMode::Select) {
+        if wall_manager.selected.is_some() {
+            wall_manager.selected = None;
+        }
+        return;
+    }
+
+    if !mouse_button_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    if curve_edit.begin_drag(&wall_manager, cursor_ws.0) {
+        return;
+    }
+
+    wall_manager.selected = wall_manager.closest_wall(cursor_ws.0, SELECT_PICK_DISTANCE);
+    match wall_manager.selected {
+        Some(index) => log::info!("Wall {} selected", index),
+        None => log::debug!("Nothing to select"),
+    }
+}
+
+// Delete removes the selected wall, Ctrl+D duplicates it next to itself and selects the copy.
+// R and T reroll and restyle the selected wall, see `reroll_wall` and `wall_style_manager`
+pub fn edit_selected_wall(
+    keys: Res<Input<KeyCode>>,
+    terrain: Res<TerrainData>,
+    mut wall_manager: ResMut<WallManager>,
+    mut history: ResMut<CommandHistory>,
+    mut ev_curve_changed: EventWriter<CurveChangedEvent>,
+    mut ev_curve_deleted: EventWriter<CurveDeletedEvent>,
+) {
+    let index = match wall_manager.selected {
+        Some(index) => index,
+        None => return,
+    };
+
+    if keys.just_pressed(KeyCode::Delete) {
+        let wall = wall_manager.get(index).unwrap();
+        history.push(Stroke {
+            walls: vec![WallChange {
+                index,
+                before: Some(WallState::of(wall)),
+                after: None,
+            }],
+            paths: Vec::new(),
+        });
+
+        // removed from the manager in `delete_wall`, which also drops the selection
+        ev_curve_deleted.send(CurveDeletedEvent { curve_index: index });
+    } else if keys.just_pressed(KeyCode::D) && ctrl_pressed(&keys) {
+        let wall = wall_manager.get(index).unwrap();
+        let offset = sideways(&wall.curve) * DUPLICATE_OFFSET;
+        let curve = terrain.project(&Curve::from(
+            wall.curve.points.iter().map(|p| *p + offset).collect(),
+        ));
+        let spline = wall.spline.as_ref().map(|spline| {
+            SplineCurve::from(spline.control_points.iter().map(|p| *p + offset).collect())
+        });
+        let style = wall.style;
+        let seed = fastrand::u64(..);
+        let start_offset = wall.start_offset;
+        let openings = wall.openings.clone();
+
+        let duplicate = wall_manager.max_index + 1;
+        wall_manager.insert_wall(duplicate, curve, style, seed);
+        let wall = wall_manager.get_mut(duplicate).unwrap();
+        wall.start_offset = start_offset;
+        wall.openings = openings;
+        if let Some(spline) = spline {
+            wall.set_spline(&spline, &terrain);
+        }
+        history.push(Stroke {
+            walls: vec![WallChange {
+                index: duplicate,
+                before: None,
+                after: Some(WallState::of(wall)),
+            }],
+            paths: Vec::new(),
+        });
+
+        wall_manager.selected = Some(duplicate);
+        log::info!("Wall {} duplicated as {}", index, duplicate);
+        ev_curve_changed.send(CurveChangedEvent {
+            curve_index: duplicate,
+        });
+    }
+}
+
+// Keeps the `SelectedWall` marker on the wall entity of the selection only
+pub fn highlight_selected_wall(
+    wall_manager: Res<WallManager>,
+    query: Query<Entity, With<SelectedWall>>,
+    mut commands: Commands,
+) {
+    // the selected wall has no entity until it's constructed
+    let selected = wall_manager
+        .selected
+        .and_then(|index| wall_manager.get(index))
+        .and_then(|wall| wall.wall_entity);
+
+    for ent in query.iter() {
+        if Some(ent) != selected {
+            commands.entity(ent).remove::<SelectedWall>();
+        }
+    }
+
+    if let Some(ent) = selected {
+        if query.get(ent).is_err() {
+            commands.entity(ent).insert(SelectedWall);
+        }
+    }
+}
+
+// Side of the curve's direction at its middle, in XZ
+fn sideways(curve: &Curve) -> Vec3 {
+    let tangent = curve.get_tangent_at_distance(curve.length * 0.5);
+    let side = tangent.cross(Vec3::Y).normalize_or_zero();
+    if side == Vec3::ZERO {
+        Vec3::X
+    } else {
+        side
+    }
+}
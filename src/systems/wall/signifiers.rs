@@ -3,6 +3,7 @@ use glam::Vec3;
 
 use crate::{
     components::{CursorRaycast, Transform},
+    geometry::curve::WallCurve,
     resources::wall_manager::WallManager,
 };
 
@@ -22,8 +23,8 @@ pub fn signifier_continue_wall(
     for (_, curve) in wall_manager.walls.iter().map(|(i, w)| (i, &w.curve)) {
         if let Some(last_pt) = curve.points.last() {
             if cursor_ws.distance(*last_pt) < CONTINUE_CURVE_DIST_THRESHOLD {
-                if let Some(prev_point) = curve.points.get(curve.points.len() - 2) {
-                    let dir = (*last_pt - *prev_point).normalize();
+                if curve.points.len() >= 2 {
+                    let dir = curve.get_tangent_at_distance(curve.length);
                     signifier_pos = Some(*last_pt + dir * 0.12);
                 }
 
@@ -33,8 +34,8 @@ pub fn signifier_continue_wall(
 
         if let Some(first_pt) = curve.points.get(0) {
             if cursor_ws.distance(*first_pt) < CONTINUE_CURVE_DIST_THRESHOLD {
-                if let Some(next_pt) = curve.points.get(1) {
-                    let dir = (*first_pt - *next_pt).normalize();
+                if curve.points.len() >= 2 {
+                    let dir = -curve.get_tangent_at_distance(0.0);
                     signifier_pos = Some(*first_pt + dir * 0.12);
                 }
 
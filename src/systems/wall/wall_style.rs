@@ -0,0 +1,122 @@
+use bevy_app::EventWriter;
+use bevy_ecs::prelude::*;
+use bevy_input::{keyboard::KeyCode, Input};
+
+use crate::{
+    resources::{events::CurveChangedEvent, history::*, WallManager, WallStyles},
+    systems::mode_manager::ctrl_pressed,
+};
+
+// T cycles the style used for new walls, Ctrl+T reloads the presets file.
+// The selected wall, if there is one, gets the new style too
+pub fn wall_style_manager(
+    keys: Res<Input<KeyCode>>,
+    mut wall_styles: ResMut<WallStyles>,
+    mut wall_manager: ResMut<WallManager>,
+    mut history: ResMut<CommandHistory>,
+    mut ev_curve_changed: EventWriter<CurveChangedEvent>,
+) {
+    if !keys.just_pressed(KeyCode::T) {
+        return;
+    }
+
+    if ctrl_pressed(&keys) {
+        wall_styles.reload();
+    } else {
+        wall_styles.cycle();
+    }
+
+    log::info!("Wall style: {}", wall_styles.active_name());
+
+    let index = match wall_manager.selected {
+        Some(index) => index,
+        None => return,
+    };
+
+    let wall = wall_manager.get_mut(index).unwrap();
+    let before = WallState::of(wall);
+    wall.style = wall_styles.active_style();
+
+    history.push(Stroke {
+        walls: vec![WallChange {
+            index,
+            before: Some(before),
+            after: Some(WallState::of(wall)),
+        }],
+        paths: Vec::new(),
+    });
+
+    ev_curve_changed.send(CurveChangedEvent { curve_index: index });
+}
+
+// K gives the selected wall the next arch shape, the rest of its arch style is kept
+pub fn arch_shape_manager(
+    keys: Res<Input<KeyCode>>,
+    mut wall_manager: ResMut<WallManager>,
+    mut history: ResMut<CommandHistory>,
+    mut ev_curve_changed: EventWriter<CurveChangedEvent>,
+) {
+    if !keys.just_pressed(KeyCode::K) {
+        return;
+    }
+
+    let index = match wall_manager.selected {
+        Some(index) => index,
+        None => {
+            log::info!("Select a wall to change the shape of its arches");
+            return;
+        }
+    };
+
+    let wall = wall_manager.get_mut(index).unwrap();
+    let before = WallState::of(wall);
+    wall.style.arch.shape = wall.style.arch.shape.next();
+    log::info!("Arch shape: {}", wall.style.arch.shape.name());
+
+    history.push(Stroke {
+        walls: vec![WallChange {
+            index,
+            before: Some(before),
+            after: Some(WallState::of(wall)),
+        }],
+        paths: Vec::new(),
+    });
+
+    ev_curve_changed.send(CurveChangedEvent { curve_index: index });
+}
+
+// B builds the selected wall the next way (bricks, dry stone, fences, hedge, ruin), the rest of its style is kept
+pub fn construction_manager(
+    keys: Res<Input<KeyCode>>,
+    mut wall_manager: ResMut<WallManager>,
+    mut history: ResMut<CommandHistory>,
+    mut ev_curve_changed: EventWriter<CurveChangedEvent>,
+) {
+    if !keys.just_pressed(KeyCode::B) {
+        return;
+    }
+
+    let index = match wall_manager.selected {
+        Some(index) => index,
+        None => {
+            log::info!("Select a wall to change how it's built");
+            return;
+        }
+    };
+
+    let wall = wall_manager.get_mut(index).unwrap();
+    let before = WallState::of(wall);
+    wall.style.construction = wall.style.construction.next();
+    log::info!("Construction: {}", wall.style.construction.name());
+
+    history.push(Stroke {
+        walls: vec![WallChange {
+            index,
+            before: Some(before),
+            after: Some(WallState::of(wall)),
+        }],
+        paths: Vec::new(),
+    });
+
+    ev_curve_changed.send(CurveChangedEvent { curve_index: index });
+}
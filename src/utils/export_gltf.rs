@@ -0,0 +1,385 @@
+use std::borrow::Cow;
+
+use glam::Vec3;
+use serde_json::{json, Value};
+
+use crate::{
+    geometry::wall_constructor::Brick,
+    render::mesh::{Mesh, VertexAttributeValues},
+};
+
+// glTF constants
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+// Custom attributes must start with an underscore, see glTF spec 3.7.2.1
+pub const ATTRIBUTE_CURVE_UV_BOUNDS: &str = "_CURVE_UV_BOUNDS";
+
+pub enum GltfExportMode {
+    // One shared brick mesh, every wall is a node instancing it (EXT_mesh_gpu_instancing)
+    Instanced,
+    // All bricks of all walls baked into a single mesh
+    Merged,
+}
+
+// Vertex data of the mesh that gets instanced for every brick
+struct BrickMeshData {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    colors: Vec<[f32; 3]>,
+    indices: Vec<u32>,
+}
+
+impl BrickMeshData {
+    fn from(mesh: &Mesh) -> Result<Self, String> {
+        let positions = match mesh.attributes.get(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float32x3(v)) => v.clone(),
+            _ => return Err("glTF export: brick mesh has no positions".to_string()),
+        };
+
+        let normals = match mesh.attributes.get(Mesh::ATTRIBUTE_NORMAL) {
+            Some(VertexAttributeValues::Float32x3(v)) => v.clone(),
+            _ => vec![[0.0, 1.0, 0.0]; positions.len()],
+        };
+
+        let colors = match mesh.attributes.get(Mesh::ATTRIBUTE_COLOR) {
+            Some(VertexAttributeValues::Float32x3(v)) => v.clone(),
+            Some(VertexAttributeValues::Float32x4(v)) => {
+                v.iter().map(|c| [c[0], c[1], c[2]]).collect()
+            }
+            _ => vec![[1.0; 3]; positions.len()],
+        };
+
+        Ok(Self {
+            positions,
+            normals,
+            colors,
+            indices: mesh.indices.clone(),
+        })
+    }
+}
+
+// Accumulates the BIN chunk, and the buffer views & accessors pointing into it
+struct GlbBuilder {
+    bin: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl GlbBuilder {
+    fn new() -> Self {
+        Self {
+            bin: Vec::new(),
+            buffer_views: Vec::new(),
+            accessors: Vec::new(),
+        }
+    }
+
+    fn push_buffer_view(&mut self, bytes: &[u8], target: Option<u32>) -> usize {
+        // accessors need to be aligned to their component size, which is always 4 bytes here
+        while !self.bin.len().is_multiple_of(4) {
+            self.bin.push(0);
+        }
+
+        let mut view = json!({
+            "buffer": 0,
+            "byteOffset": self.bin.len(),
+            "byteLength": bytes.len(),
+        });
+        if let Some(target) = target {
+            view["target"] = json!(target);
+        }
+
+        self.bin.extend_from_slice(bytes);
+        self.buffer_views.push(view);
+        self.buffer_views.len() - 1
+    }
+
+    // `N` is the number of components, i.e. 3 for "VEC3"
+    fn push_f32<const N: usize>(&mut self, data: &[[f32; N]], target: Option<u32>) -> usize {
+        let bytes: Vec<u8> = data
+            .iter()
+            .flatten()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let view = self.push_buffer_view(&bytes, target);
+
+        let mut accessor = json!({
+            "bufferView": view,
+            "componentType": FLOAT,
+            "count": data.len(),
+            "type": match N {
+                1 => "SCALAR",
+                2 => "VEC2",
+                3 => "VEC3",
+                4 => "VEC4",
+                _ => unreachable!(),
+            },
+        });
+
+        // POSITION accessors are required to have bounds
+        if N == 3 {
+            let mut min = [f32::MAX; N];
+            let mut max = [f32::MIN; N];
+            for v in data {
+                for i in 0..N {
+                    min[i] = min[i].min(v[i]);
+                    max[i] = max[i].max(v[i]);
+                }
+            }
+            accessor["min"] = json!(min.to_vec());
+            accessor["max"] = json!(max.to_vec());
+        }
+
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn push_indices(&mut self, indices: &[u32]) -> usize {
+        let bytes: Vec<u8> = indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+        let view = self.push_buffer_view(&bytes, Some(ELEMENT_ARRAY_BUFFER));
+
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": UNSIGNED_INT,
+            "count": indices.len(),
+            "type": "SCALAR",
+        }));
+        self.accessors.len() - 1
+    }
+}
+
+// Deterministic per-brick shade, so that re-exporting the same scene gives the same colors
+fn brick_color(color_seed: usize, brick_index: usize, mesh_color: [f32; 3]) -> [f32; 3] {
+    let rng = fastrand::Rng::with_seed(((color_seed as u64) << 32) | brick_index as u64);
+    let shade = 0.75 + rng.f32() * 0.25;
+    [
+        mesh_color[0] * shade,
+        mesh_color[1] * shade,
+        mesh_color[2] * shade,
+    ]
+}
+
+fn brick_uv_bounds(brick: &Brick) -> [f32; 4] {
+    let min = brick.pivot_uv - brick.bounds_uv / 2.0;
+    let max = brick.pivot_uv + brick.bounds_uv / 2.0;
+    [min.x, min.y, max.x, max.y]
+}
+
+// Bricks that are exported as one node in instanced mode, `color_seed` picks their shades
+struct BrickGroup<'a> {
+    name: String,
+    color_seed: usize,
+    bricks: &'a [Brick],
+}
+
+// `walls` is a list of (wall index, bricks of that wall), `arches` are the bricks of all arches
+pub fn export_walls_as_glb(
+    path: &str,
+    brick_mesh: &Mesh,
+    walls: &[(usize, Vec<Brick>)],
+    arches: &[Brick],
+    mode: GltfExportMode,
+) -> Result<(), String> {
+    let brick_mesh = BrickMeshData::from(brick_mesh)?;
+
+    let mut groups: Vec<BrickGroup> = walls
+        .iter()
+        .map(|(wall_index, bricks)| BrickGroup {
+            name: format!("wall_{}", wall_index),
+            color_seed: *wall_index,
+            bricks,
+        })
+        .collect();
+    if !arches.is_empty() {
+        groups.push(BrickGroup {
+            name: String::from("arches"),
+            // wall indices never get this high
+            color_seed: usize::MAX,
+            bricks: arches,
+        });
+    }
+
+    let mut builder = GlbBuilder::new();
+    let (meshes, nodes, extensions_used) = match mode {
+        GltfExportMode::Instanced => export_instanced(&mut builder, &brick_mesh, &groups),
+        GltfExportMode::Merged => export_merged(&mut builder, &brick_mesh, &groups),
+    };
+
+    let mut root = json!({
+        "asset": { "version": "2.0", "generator": "country-slice" },
+        "scene": 0,
+        "scenes": [{ "nodes": (0..nodes.len()).collect::<Vec<_>>() }],
+        "nodes": nodes,
+        "meshes": meshes,
+        "accessors": builder.accessors,
+        "bufferViews": builder.buffer_views,
+        "buffers": [{ "byteLength": builder.bin.len() }],
+    });
+    if !extensions_used.is_empty() {
+        root["extensionsUsed"] = json!(extensions_used);
+        root["extensionsRequired"] = json!(extensions_used);
+    }
+
+    let json = serde_json::to_vec(&root)
+        .map_err(|err| format!("glTF export: couldn't serialize {}: {}", path, err))?;
+
+    let glb = gltf::Glb {
+        header: gltf::binary::Header {
+            magic: *b"glTF",
+            version: 2,
+            length: 0, // computed by `to_writer`
+        },
+        json: Cow::Owned(json),
+        bin: Some(Cow::Owned(builder.bin)),
+    };
+
+    let file = std::fs::File::create(path)
+        .map_err(|err| format!("glTF export: couldn't create {}: {}", path, err))?;
+    glb.to_writer(std::io::BufWriter::new(file))
+        .map_err(|err| format!("glTF export: couldn't write {}: {:?}", path, err))
+}
+
+fn export_instanced(
+    builder: &mut GlbBuilder,
+    brick_mesh: &BrickMeshData,
+    groups: &[BrickGroup],
+) -> (Vec<Value>, Vec<Value>, Vec<&'static str>) {
+    let positions = builder.push_f32(&brick_mesh.positions, Some(ARRAY_BUFFER));
+    let normals = builder.push_f32(&brick_mesh.normals, Some(ARRAY_BUFFER));
+    let indices = builder.push_indices(&brick_mesh.indices);
+
+    let mesh = json!({
+        "name": "brick",
+        "primitives": [{
+            "attributes": { "POSITION": positions, "NORMAL": normals },
+            "indices": indices,
+        }],
+    });
+
+    // Per-instance attributes, the vertex color of the brick mesh is averaged into a single color per brick
+    let mesh_color = average_color(&brick_mesh.colors);
+
+    let mut nodes = Vec::new();
+    for group in groups {
+        let bricks = group.bricks;
+        if bricks.is_empty() {
+            continue;
+        }
+
+        let translations: Vec<[f32; 3]> = bricks
+            .iter()
+            .map(|b| b.transform.translation.to_array())
+            .collect();
+        let rotations: Vec<[f32; 4]> = bricks
+            .iter()
+            .map(|b| b.transform.rotation.normalize().into())
+            .collect();
+        let scales: Vec<[f32; 3]> = bricks
+            .iter()
+            .map(|b| b.transform.scale.to_array())
+            .collect();
+        let colors: Vec<[f32; 3]> = (0..bricks.len())
+            .map(|i| brick_color(group.color_seed, i, mesh_color))
+            .collect();
+        let uv_bounds: Vec<[f32; 4]> = bricks.iter().map(brick_uv_bounds).collect();
+
+        nodes.push(json!({
+            "name": group.name,
+            "mesh": 0,
+            "extensions": {
+                "EXT_mesh_gpu_instancing": {
+                    "attributes": {
+                        "TRANSLATION": builder.push_f32(&translations, None),
+                        "ROTATION": builder.push_f32(&rotations, None),
+                        "SCALE": builder.push_f32(&scales, None),
+                        "_COLOR": builder.push_f32(&colors, None),
+                        ATTRIBUTE_CURVE_UV_BOUNDS: builder.push_f32(&uv_bounds, None),
+                    }
+                }
+            },
+        }));
+    }
+
+    (vec![mesh], nodes, vec!["EXT_mesh_gpu_instancing"])
+}
+
+fn export_merged(
+    builder: &mut GlbBuilder,
+    brick_mesh: &BrickMeshData,
+    groups: &[BrickGroup],
+) -> (Vec<Value>, Vec<Value>, Vec<&'static str>) {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut colors: Vec<[f32; 3]> = Vec::new();
+    let mut uv_bounds: Vec<[f32; 4]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    for group in groups {
+        for (brick_index, brick) in group.bricks.iter().enumerate() {
+            let transform = brick.transform.compute_matrix();
+            let rotation = brick.transform.rotation;
+            let inv_scale = Vec3::ONE / brick.transform.scale;
+            let brick_uv = brick_uv_bounds(brick);
+
+            indices.extend(
+                brick_mesh
+                    .indices
+                    .iter()
+                    .map(|i| i + positions.len() as u32),
+            );
+
+            for (i, p) in brick_mesh.positions.iter().enumerate() {
+                positions.push(transform.transform_point3(Vec3::from(*p)).to_array());
+                // normals need the inverse-transpose, which for TRS is R * S^-1
+                normals.push(
+                    (rotation * (Vec3::from(brick_mesh.normals[i]) * inv_scale))
+                        .normalize()
+                        .to_array(),
+                );
+                colors.push(brick_color(
+                    group.color_seed,
+                    brick_index,
+                    brick_mesh.colors[i],
+                ));
+                uv_bounds.push(brick_uv);
+            }
+        }
+    }
+
+    if positions.is_empty() {
+        return (Vec::new(), Vec::new(), Vec::new());
+    }
+
+    let mesh = json!({
+        "name": "walls",
+        "primitives": [{
+            "attributes": {
+                "POSITION": builder.push_f32(&positions, Some(ARRAY_BUFFER)),
+                "NORMAL": builder.push_f32(&normals, Some(ARRAY_BUFFER)),
+                "COLOR_0": builder.push_f32(&colors, Some(ARRAY_BUFFER)),
+                ATTRIBUTE_CURVE_UV_BOUNDS: builder.push_f32(&uv_bounds, Some(ARRAY_BUFFER)),
+            },
+            "indices": builder.push_indices(&indices),
+        }],
+    });
+
+    (
+        vec![mesh],
+        vec![json!({ "name": "walls", "mesh": 0 })],
+        Vec::new(),
+    )
+}
+
+fn average_color(colors: &[[f32; 3]]) -> [f32; 3] {
+    if colors.is_empty() {
+        return [1.0; 3];
+    }
+
+    let sum = colors
+        .iter()
+        .fold(Vec3::ZERO, |acc, c| acc + Vec3::from(*c));
+    (sum / colors.len() as f32).to_array()
+}
@@ -0,0 +1,211 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+// 16-bit PNGs map 0..65535 to heights from `PNG_HEIGHT_RANGE.0` to `PNG_HEIGHT_RANGE.1` meters
+pub const PNG_HEIGHT_RANGE: (f32, f32) = (-16.0, 16.0);
+
+// Grid of heights in meters, row by row. Rows go along +Z, columns along +X.
+// Saved and loaded either as a grayscale PNG (`.png`), or as raw little-endian f32 (`.r32`, `.raw`), which has to be square
+#[derive(Clone, Debug, PartialEq)]
+pub struct Heightmap {
+    pub dims: (usize, usize),
+    pub heights: Vec<f32>,
+}
+
+impl Heightmap {
+    pub fn new(dims: (usize, usize), heights: Vec<f32>) -> Self {
+        assert_eq!(
+            dims.0 * dims.1,
+            heights.len(),
+            "Heightmap: dimensions don't match the number of heights"
+        );
+        Self { dims, heights }
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        if is_png(path) {
+            Self::load_png(path)
+        } else {
+            Self::load_raw(path)
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        if is_png(path) {
+            self.save_png(path)
+        } else {
+            self.save_raw(path)
+        }
+    }
+
+    // Bilinear, `u` and `v` go from the first to the last pixel, so resampling to the same dimensions keeps the heights
+    pub fn sample(&self, u: f32, v: f32) -> f32 {
+        let x = u.clamp(0.0, 1.0) * (self.dims.0 - 1) as f32;
+        let y = v.clamp(0.0, 1.0) * (self.dims.1 - 1) as f32;
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(self.dims.0 - 1), (y0 + 1).min(self.dims.1 - 1));
+        let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+
+        let h = |x: usize, y: usize| self.heights[y * self.dims.0 + x];
+        let top = h(x0, y0) + (h(x1, y0) - h(x0, y0)) * tx;
+        let bottom = h(x0, y1) + (h(x1, y1) - h(x0, y1)) * tx;
+        top + (bottom - top) * ty
+    }
+
+    fn load_png(path: &str) -> Result<Self, String> {
+        let file =
+            File::open(path).map_err(|e| format!("Heightmap: can't open {}: {}", path, e))?;
+        let mut decoder = png::Decoder::new(file);
+        // keep all 16 bits
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let (info, mut reader) = decoder
+            .read_info()
+            .map_err(|e| format!("Heightmap: can't read {}: {}", path, e))?;
+
+        if info.color_type != png::ColorType::Grayscale {
+            return Err(format!(
+                "Heightmap: {} has to be grayscale, it's {:?}",
+                path, info.color_type
+            ));
+        }
+
+        let mut buffer = vec![0; info.buffer_size()];
+        reader
+            .next_frame(&mut buffer)
+            .map_err(|e| format!("Heightmap: can't read {}: {}", path, e))?;
+
+        let (width, height) = (info.width as usize, info.height as usize);
+        let (low, high) = PNG_HEIGHT_RANGE;
+        let values: Vec<f32> = match info.bit_depth {
+            png::BitDepth::Sixteen => buffer
+                .chunks_exact(2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]) as f32 / u16::MAX as f32)
+                .collect(),
+            png::BitDepth::Eight => buffer.iter().map(|b| *b as f32 / u8::MAX as f32).collect(),
+            depth => {
+                return Err(format!(
+                    "Heightmap: {} has an unsupported bit depth {:?}",
+                    path, depth
+                ))
+            }
+        };
+
+        // rows are padded to whole bytes, which doesn't happen for 8 and 16 bits
+        let heights = values
+            .into_iter()
+            .take(width * height)
+            .map(|v| low + v * (high - low))
+            .collect();
+
+        Ok(Self::new((width, height), heights))
+    }
+
+    fn save_png(&self, path: &str) -> Result<(), String> {
+        let file =
+            File::create(path).map_err(|e| format!("Heightmap: can't create {}: {}", path, e))?;
+        let mut encoder =
+            png::Encoder::new(BufWriter::new(file), self.dims.0 as u32, self.dims.1 as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Sixteen);
+
+        let (low, high) = PNG_HEIGHT_RANGE;
+        if self.heights.iter().any(|h| *h < low || *h > high) {
+            log::warn!(
+                "Heightmap: heights outside of {}..{} m are clamped in {}",
+                low,
+                high,
+                path
+            );
+        }
+
+        let data: Vec<u8> = self
+            .heights
+            .iter()
+            .flat_map(|h| {
+                let v = ((h - low) / (high - low)).clamp(0.0, 1.0);
+                ((v * u16::MAX as f32).round() as u16).to_be_bytes()
+            })
+            .collect();
+
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&data))
+            .map_err(|e| format!("Heightmap: can't write {}: {}", path, e))
+    }
+
+    fn load_raw(path: &str) -> Result<Self, String> {
+        let bytes =
+            std::fs::read(path).map_err(|e| format!("Heightmap: can't read {}: {}", path, e))?;
+        if bytes.len() % 4 != 0 {
+            return Err(format!("Heightmap: {} isn't made of f32s", path));
+        }
+
+        let heights: Vec<f32> = bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+
+        // raw files don't store their dimensions
+        let side = (heights.len() as f64).sqrt().round() as usize;
+        if side < 2 || side * side != heights.len() {
+            return Err(format!(
+                "Heightmap: {} has {} heights, raw heightmaps have to be square",
+                path,
+                heights.len()
+            ));
+        }
+        if let Some(i) = heights.iter().position(|h| !h.is_finite()) {
+            return Err(format!(
+                "Heightmap: {} has a height of {} at {}",
+                path, heights[i], i
+            ));
+        }
+
+        Ok(Self::new((side, side), heights))
+    }
+
+    fn save_raw(&self, path: &str) -> Result<(), String> {
+        if self.dims.0 != self.dims.1 {
+            return Err(format!(
+                "Heightmap: can't write {}, raw heightmaps have to be square but it's {}x{}",
+                path, self.dims.0, self.dims.1
+            ));
+        }
+
+        let bytes: Vec<u8> = self.heights.iter().flat_map(|h| h.to_le_bytes()).collect();
+        std::fs::write(path, bytes).map_err(|e| format!("Heightmap: can't write {}: {}", path, e))
+    }
+}
+
+fn is_png(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("png"))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_heightmaps_are_square_and_finite() {
+        let path = std::env::temp_dir().join("country_slice_heightmap_test.r32");
+        let path = path.to_str().unwrap();
+
+        let strip = Heightmap::new((4, 1), vec![0.0; 4]);
+        assert!(strip.save(path).is_err());
+
+        let square = Heightmap::new((2, 2), vec![0.5, -1.0, 2.25, 8.0]);
+        square.save(path).unwrap();
+        assert_eq!(Heightmap::load(path), Ok(square));
+
+        let bytes: Vec<u8> = [1.0, f32::NAN, 0.0, 0.0]
+            .iter()
+            .flat_map(|h: &f32| h.to_le_bytes())
+            .collect();
+        std::fs::write(path, bytes).unwrap();
+        assert!(Heightmap::load(path).is_err());
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -1,6 +1,7 @@
-pub mod load_gltf;
 pub mod load_json;
 
+pub use country_slice::utils::{export_gltf, heightmap, load_gltf, scene_file};
+
 pub mod custom_macro {
 
     macro_rules! log_if_error {
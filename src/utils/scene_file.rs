@@ -0,0 +1,304 @@
+use glam::{Vec2, Vec3};
+use serde_json::{json, Value};
+
+use crate::geometry::{
+    chunks::ChunkCoord, terrain_settings::TerrainSettings, wall_opening::WallOpening,
+    wall_style::WallStyle,
+};
+
+// Bump this whenever the layout below changes, and keep `from_json` able to read the older versions
+// v2: walls have a `style`
+// v3: walls have a `seed`
+// v4: `paths` are curves with a width, instead of the painted `path_mask`
+// v5: terrain stores all generator `settings`, instead of `amp` and `seed`
+// v6: walls have `openings`
+// v7: terrain stores the heights of the `tiles` that were sculpted or imported
+// v8: walls can be a `spline`, their `points` are then its control points
+// v9: walls have a `start_offset` their bricks are counted from
+pub const SCENE_FILE_VERSION: u64 = 9;
+
+pub struct SceneWall {
+    pub points: Vec<Vec3>,
+    // walls drawn as splines are built along the spline through `points`, see `SplineCurve`
+    pub spline: bool,
+    pub style: WallStyle,
+    pub seed: u64,
+    // see `BrickLayout::start_offset`
+    pub start_offset: f32,
+    pub openings: Vec<WallOpening>,
+}
+
+pub struct SceneTerrain {
+    pub settings: TerrainSettings,
+    pub offset: Vec2,
+    // heights of the chunks that were edited, row by row, the others are generated from the settings
+    pub tiles: Vec<(ChunkCoord, Vec<f32>)>,
+}
+
+// A path is its centerline and its width
+pub struct ScenePath {
+    pub points: Vec<Vec3>,
+    pub width: f32,
+}
+
+pub struct SceneFile {
+    pub walls: Vec<SceneWall>,
+    pub paths: Vec<ScenePath>,
+    pub terrain: SceneTerrain,
+}
+
+impl SceneWall {
+    // Also used by `country-slice-gen`, which only needs the walls out of a scene file
+    pub fn list_from_json(v: &Value) -> Result<Vec<Self>, String> {
+        let mut walls = Vec::new();
+        for (i, wall) in v
+            .as_array()
+            .ok_or("Scene file: missing `walls`")?
+            .iter()
+            .enumerate()
+        {
+            let points = wall["points"]
+                .as_array()
+                .ok_or("Scene file: wall has no `points`")?
+                .iter()
+                .map(as_vec3)
+                .collect::<Result<Vec<_>, _>>()?;
+            // v1 scenes (and hand-written inputs) have no style
+            let style = match wall.get("style") {
+                Some(style) => WallStyle::from_json(style)?,
+                None => WallStyle::default(),
+            };
+            // walls without a seed still shouldn't all look the same
+            let seed = match wall.get("seed") {
+                Some(seed) => seed
+                    .as_u64()
+                    .ok_or("Scene file: wall has no valid `seed`")?,
+                None => i as u64,
+            };
+            let openings = match wall.get("openings") {
+                Some(openings) => openings
+                    .as_array()
+                    .ok_or("Scene file: wall `openings` is not a list")?
+                    .iter()
+                    .map(WallOpening::from_json)
+                    .collect::<Result<Vec<_>, _>>()?,
+                None => Vec::new(),
+            };
+            let spline = match wall.get("spline") {
+                Some(spline) => spline
+                    .as_bool()
+                    .ok_or("Scene file: wall `spline` is not a boolean")?,
+                None => false,
+            };
+            let start_offset = match wall.get("start_offset") {
+                Some(start_offset) => as_f32(start_offset)?,
+                None => 0.0,
+            };
+            walls.push(SceneWall {
+                points,
+                spline,
+                style,
+                seed,
+                start_offset,
+                openings,
+            });
+        }
+        Ok(walls)
+    }
+}
+
+impl ScenePath {
+    fn list_from_json(v: &Value) -> Result<Vec<Self>, String> {
+        let mut paths = Vec::new();
+        for path in v.as_array().ok_or("Scene file: missing `paths`")? {
+            let points = path["points"]
+                .as_array()
+                .ok_or("Scene file: path has no `points`")?
+                .iter()
+                .map(as_vec3)
+                .collect::<Result<Vec<_>, _>>()?;
+            let width = as_f32(&path["width"])?;
+            paths.push(ScenePath { points, width });
+        }
+        Ok(paths)
+    }
+}
+
+impl SceneFile {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "version": SCENE_FILE_VERSION,
+            "walls": self.walls.iter().map(|w| json!({
+                "points": w.points.iter().map(|p| p.to_array()).collect::<Vec<_>>(),
+                "style": w.style.to_json(),
+                "seed": w.seed,
+                "start_offset": w.start_offset,
+                "openings": w.openings.iter().map(|o| o.to_json()).collect::<Vec<_>>(),
+                "spline": w.spline,
+            })).collect::<Vec<_>>(),
+            "paths": self.paths.iter().map(|p| json!({
+                "points": p.points.iter().map(|p| p.to_array()).collect::<Vec<_>>(),
+                "width": p.width,
+            })).collect::<Vec<_>>(),
+            "terrain": {
+                "settings": self.terrain.settings.to_json(),
+                "offset": self.terrain.offset.to_array(),
+                "tiles": self.terrain.tiles.iter().map(|(coord, heights)| json!({
+                    "chunk": [coord.x, coord.z],
+                    "heights": heights.iter().map(|h| shortest_f64(*h)).collect::<Vec<_>>(),
+                })).collect::<Vec<_>>(),
+            },
+        })
+    }
+
+    pub fn from_json(v: &Value) -> Result<Self, String> {
+        let version = v["version"]
+            .as_u64()
+            .ok_or("Scene file: missing `version`")?;
+        if version > SCENE_FILE_VERSION {
+            return Err(format!(
+                "Scene file: version {} is newer than the supported version {}",
+                version, SCENE_FILE_VERSION
+            ));
+        }
+
+        let walls = SceneWall::list_from_json(&v["walls"])?;
+
+        // painted path masks can't be turned into curves
+        let paths = if version < 4 {
+            if v.get("path_mask").is_some() {
+                log::warn!(
+                    "Scene file: version {} stores paths as a painted mask, they are left out",
+                    version
+                );
+            }
+            Vec::new()
+        } else {
+            ScenePath::list_from_json(&v["paths"])?
+        };
+
+        let terrain = &v["terrain"];
+        // older scenes only changed the amplitude and the seed of the default settings
+        let settings = if version < 5 {
+            TerrainSettings {
+                amplitude: as_f32(&terrain["amp"])?,
+                seed: terrain["seed"]
+                    .as_u64()
+                    .ok_or("Scene file: terrain has no valid `seed`")?,
+                ..TerrainSettings::default()
+            }
+        } else {
+            TerrainSettings::from_json(&terrain["settings"])?
+        };
+        // edited heights were lost on save before
+        let tiles = if version < 7 {
+            Vec::new()
+        } else {
+            terrain_tiles_from_json(&terrain["tiles"])?
+        };
+        let terrain = SceneTerrain {
+            settings,
+            offset: as_vec2(&terrain["offset"])?,
+            tiles,
+        };
+
+        Ok(Self {
+            walls,
+            paths,
+            terrain,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let data = serde_json::to_string(&self.to_json())
+            .map_err(|err| format!("Scene file: couldn't serialize {}: {}", path, err))?;
+        std::fs::write(path, data)
+            .map_err(|err| format!("Scene file: couldn't write {}: {}", path, err))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|err| format!("Scene file: couldn't read {}: {}", path, err))?;
+        let v: Value = serde_json::from_str(&data)
+            .map_err(|err| format!("Scene file: couldn't parse {}: {}", path, err))?;
+        Self::from_json(&v)
+    }
+}
+
+fn terrain_tiles_from_json(v: &Value) -> Result<Vec<(ChunkCoord, Vec<f32>)>, String> {
+    let mut tiles = Vec::new();
+    for tile in v.as_array().ok_or("Scene file: missing terrain `tiles`")? {
+        let coord = as_chunk(&tile["chunk"])?;
+        let heights = tile["heights"]
+            .as_array()
+            .ok_or("Scene file: terrain tile has no `heights`")?
+            .iter()
+            .map(as_f32)
+            .collect::<Result<Vec<_>, _>>()?;
+        tiles.push((coord, heights));
+    }
+    Ok(tiles)
+}
+
+// The shortest decimal that reads back as `v`, `f64::from` would write out every digit of the f32
+fn shortest_f64(v: f32) -> f64 {
+    v.to_string().parse().unwrap_or_else(|_| f64::from(v))
+}
+
+fn as_f32(v: &Value) -> Result<f32, String> {
+    v.as_f64()
+        .map(|v| v as f32)
+        .ok_or(format!("Scene file: expected a number, got {}", v))
+}
+
+fn as_vec2(v: &Value) -> Result<Vec2, String> {
+    match v.as_array().map(|a| a.as_slice()) {
+        Some([x, y]) => Ok(Vec2::new(as_f32(x)?, as_f32(y)?)),
+        _ => Err(format!("Scene file: expected [x, y], got {}", v)),
+    }
+}
+
+fn as_chunk(v: &Value) -> Result<ChunkCoord, String> {
+    match v.as_array().map(|a| a.as_slice()) {
+        Some([x, z]) => match (x.as_i64(), z.as_i64()) {
+            (Some(x), Some(z)) => Ok(ChunkCoord::new(x as i32, z as i32)),
+            _ => Err(format!("Scene file: expected a chunk [x, z], got {}", v)),
+        },
+        _ => Err(format!("Scene file: expected a chunk [x, z], got {}", v)),
+    }
+}
+
+fn as_vec3(v: &Value) -> Result<Vec3, String> {
+    match v.as_array().map(|a| a.as_slice()) {
+        Some([x, y, z]) => Ok(Vec3::new(as_f32(x)?, as_f32(y)?, as_f32(z)?)),
+        _ => Err(format!("Scene file: expected [x, y, z], got {}", v)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edited_terrain_round_trips() {
+        let heights = vec![0.1, -3.3333333, 1.0e-7, 12.75];
+        let scene = SceneFile {
+            walls: Vec::new(),
+            paths: Vec::new(),
+            terrain: SceneTerrain {
+                settings: TerrainSettings::default(),
+                offset: Vec2::new(1.5, -2.0),
+                tiles: vec![(ChunkCoord::new(-1, 2), heights.clone())],
+            },
+        };
+
+        let json = serde_json::to_string(&scene.to_json()).unwrap();
+        let loaded = SceneFile::from_json(&serde_json::from_str(&json).unwrap()).unwrap();
+        assert_eq!(
+            loaded.terrain.tiles,
+            vec![(ChunkCoord::new(-1, 2), heights)]
+        );
+        // the heights are written as short as they can be
+        assert!(json.contains("[0.1,-3.3333333,1e-7,12.75]"), "{}", json);
+    }
+}
@@ -107,15 +107,40 @@ pub fn process_window_events(
                     VirtualKeyCode::Down => Some(bevy_input::keyboard::KeyCode::Down),
                     VirtualKeyCode::Space => Some(bevy_input::keyboard::KeyCode::Space),
                     VirtualKeyCode::Q => Some(bevy_input::keyboard::KeyCode::Q),
+                    VirtualKeyCode::A => Some(bevy_input::keyboard::KeyCode::A),
+                    VirtualKeyCode::B => Some(bevy_input::keyboard::KeyCode::B),
+                    VirtualKeyCode::C => Some(bevy_input::keyboard::KeyCode::C),
+                    VirtualKeyCode::D => Some(bevy_input::keyboard::KeyCode::D),
                     VirtualKeyCode::E => Some(bevy_input::keyboard::KeyCode::E),
+                    VirtualKeyCode::S => Some(bevy_input::keyboard::KeyCode::S),
+                    VirtualKeyCode::L => Some(bevy_input::keyboard::KeyCode::L),
+                    VirtualKeyCode::G => Some(bevy_input::keyboard::KeyCode::G),
+                    VirtualKeyCode::H => Some(bevy_input::keyboard::KeyCode::H),
+                    VirtualKeyCode::I => Some(bevy_input::keyboard::KeyCode::I),
+                    VirtualKeyCode::K => Some(bevy_input::keyboard::KeyCode::K),
+                    VirtualKeyCode::M => Some(bevy_input::keyboard::KeyCode::M),
+                    VirtualKeyCode::O => Some(bevy_input::keyboard::KeyCode::O),
+                    VirtualKeyCode::P => Some(bevy_input::keyboard::KeyCode::P),
+                    VirtualKeyCode::R => Some(bevy_input::keyboard::KeyCode::R),
+                    VirtualKeyCode::T => Some(bevy_input::keyboard::KeyCode::T),
+                    VirtualKeyCode::U => Some(bevy_input::keyboard::KeyCode::U),
+                    VirtualKeyCode::Z => Some(bevy_input::keyboard::KeyCode::Z),
+                    VirtualKeyCode::Y => Some(bevy_input::keyboard::KeyCode::Y),
+                    VirtualKeyCode::LControl => Some(bevy_input::keyboard::KeyCode::LControl),
+                    VirtualKeyCode::RControl => Some(bevy_input::keyboard::KeyCode::RControl),
                     VirtualKeyCode::Escape => Some(bevy_input::keyboard::KeyCode::Escape),
                     VirtualKeyCode::Back => Some(bevy_input::keyboard::KeyCode::Back),
+                    VirtualKeyCode::Delete => Some(bevy_input::keyboard::KeyCode::Delete),
+                    VirtualKeyCode::Key0 => Some(bevy_input::keyboard::KeyCode::Key0),
                     VirtualKeyCode::Key1 => Some(bevy_input::keyboard::KeyCode::Key1),
                     VirtualKeyCode::Key2 => Some(bevy_input::keyboard::KeyCode::Key2),
                     VirtualKeyCode::Key3 => Some(bevy_input::keyboard::KeyCode::Key3),
                     VirtualKeyCode::Key4 => Some(bevy_input::keyboard::KeyCode::Key4),
                     VirtualKeyCode::Key5 => Some(bevy_input::keyboard::KeyCode::Key5),
                     VirtualKeyCode::Key6 => Some(bevy_input::keyboard::KeyCode::Key6),
+                    VirtualKeyCode::Key7 => Some(bevy_input::keyboard::KeyCode::Key7),
+                    VirtualKeyCode::Key8 => Some(bevy_input::keyboard::KeyCode::Key8),
+                    VirtualKeyCode::Key9 => Some(bevy_input::keyboard::KeyCode::Key9),
                     _ => None,
                 };
 